@@ -0,0 +1,84 @@
+//! Token and SOL amounts as people type them: `1.5` rather than `1500000`
+
+/// xUSDC, like USDC, has 6 decimals
+pub const XUSDC_DECIMALS: u8 = 6;
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Parse a decimal amount into base units
+pub fn parse(amount: &str, decimals: u8) -> Result<u64, String> {
+    let invalid = || format!("invalid amount {amount:?}");
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    if fraction.len() > decimals as usize {
+        return Err(format!("{amount} has more than {decimals} decimals"));
+    }
+    let scale = 10u64.pow(decimals as u32);
+    let whole = match whole {
+        "" => 0,
+        whole => whole.parse::<u64>().map_err(|_| invalid())?,
+    };
+    let fraction = match fraction {
+        "" => 0,
+        fraction => {
+            fraction.parse::<u64>().map_err(|_| invalid())?
+                * 10u64.pow((decimals as usize - fraction.len()) as u32)
+        }
+    };
+    whole
+        .checked_mul(scale)
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or_else(|| format!("{amount} is too large"))
+}
+
+pub fn parse_xusdc(amount: &str) -> Result<u64, String> {
+    parse(amount, XUSDC_DECIMALS)
+}
+
+/// SOL, in lamports
+pub fn parse_sol(amount: &str) -> Result<u64, String> {
+    parse(amount, SOL_DECIMALS)
+}
+
+/// Format base units as a decimal amount, without trailing zeros
+pub fn format(amount: u64, decimals: u8) -> String {
+    let scale = 10u64.pow(decimals as u32);
+    let fraction = format!("{:0width$}", amount % scale, width = decimals as usize);
+    match fraction.trim_end_matches('0') {
+        "" => (amount / scale).to_string(),
+        fraction => format!("{}.{fraction}", amount / scale),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("1", XUSDC_DECIMALS), Ok(1_000_000));
+        assert_eq!(parse("1.5", XUSDC_DECIMALS), Ok(1_500_000));
+        assert_eq!(parse(".000001", XUSDC_DECIMALS), Ok(1));
+        assert_eq!(parse("2.", SOL_DECIMALS), Ok(2_000_000_000));
+        assert!(parse("0.0000001", XUSDC_DECIMALS).is_err());
+        assert!(parse("", XUSDC_DECIMALS).is_err());
+        assert!(parse(".", XUSDC_DECIMALS).is_err());
+        assert!(parse("-1", XUSDC_DECIMALS).is_err());
+        assert!(parse("1e6", XUSDC_DECIMALS).is_err());
+        assert!(parse("18446744073710", XUSDC_DECIMALS).is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(1_500_000, XUSDC_DECIMALS), "1.5");
+        assert_eq!(format(2_000_000, XUSDC_DECIMALS), "2");
+        assert_eq!(format(1, SOL_DECIMALS), "0.000000001");
+        assert_eq!(format(0, XUSDC_DECIMALS), "0");
+    }
+}
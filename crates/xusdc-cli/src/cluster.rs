@@ -0,0 +1,140 @@
+//! Reading from and sending to a cluster, or simulating against a snapshot of it
+
+use std::error::Error;
+
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::sysvar;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use xusdc_client::instruction::discriminator;
+use xusdc_client::SettleError;
+
+use crate::dry_run;
+
+/// Expand the monikers the Solana CLI accepts for `--url`
+pub fn resolve_url(url: &str) -> String {
+    match url {
+        "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
+        "d" | "devnet" => "https://api.devnet.solana.com",
+        "t" | "testnet" => "https://api.testnet.solana.com",
+        "l" | "localhost" => "http://127.0.0.1:8899",
+        url => url,
+    }
+    .to_string()
+}
+
+pub struct Cluster {
+    pub rpc: RpcClient,
+    /// Simulate transactions against a local snapshot instead of sending them
+    pub dry_run: bool,
+}
+
+impl Cluster {
+    pub fn new(url: &str, dry_run: bool) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(resolve_url(url), CommitmentConfig::confirmed()),
+            dry_run,
+        }
+    }
+
+    pub fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, Box<dyn Error>> {
+        Ok(self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())?
+            .value)
+    }
+
+    pub fn clock(&self) -> Result<Clock, Box<dyn Error>> {
+        let account = self
+            .get_account(&sysvar::clock::ID)?
+            .ok_or("the clock sysvar is missing")?;
+        Ok(bincode::deserialize(&account.data)?)
+    }
+
+    /// Send `instructions` paid for by `payer`, or simulate them with `--dry-run`.
+    /// `signers` are any others the instructions need; a dry run doesn't.
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), Box<dyn Error>> {
+        if self.dry_run {
+            return dry_run::simulate(self, instructions, &payer.pubkey());
+        }
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            self.rpc.get_latest_blockhash()?,
+        );
+        match self.rpc.send_and_confirm_transaction(&transaction) {
+            Ok(signature) => {
+                println!("Signature: {signature}");
+                Ok(())
+            }
+            Err(e) => Err(match e.get_transaction_error() {
+                Some(error) => describe(&error, instructions).into(),
+                None => e.into(),
+            }),
+        }
+    }
+}
+
+/// Name the error when `settle_payment` failed; its codes overlap the program's others
+pub fn describe(error: &TransactionError, instructions: &[Instruction]) -> String {
+    if let TransactionError::InstructionError(index, InstructionError::Custom(code)) = error {
+        let settle_error = instructions
+            .get(*index as usize)
+            .filter(|ix| ix.data.starts_with(&discriminator::SETTLE_PAYMENT))
+            .and_then(|_| SettleError::from_code(*code));
+        if let Some(settle_error) = settle_error {
+            return format!("transaction failed: {settle_error} ({code})");
+        }
+    }
+    format!("transaction failed: {error}")
+}
+
+#[cfg(test)]
+mod tests {
+    use xusdc_client::instruction;
+    use xusdc_client::PaymentAuthorization;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_url() {
+        assert_eq!(resolve_url("devnet"), "https://api.devnet.solana.com");
+        assert_eq!(resolve_url("l"), "http://127.0.0.1:8899");
+        assert_eq!(
+            resolve_url("https://rpc.example.com"),
+            "https://rpc.example.com"
+        );
+    }
+
+    #[test]
+    fn test_describe_settle_errors() {
+        let code = SettleError::PaymentExpired.code();
+        let error = TransactionError::InstructionError(1, InstructionError::Custom(code));
+        let gc = instruction::garbage_collect(&Pubkey::new_unique());
+        let payload = PaymentAuthorization::builder(Pubkey::new_unique(), 1)
+            .sign(&Keypair::new())
+            .unwrap();
+        let settle = instruction::settle_payment(&Pubkey::new_unique(), &payload);
+
+        assert!(describe(&error, &[gc.clone(), settle])
+            .contains(&SettleError::PaymentExpired.to_string()));
+        // The same code from another instruction means something else
+        assert_eq!(
+            describe(&error, &[gc.clone(), gc]),
+            "transaction failed: Error processing Instruction 1: custom program error: 0x1771"
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::error::Error;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use spl_associated_token_account_client::instruction::create_associated_token_account_idempotent;
+use xusdc_client::accounts::{ContributorRentInfo, Nonce, ProgramAccount, NONCE_VERSION};
+use xusdc_client::instruction::{self, Collateral};
+use xusdc_client::{
+    hook, pda, MetadataField, MintMetadata, PaymentAuthorization, SettlePayload,
+    TOKEN_2022_PROGRAM_ID, XUSDC_MINT_KEY,
+};
+
+use crate::amount::{self, SOL_DECIMALS, XUSDC_DECIMALS};
+use crate::cluster::Cluster;
+
+type Result<T = ()> = std::result::Result<T, Box<dyn Error>>;
+
+/// Garbage collections per transaction, each adding three accounts
+pub const GC_BATCH: usize = 10;
+
+fn sol(lamports: u64) -> String {
+    format!("{} SOL", amount::format(lamports, SOL_DECIMALS))
+}
+
+fn xusdc(amount: u64) -> String {
+    format!("{} xUSDC", amount::format(amount, XUSDC_DECIMALS))
+}
+
+/// A 32-byte nonce written as 64 hex digits
+pub fn parse_nonce(hex: &str) -> std::result::Result<[u8; 32], String> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("expected 64 hex digits, got {hex:?}"));
+    }
+    let mut nonce = [0; 32];
+    for (byte, digits) in nonce.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).unwrap(), 16).unwrap();
+    }
+    Ok(nonce)
+}
+
+/// `name`, `symbol` or `uri`
+pub fn parse_metadata_field(field: &str) -> std::result::Result<MetadataField, String> {
+    match field {
+        "name" => Ok(MetadataField::Name),
+        "symbol" => Ok(MetadataField::Symbol),
+        "uri" => Ok(MetadataField::Uri),
+        _ => Err(format!("expected name, symbol or uri, got {field:?}")),
+    }
+}
+
+fn nonce_hex(nonce: &[u8; 32]) -> String {
+    nonce.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// The collateral minted as `mint`, whichever token program owns it
+fn collateral(cluster: &Cluster, mint: &Pubkey) -> Result<Collateral> {
+    let account = cluster
+        .get_account(mint)?
+        .ok_or_else(|| format!("mint {mint} does not exist"))?;
+    Ok(Collateral {
+        mint: *mint,
+        token_program: account.owner,
+    })
+}
+
+pub fn init(
+    cluster: &Cluster,
+    admin: &Keypair,
+    usdc_mint: &Pubkey,
+    mint_keypair: Option<&Keypair>,
+    metadata: &MintMetadata,
+) -> Result {
+    let signers: Vec<&Keypair> = match mint_keypair {
+        Some(mint_keypair) if mint_keypair.pubkey() != XUSDC_MINT_KEY => {
+            return Err(format!(
+                "the xUSDC mint is {XUSDC_MINT_KEY}, not {}",
+                mint_keypair.pubkey()
+            )
+            .into())
+        }
+        Some(mint_keypair) => vec![mint_keypair],
+        None if cluster.dry_run => vec![],
+        None => return Err("--mint-keypair is required to create the xUSDC mint".into()),
+    };
+    cluster.send(
+        &[instruction::initialize(
+            &admin.pubkey(),
+            usdc_mint,
+            metadata,
+        )],
+        admin,
+        &signers,
+    )
+}
+
+pub fn set_metadata(
+    cluster: &Cluster,
+    admin: &Keypair,
+    field: MetadataField,
+    value: &str,
+) -> Result {
+    cluster.send(
+        &[instruction::update_metadata(&admin.pubkey(), field, value)],
+        admin,
+        &[],
+    )
+}
+
+pub fn deposit(cluster: &Cluster, user: &Keypair, mint: &Pubkey, amount: u64) -> Result {
+    let collateral = collateral(cluster, mint)?;
+    let user_key = user.pubkey();
+    println!("Depositing {} for {user_key}", xusdc(amount));
+    cluster.send(
+        &[
+            create_associated_token_account_idempotent(
+                &user_key,
+                &user_key,
+                &XUSDC_MINT_KEY,
+                &TOKEN_2022_PROGRAM_ID,
+            ),
+            instruction::deposit(&user_key, &collateral, amount),
+        ],
+        user,
+        &[],
+    )
+}
+
+pub fn withdraw(cluster: &Cluster, user: &Keypair, mint: &Pubkey, amount: u64) -> Result {
+    let collateral = collateral(cluster, mint)?;
+    let user_key = user.pubkey();
+    println!("Withdrawing {} for {user_key}", xusdc(amount));
+    cluster.send(
+        &[
+            create_associated_token_account_idempotent(
+                &user_key,
+                &user_key,
+                &collateral.mint,
+                &collateral.token_program,
+            ),
+            instruction::withdraw(&user_key, &collateral, amount),
+        ],
+        user,
+        &[],
+    )
+}
+
+pub fn rent_contribute(cluster: &Cluster, user: &Keypair, lamports: u64) -> Result {
+    println!("Contributing {} to the rent pool", sol(lamports));
+    cluster.send(
+        &[instruction::contribute_rent(&user.pubkey(), lamports)],
+        user,
+        &[],
+    )
+}
+
+pub fn rent_withdraw(cluster: &Cluster, user: &Keypair, lamports: u64) -> Result {
+    println!("Withdrawing {} from the rent pool", sol(lamports));
+    cluster.send(
+        &[instruction::withdraw_rent(&user.pubkey(), lamports)],
+        user,
+        &[],
+    )
+}
+
+pub fn rent_status(cluster: &Cluster, user: &Pubkey) -> Result {
+    let pool = pda::rent_pool().0;
+    let pool_lamports = cluster.get_account(&pool)?.map_or(0, |a| a.lamports);
+    println!("Rent pool:     {pool}");
+    println!("Balance:       {}", sol(pool_lamports));
+
+    let contributor = pda::rent_contributor(user).0;
+    println!("Contributor:   {user}");
+    match cluster.get_account(&contributor)? {
+        Some(account) => {
+            let info = ContributorRentInfo::try_from_account(&account.owner, &account.data)?;
+            println!("Contributed:   {}", sol(info.amount));
+            println!("Nonces funded: {}", info.nonces_funded);
+        }
+        None => println!("Contributed:   nothing"),
+    }
+    Ok(())
+}
+
+/// Sign an authorization offline, printing the `SettlePayload` as base64
+pub fn sign_auth(
+    payer: &Keypair,
+    to: Pubkey,
+    amount: u64,
+    ttl: Duration,
+    nonce: Option<[u8; 32]>,
+    resource_hash: Option<[u8; 32]>,
+    memo: Option<String>,
+) -> Result<String> {
+    let mut builder = PaymentAuthorization::builder(to, amount).ttl(ttl);
+    if let Some(nonce) = nonce {
+        builder = builder.nonce(nonce);
+    }
+    if let Some(resource_hash) = resource_hash {
+        builder = builder.resource_hash(resource_hash);
+    }
+    if let Some(memo) = memo {
+        builder = builder.memo(memo);
+    }
+    let payload = builder.sign(payer)?;
+    Ok(STANDARD.encode(borsh::to_vec(&payload)?))
+}
+
+pub fn settle(cluster: &Cluster, facilitator: &Keypair, payload: &str) -> Result {
+    let payload: SettlePayload = borsh::from_slice(&STANDARD.decode(payload.trim())?)
+        .map_err(|e| format!("not a SettlePayload: {e}"))?;
+    let payment_auth = &payload.payment_auth;
+    println!(
+        "Settling {} from {} to {}, nonce {}",
+        xusdc(payment_auth.amount),
+        payment_auth.from,
+        payment_auth.to,
+        nonce_hex(&payment_auth.nonce)
+    );
+    let mint = cluster
+        .get_account(&XUSDC_MINT_KEY)?
+        .ok_or_else(|| format!("mint {XUSDC_MINT_KEY} does not exist"))?;
+    let mut settle = instruction::settle_payment(&facilitator.pubkey(), &payload);
+    settle.accounts.extend(hook::mint_transfer_accounts(
+        &mint.data,
+        &payment_auth.from,
+        &payment_auth.to,
+    ));
+    cluster.send(&[settle], facilitator, &[])
+}
+
+/// Close up to `limit` expired nonce accounts, oldest first. With `migrate`, also
+/// rewrite live version 0 nonces in the current layout.
+pub fn gc(cluster: &Cluster, payer: &Keypair, limit: Option<usize>, migrate: bool) -> Result {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            Nonce::DISCRIMINATOR.to_vec(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(solana_account_decoder_client_types::UiAccountEncoding::Base64),
+            commitment: Some(cluster.rpc.commitment()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let now = cluster.clock()?.unix_timestamp;
+    let mut expired = Vec::new();
+    let mut outdated = Vec::new();
+    for (address, account) in cluster
+        .rpc
+        .get_program_accounts_with_config(&xusdc_client::ID, config)?
+    {
+        let Ok(nonce) = Nonce::try_from_account(&account.owner, &account.data) else {
+            continue;
+        };
+        if nonce.expires_at < now {
+            expired.push((nonce.expires_at, address, account.lamports));
+        } else if nonce.version < NONCE_VERSION {
+            outdated.push(address);
+        }
+    }
+    expired.sort();
+    expired.truncate(limit.unwrap_or(usize::MAX));
+    if expired.is_empty() {
+        println!("No expired nonces");
+    } else {
+        let lamports: u64 = expired.iter().map(|(_, _, lamports)| lamports).sum();
+        println!(
+            "Closing {} expired nonces, returning {} to the rent pool",
+            expired.len(),
+            sol(lamports)
+        );
+        for batch in expired.chunks(GC_BATCH) {
+            let instructions: Vec<_> = batch
+                .iter()
+                .map(|(_, address, _)| instruction::garbage_collect(address))
+                .collect();
+            cluster.send(&instructions, payer, &[])?;
+        }
+    }
+
+    if !migrate {
+        if !outdated.is_empty() {
+            println!(
+                "{} live nonces predate version {NONCE_VERSION}; run with --migrate to update them",
+                outdated.len()
+            );
+        }
+        return Ok(());
+    }
+    if outdated.is_empty() {
+        println!("No nonces to migrate");
+        return Ok(());
+    }
+    println!(
+        "Migrating {} nonces to version {NONCE_VERSION}",
+        outdated.len()
+    );
+    for batch in outdated.chunks(GC_BATCH) {
+        let instructions: Vec<_> = batch.iter().map(instruction::migrate_nonce).collect();
+        cluster.send(&instructions, payer, &[])?;
+    }
+    Ok(())
+}
+
+pub fn inspect_nonce(cluster: &Cluster, nonce: &[u8; 32]) -> Result {
+    let address = pda::nonce(nonce).0;
+    println!("Nonce:    {}", nonce_hex(nonce));
+    println!("Address:  {address}");
+    let Some(account) = cluster.get_account(&address)? else {
+        println!("Status:   unused");
+        return Ok(());
+    };
+    let record = Nonce::try_from_account(&account.owner, &account.data)?;
+    let now = cluster.clock()?.unix_timestamp;
+    if record.expires_at < now {
+        println!(
+            "Status:   used, expired {}s ago and collectable",
+            now - record.expires_at
+        );
+    } else {
+        println!("Status:   used, expires in {}s", record.expires_at - now);
+    }
+    println!("Expires:  {}", record.expires_at);
+    println!("Rent:     {}", sol(account.lamports));
+    println!("Version:  {}", record.version);
+    // Version 0 recorded no payment
+    if record.version < NONCE_VERSION {
+        println!("Migrate with `xusdc gc --migrate`");
+        return Ok(());
+    }
+    println!("From:     {}", record.from);
+    println!("To:       {}", record.to);
+    println!("Amount:   {}", xusdc(record.amount));
+    println!("Refunded: {}", xusdc(record.refunded));
+    println!("Via:      {}", record.facilitator);
+    println!("Settled:  {}", record.settled_at);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nonce() {
+        let nonce: [u8; 32] = std::array::from_fn(|i| i as u8 * 8);
+        assert_eq!(parse_nonce(&nonce_hex(&nonce)), Ok(nonce));
+        assert_eq!(
+            parse_nonce(&format!("0x{}", "AB".repeat(32))),
+            Ok([0xab; 32])
+        );
+        assert!(parse_nonce("abcd").is_err());
+        assert!(parse_nonce(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_sign_auth_settles() {
+        let payer = Keypair::new();
+        let to = Pubkey::new_unique();
+        let encoded = sign_auth(
+            &payer,
+            to,
+            1_500_000,
+            Duration::from_secs(60),
+            Some([9; 32]),
+            Some([7; 32]),
+            Some("order 42".to_string()),
+        )
+        .unwrap();
+
+        let payload: SettlePayload = borsh::from_slice(&STANDARD.decode(encoded).unwrap()).unwrap();
+        assert_eq!(payload.payment_auth.from, payer.pubkey());
+        assert_eq!(payload.payment_auth.to, to);
+        assert_eq!(payload.payment_auth.nonce, [9; 32]);
+        assert_eq!(payload.payment_auth.resource_hash, [7; 32]);
+        assert_eq!(payload.payment_auth.memo.as_deref(), Some("order 42"));
+        let ix = instruction::settle_payment(&Pubkey::new_unique(), &payload);
+        assert_eq!(ix.accounts[6].pubkey, pda::nonce(&[9; 32]).0);
+    }
+}
@@ -0,0 +1,99 @@
+//! `--dry-run`: run a transaction in litesvm against a snapshot of the accounts it
+//! touches, taken from the cluster, so nothing is signed or sent
+
+use std::collections::BTreeSet;
+use std::error::Error;
+
+use litesvm::LiteSVM;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+use crate::cluster::{describe, Cluster};
+
+/// `getMultipleAccounts` takes at most this many addresses
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// The xUSDC program as deployed on the cluster
+fn program(cluster: &Cluster) -> Result<Vec<u8>, Box<dyn Error>> {
+    let id = xusdc_client::ID;
+    let program = cluster
+        .get_account(&id)?
+        .ok_or_else(|| format!("the xUSDC program {id} is not deployed on this cluster"))?;
+    if program.owner != bpf_loader_upgradeable::ID {
+        return Ok(program.data);
+    }
+    let UpgradeableLoaderState::Program {
+        programdata_address,
+    } = bincode::deserialize(&program.data)?
+    else {
+        return Err(format!("{id} is not an upgradeable program").into());
+    };
+    let programdata = cluster
+        .get_account(&programdata_address)?
+        .ok_or_else(|| format!("the program data of {id} is missing"))?;
+    Ok(programdata.data[UpgradeableLoaderState::size_of_programdata_metadata()..].to_vec())
+}
+
+/// A bank holding the xUSDC program, the cluster's clock and every account
+/// `instructions` reference
+fn snapshot(
+    cluster: &Cluster,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+) -> Result<LiteSVM, Box<dyn Error>> {
+    let mut svm = LiteSVM::new().with_sigverify(false);
+    svm.add_program(xusdc_client::ID, &program(cluster)?);
+    svm.set_sysvar(&cluster.clock()?);
+
+    // litesvm has its own builtins, sysvars and SPL programs; fetch the rest
+    let addresses: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|ix| ix.accounts.iter().map(|meta| meta.pubkey))
+        .chain([*payer])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|address| svm.get_account(address).is_none())
+        .collect();
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = cluster.rpc.get_multiple_accounts(chunk)?;
+        for (address, account) in chunk.iter().zip(accounts) {
+            match account {
+                Some(account) if !account.executable => svm.set_account(*address, account)?,
+                _ => {}
+            }
+        }
+    }
+    Ok(svm)
+}
+
+pub fn simulate(
+    cluster: &Cluster,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+) -> Result<(), Box<dyn Error>> {
+    let mut svm = snapshot(cluster, instructions, payer)?;
+    let mut message = Message::new(instructions, Some(payer));
+    message.recent_blockhash = svm.latest_blockhash();
+    let transaction = Transaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message,
+    };
+    match svm.send_transaction(transaction) {
+        Ok(meta) => {
+            println!("{}", meta.logs.join("\n"));
+            println!(
+                "Dry run succeeded, consuming {} compute units",
+                meta.compute_units_consumed
+            );
+            Ok(())
+        }
+        Err(failed) => {
+            println!("{}", failed.meta.logs.join("\n"));
+            Err(format!("dry run {}", describe(&failed.err, instructions)).into())
+        }
+    }
+}
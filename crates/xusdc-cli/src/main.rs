@@ -0,0 +1,296 @@
+mod amount;
+mod cluster;
+mod commands;
+mod dry_run;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use xusdc_client::auth::DEFAULT_TTL;
+use xusdc_client::{MetadataField, MintMetadata, USDC_MINT_KEY};
+
+use crate::amount::{parse_sol, parse_xusdc};
+use crate::cluster::Cluster;
+use crate::commands::{parse_metadata_field, parse_nonce};
+
+#[derive(Parser)]
+#[command(
+    name = "xusdc",
+    about = "Manage xUSDC deposits, rent, payments and nonces"
+)]
+struct Args {
+    /// Keypair that signs and pays [default: ~/.config/solana/id.json]
+    #[arg(short, long, global = true, env = "XUSDC_KEYPAIR")]
+    keypair: Option<PathBuf>,
+    /// Cluster: mainnet-beta, devnet, testnet, localhost or a JSON RPC URL
+    #[arg(
+        short = 'u',
+        long = "url",
+        global = true,
+        env = "XUSDC_RPC_URL",
+        default_value = "devnet"
+    )]
+    url: String,
+    /// Simulate transactions against a local litesvm snapshot of the accounts they
+    /// touch instead of sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the xUSDC mint and registries (admin only)
+    Init {
+        /// USDC mint backing xUSDC
+        #[arg(long, default_value_t = USDC_MINT_KEY)]
+        usdc_mint: Pubkey,
+        /// Keypair of the xUSDC mint address, not needed with --dry-run
+        #[arg(long)]
+        mint_keypair: Option<PathBuf>,
+        /// Token name wallets show
+        #[arg(long, default_value = "xUSDC")]
+        name: String,
+        #[arg(long, default_value = "xUSDC")]
+        symbol: String,
+        /// URI of the token's off-chain JSON metadata
+        #[arg(long)]
+        uri: String,
+    },
+    /// Change the xUSDC mint's name, symbol or URI (admin only)
+    SetMetadata {
+        /// name, symbol or uri
+        #[arg(value_parser = parse_metadata_field)]
+        field: MetadataField,
+        value: String,
+    },
+    /// Exchange collateral for xUSDC
+    Deposit {
+        /// Amount of collateral, e.g. 12.5
+        #[arg(value_parser = parse_xusdc)]
+        amount: u64,
+        /// Collateral mint
+        #[arg(long, default_value_t = USDC_MINT_KEY)]
+        mint: Pubkey,
+    },
+    /// Exchange xUSDC for collateral
+    Withdraw {
+        /// Amount of xUSDC, e.g. 12.5
+        #[arg(value_parser = parse_xusdc)]
+        amount: u64,
+        /// Collateral mint
+        #[arg(long, default_value_t = USDC_MINT_KEY)]
+        mint: Pubkey,
+    },
+    /// The rent pool paying for nonce accounts
+    #[command(subcommand)]
+    Rent(RentCommand),
+    /// Sign a payment authorization, printing the base64 SettlePayload
+    SignAuth {
+        /// Payee
+        #[arg(long)]
+        to: Pubkey,
+        /// Amount of xUSDC, e.g. 0.01
+        #[arg(long, value_parser = parse_xusdc)]
+        amount: u64,
+        /// Seconds until the authorization expires
+        #[arg(long, default_value_t = DEFAULT_TTL.as_secs())]
+        ttl: u64,
+        /// 64 hex digits [default: random]
+        #[arg(long, value_parser = parse_nonce)]
+        nonce: Option<[u8; 32]>,
+        /// Hash of the HTTP request paid for, 64 hex digits [default: none]
+        #[arg(long, value_parser = parse_nonce)]
+        resource_hash: Option<[u8; 32]>,
+        /// Note emitted with the payment
+        #[arg(long)]
+        memo: Option<String>,
+    },
+    /// Submit a base64 SettlePayload, paying the fees as facilitator
+    Settle { payload: String },
+    /// Close expired nonce accounts, returning their rent to the pool
+    Gc {
+        /// Close at most this many
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Also rewrite live version 0 nonces in the current layout
+        #[arg(long)]
+        migrate: bool,
+    },
+    /// Look up on-chain state
+    #[command(subcommand)]
+    Inspect(InspectCommand),
+}
+
+#[derive(Subcommand)]
+enum RentCommand {
+    /// Put SOL into the rent pool
+    Contribute {
+        #[arg(value_parser = parse_sol)]
+        sol: u64,
+    },
+    /// Take back SOL you contributed
+    Withdraw {
+        #[arg(value_parser = parse_sol)]
+        sol: u64,
+    },
+    /// Show the pool's balance and a contributor's share
+    Status {
+        /// Contributor [default: the keypair's]
+        #[arg(long)]
+        user: Option<Pubkey>,
+    },
+}
+
+#[derive(Subcommand)]
+enum InspectCommand {
+    /// Whether a payment nonce has been used, the payment it recorded, and when it can
+    /// be collected
+    Nonce {
+        #[arg(value_parser = parse_nonce)]
+        nonce: [u8; 32],
+    },
+}
+
+fn read_keypair(path: &PathBuf) -> Result<Keypair, String> {
+    read_keypair_file(path).map_err(|e| format!("reading {}: {e}", path.display()))
+}
+
+impl Args {
+    fn keypair(&self) -> Result<Keypair, String> {
+        match &self.keypair {
+            Some(path) => read_keypair(path),
+            None => {
+                let home = std::env::var_os("HOME").ok_or("--keypair is required without $HOME")?;
+                read_keypair(&PathBuf::from(home).join(".config/solana/id.json"))
+            }
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let cluster = Cluster::new(&args.url, args.dry_run);
+
+    match &args.command {
+        Command::Init {
+            usdc_mint,
+            mint_keypair,
+            name,
+            symbol,
+            uri,
+        } => {
+            let mint_keypair = mint_keypair.as_ref().map(read_keypair).transpose()?;
+            let metadata = MintMetadata {
+                name: name.clone(),
+                symbol: symbol.clone(),
+                uri: uri.clone(),
+            };
+            commands::init(
+                &cluster,
+                &args.keypair()?,
+                usdc_mint,
+                mint_keypair.as_ref(),
+                &metadata,
+            )
+        }
+        Command::SetMetadata { field, value } => {
+            commands::set_metadata(&cluster, &args.keypair()?, *field, value)
+        }
+        Command::Deposit { amount, mint } => {
+            commands::deposit(&cluster, &args.keypair()?, mint, *amount)
+        }
+        Command::Withdraw { amount, mint } => {
+            commands::withdraw(&cluster, &args.keypair()?, mint, *amount)
+        }
+        Command::Rent(RentCommand::Contribute { sol }) => {
+            commands::rent_contribute(&cluster, &args.keypair()?, *sol)
+        }
+        Command::Rent(RentCommand::Withdraw { sol }) => {
+            commands::rent_withdraw(&cluster, &args.keypair()?, *sol)
+        }
+        Command::Rent(RentCommand::Status { user }) => {
+            let user = match user {
+                Some(user) => *user,
+                None => args.keypair()?.pubkey(),
+            };
+            commands::rent_status(&cluster, &user)
+        }
+        Command::SignAuth {
+            to,
+            amount,
+            ttl,
+            nonce,
+            resource_hash,
+            memo,
+        } => {
+            let payload = commands::sign_auth(
+                &args.keypair()?,
+                *to,
+                *amount,
+                Duration::from_secs(*ttl),
+                *nonce,
+                *resource_hash,
+                memo.clone(),
+            )?;
+            println!("{payload}");
+            Ok(())
+        }
+        Command::Settle { payload } => commands::settle(&cluster, &args.keypair()?, payload),
+        Command::Gc { limit, migrate } => {
+            commands::gc(&cluster, &args.keypair()?, *limit, *migrate)
+        }
+        Command::Inspect(InspectCommand::Nonce { nonce }) => {
+            commands::inspect_nonce(&cluster, nonce)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        Args::command().debug_assert();
+
+        let args = Args::parse_from([
+            "xusdc",
+            "-u",
+            "localhost",
+            "rent",
+            "contribute",
+            "0.5",
+            "--dry-run",
+        ]);
+        assert!(args.dry_run);
+        assert!(matches!(
+            args.command,
+            Command::Rent(RentCommand::Contribute { sol: 500_000_000 })
+        ));
+
+        let args = Args::parse_from(["xusdc", "set-metadata", "uri", "https://example.com"]);
+        assert!(matches!(
+            args.command,
+            Command::SetMetadata { field: MetadataField::Uri, value } if value == "https://example.com"
+        ));
+
+        let nonce = "ab".repeat(32);
+        let args = Args::parse_from(["xusdc", "inspect", "nonce", &nonce]);
+        assert!(
+            matches!(args.command, Command::Inspect(InspectCommand::Nonce { nonce }) if nonce == [0xab; 32])
+        );
+    }
+}
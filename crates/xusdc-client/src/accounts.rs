@@ -0,0 +1,114 @@
+//! Program accounts: an 8-byte Anchor discriminator followed by the Borsh fields.
+
+use borsh::BorshDeserialize;
+use solana_program::pubkey::Pubkey;
+
+use crate::error::ClientError;
+use crate::ID;
+
+pub trait ProgramAccount: BorshDeserialize {
+    const NAME: &'static str;
+    /// `sha256("account:<NAME>")[..8]`
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Decode account data, checking the discriminator. Trailing bytes are ignored,
+    /// as Anchor does.
+    fn try_from_bytes(data: &[u8]) -> Result<Self, ClientError> {
+        let mut fields = data
+            .strip_prefix(&Self::DISCRIMINATOR)
+            .ok_or(ClientError::InvalidDiscriminator(Self::NAME))?;
+        Ok(Self::deserialize(&mut fields)?)
+    }
+
+    /// Decode a fetched account, checking it is owned by the program
+    fn try_from_account(owner: &Pubkey, data: &[u8]) -> Result<Self, ClientError> {
+        if *owner != ID {
+            return Err(ClientError::InvalidOwner(*owner));
+        }
+        Self::try_from_bytes(data)
+    }
+}
+
+/// The nonce layout `settle_payment` writes; `migrate_nonce` brings older ones up to it
+pub const NONCE_VERSION: u8 = 1;
+
+/// A used payment authorization nonce and the payment settled with it, closable by
+/// `garbage_collect` once expired.
+///
+/// Decoding accepts both versions. A version 0 nonce holds only `expires_at`, so it
+/// decodes with every other field zeroed.
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nonce {
+    pub expires_at: i64,
+    pub version: u8,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    /// Total returned by `refund_payment`
+    pub refunded: u64,
+    pub facilitator: Pubkey,
+    pub settled_at: i64,
+}
+
+impl Nonce {
+    /// Version 0 holds only `expires_at`
+    const V0_LEN: usize = 8 + 8;
+
+    /// Read only the leading `expires_at`, which both versions have
+    pub fn expires_at(data: &[u8]) -> Result<i64, ClientError> {
+        let mut fields = data
+            .strip_prefix(&Self::DISCRIMINATOR)
+            .ok_or(ClientError::InvalidDiscriminator(Self::NAME))?;
+        Ok(i64::deserialize(&mut fields)?)
+    }
+}
+
+impl ProgramAccount for Nonce {
+    const NAME: &'static str = "Nonce";
+    const DISCRIMINATOR: [u8; 8] = [143, 197, 147, 95, 106, 165, 50, 43];
+
+    /// Version 0 had no `version` field, so it is told apart by length
+    fn try_from_bytes(data: &[u8]) -> Result<Self, ClientError> {
+        let mut fields = data
+            .strip_prefix(&Self::DISCRIMINATOR)
+            .ok_or(ClientError::InvalidDiscriminator(Self::NAME))?;
+        Ok(match data.len() {
+            Self::V0_LEN => Self {
+                expires_at: i64::deserialize(&mut fields)?,
+                version: 0,
+                from: Pubkey::default(),
+                to: Pubkey::default(),
+                amount: 0,
+                refunded: 0,
+                facilitator: Pubkey::default(),
+                settled_at: 0,
+            },
+            _ => Self::deserialize(&mut fields)?,
+        })
+    }
+}
+
+/// How much rent a user has put into the global rent pool
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContributorRentInfo {
+    pub amount: u64,
+    pub user: Pubkey,
+    pub nonces_funded: u64,
+}
+
+impl ProgramAccount for ContributorRentInfo {
+    const NAME: &'static str = "ContributorRentInfo";
+    const DISCRIMINATOR: [u8; 8] = [255, 40, 36, 80, 72, 88, 162, 130];
+}
+
+/// Exists while the admin has `wallet` blocked
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockedAccount {
+    pub wallet: Pubkey,
+    pub blocked_at: i64,
+}
+
+impl ProgramAccount for BlockedAccount {
+    const NAME: &'static str = "BlockedAccount";
+    const DISCRIMINATOR: [u8; 8] = [227, 209, 198, 155, 99, 193, 183, 55];
+}
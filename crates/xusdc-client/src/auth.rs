@@ -0,0 +1,496 @@
+//! Creating and signing payment authorizations
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+use solana_program::pubkey::Pubkey;
+use solana_signer::SignerError;
+
+use crate::error::SignError;
+use crate::types::{
+    splits_hash, PaymentAuthorization, SettlePayload, Split, SplitPaymentAuthorization,
+    SplitRecipients, SplitSettlePayload,
+};
+
+/// How long an authorization stays valid unless the builder is told otherwise. The
+/// nonce account of a settled payment holds rent until then, so keep it short.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Signs payment authorizations on behalf of the payer.
+///
+/// Implemented for every [`solana_signer::Signer`], which covers keypairs as well as
+/// hardware and remote wallets, and by [`FnSigner`] for anything else.
+pub trait Signer {
+    /// The payer, used as `PaymentAuthorization::from`
+    fn signer_pubkey(&self) -> Pubkey;
+    /// Ed25519 signature of `message`
+    fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError>;
+}
+
+impl<T: solana_signer::Signer + ?Sized> Signer for T {
+    fn signer_pubkey(&self) -> Pubkey {
+        self.pubkey()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError> {
+        Ok(self.try_sign_message(message)?.into())
+    }
+}
+
+/// A [`Signer`] backed by a closure, e.g. one calling out to a KMS
+pub struct FnSigner<F> {
+    pubkey: Pubkey,
+    sign: F,
+}
+
+impl<F> FnSigner<F>
+where
+    F: Fn(&[u8]) -> Result<[u8; 64], SignerError>,
+{
+    pub fn new(pubkey: Pubkey, sign: F) -> Self {
+        Self { pubkey, sign }
+    }
+}
+
+impl<F> Signer for FnSigner<F>
+where
+    F: Fn(&[u8]) -> Result<[u8; 64], SignerError>,
+{
+    fn signer_pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError> {
+        (self.sign)(message)
+    }
+}
+
+/// Builds a [`PaymentAuthorization`], created by [`PaymentAuthorization::builder`]
+#[derive(Debug, Clone)]
+pub struct AuthorizationBuilder {
+    to: Pubkey,
+    amount: u64,
+    resource_hash: [u8; 32],
+    memo: Option<String>,
+    nonce: Option<[u8; 32]>,
+    ttl: Duration,
+    valid_until: Option<i64>,
+    now: Option<i64>,
+}
+
+impl PaymentAuthorization {
+    /// Authorize paying `amount` xUSDC base units to `to`
+    pub fn builder(to: Pubkey, amount: u64) -> AuthorizationBuilder {
+        AuthorizationBuilder {
+            to,
+            amount,
+            resource_hash: [0; 32],
+            memo: None,
+            nonce: None,
+            ttl: DEFAULT_TTL,
+            valid_until: None,
+            now: None,
+        }
+    }
+}
+
+impl AuthorizationBuilder {
+    /// Pay for the HTTP request with this [`resource_hash`](crate::resource_hash)
+    pub fn resource_hash(mut self, resource_hash: [u8; 32]) -> Self {
+        self.resource_hash = resource_hash;
+        self
+    }
+
+    /// Attach a note that `settle_payment` emits with the payment
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Use this nonce instead of a random one
+    pub fn nonce(mut self, nonce: [u8; 32]) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Expire `ttl` after now. Defaults to [`DEFAULT_TTL`].
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Expire at this Unix timestamp, ignoring the TTL
+    pub fn valid_until(mut self, valid_until: i64) -> Self {
+        self.valid_until = Some(valid_until);
+        self
+    }
+
+    /// Apply the TTL from this Unix timestamp instead of the system clock, e.g. the
+    /// cluster's `Clock` when the two drift apart
+    pub fn now(mut self, unix_timestamp: i64) -> Self {
+        self.now = Some(unix_timestamp);
+        self
+    }
+
+    /// The unsigned authorization for `from`
+    pub fn build(self, from: Pubkey) -> Result<PaymentAuthorization, SignError> {
+        Ok(PaymentAuthorization {
+            from,
+            to: self.to,
+            amount: self.amount,
+            nonce: self.nonce.unwrap_or_else(|| rand::thread_rng().gen()),
+            valid_until: expiry(self.valid_until, self.now, self.ttl)?,
+            resource_hash: self.resource_hash,
+            memo: self.memo,
+        })
+    }
+
+    /// Build the authorization for the signer and sign it
+    pub fn sign(self, signer: &(impl Signer + ?Sized)) -> Result<SettlePayload, SignError> {
+        SettlePayload::sign(self.build(signer.signer_pubkey())?, signer)
+    }
+}
+
+/// `valid_until` if set, otherwise `ttl` after `now` or the system clock
+fn expiry(valid_until: Option<i64>, now: Option<i64>, ttl: Duration) -> Result<i64, SignError> {
+    if let Some(valid_until) = valid_until {
+        return Ok(valid_until);
+    }
+    let now = match now {
+        Some(now) => now,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| SignError::Clock)?
+            .as_secs() as i64,
+    };
+    i64::try_from(ttl.as_secs())
+        .ok()
+        .and_then(|ttl| now.checked_add(ttl))
+        .ok_or(SignError::Clock)
+}
+
+/// Builds a [`SplitPaymentAuthorization`], created by
+/// [`SplitPaymentAuthorization::builder`]
+#[derive(Debug, Clone)]
+pub struct SplitAuthorizationBuilder {
+    splits: Vec<Split>,
+    commit: bool,
+    resource_hash: [u8; 32],
+    memo: Option<String>,
+    nonce: Option<[u8; 32]>,
+    ttl: Duration,
+    valid_until: Option<i64>,
+    now: Option<i64>,
+}
+
+impl SplitPaymentAuthorization {
+    /// Authorize paying each split's amount to its recipient, listing the splits in
+    /// the signed authorization
+    pub fn builder(splits: Vec<Split>) -> SplitAuthorizationBuilder {
+        SplitAuthorizationBuilder {
+            splits,
+            commit: false,
+            resource_hash: [0; 32],
+            memo: None,
+            nonce: None,
+            ttl: DEFAULT_TTL,
+            valid_until: None,
+            now: None,
+        }
+    }
+}
+
+impl SplitAuthorizationBuilder {
+    /// Sign only a hash of the splits, which the payload then reveals
+    pub fn commit(mut self) -> Self {
+        self.commit = true;
+        self
+    }
+
+    /// Pay for the HTTP request with this [`resource_hash`](crate::resource_hash)
+    pub fn resource_hash(mut self, resource_hash: [u8; 32]) -> Self {
+        self.resource_hash = resource_hash;
+        self
+    }
+
+    /// Attach a note that `settle_split_payment` emits with the payment
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Use this nonce instead of a random one
+    pub fn nonce(mut self, nonce: [u8; 32]) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Expire `ttl` after now. Defaults to [`DEFAULT_TTL`].
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Expire at this Unix timestamp, ignoring the TTL
+    pub fn valid_until(mut self, valid_until: i64) -> Self {
+        self.valid_until = Some(valid_until);
+        self
+    }
+
+    /// Apply the TTL from this Unix timestamp instead of the system clock
+    pub fn now(mut self, unix_timestamp: i64) -> Self {
+        self.now = Some(unix_timestamp);
+        self
+    }
+
+    /// Build the authorization for the signer and sign it. The amount is the sum of
+    /// the splits, saturating so that an overflowing sum fails to settle.
+    pub fn sign(self, signer: &(impl Signer + ?Sized)) -> Result<SplitSettlePayload, SignError> {
+        let amount = self
+            .splits
+            .iter()
+            .fold(0u64, |total, split| total.saturating_add(split.amount));
+        let (to, revealed) = if self.commit {
+            (
+                SplitRecipients::Commitment(splits_hash(&self.splits)),
+                self.splits,
+            )
+        } else {
+            (SplitRecipients::List(self.splits), Vec::new())
+        };
+        let payment_auth = SplitPaymentAuthorization {
+            from: signer.signer_pubkey(),
+            to,
+            amount,
+            nonce: self.nonce.unwrap_or_else(|| rand::thread_rng().gen()),
+            valid_until: expiry(self.valid_until, self.now, self.ttl)?,
+            resource_hash: self.resource_hash,
+            memo: self.memo,
+        };
+        SplitSettlePayload::sign(payment_auth, revealed, signer)
+    }
+}
+
+impl SplitSettlePayload {
+    /// Sign a split authorization exactly as `settle_split_payment` verifies it.
+    /// `revealed` holds the splits behind a commitment, and is empty for a list.
+    pub fn sign(
+        payment_auth: SplitPaymentAuthorization,
+        revealed: Vec<Split>,
+        signer: &(impl Signer + ?Sized),
+    ) -> Result<Self, SignError> {
+        let signer_pubkey = signer.signer_pubkey();
+        if signer_pubkey != payment_auth.from {
+            return Err(SignError::SignerMismatch {
+                from: payment_auth.from,
+                signer: signer_pubkey,
+            });
+        }
+        let signature = signer.sign(&payment_auth.message())?;
+        Ok(Self {
+            payment_auth,
+            revealed,
+            signature,
+            signer_pubkey: signer_pubkey.to_bytes(),
+        })
+    }
+}
+
+impl SettlePayload {
+    /// Sign an authorization exactly as `settle_payment` verifies it: an Ed25519
+    /// signature over its Borsh encoding, by `from`
+    pub fn sign(
+        payment_auth: PaymentAuthorization,
+        signer: &(impl Signer + ?Sized),
+    ) -> Result<Self, SignError> {
+        let signer_pubkey = signer.signer_pubkey();
+        if signer_pubkey != payment_auth.from {
+            return Err(SignError::SignerMismatch {
+                from: payment_auth.from,
+                signer: signer_pubkey,
+            });
+        }
+        let signature = signer.sign(&payment_auth.message())?;
+        Ok(Self {
+            payment_auth,
+            signature,
+            signer_pubkey: signer_pubkey.to_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_keypair::Keypair;
+    use solana_signer::Signer as _;
+
+    #[test]
+    fn test_builder_applies_ttl_to_now() {
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let auth = PaymentAuthorization::builder(to, 5)
+            .now(1_000)
+            .ttl(Duration::from_secs(30))
+            .nonce([1; 32])
+            .build(from)
+            .unwrap();
+        assert_eq!(
+            auth,
+            PaymentAuthorization {
+                from,
+                to,
+                amount: 5,
+                nonce: [1; 32],
+                valid_until: 1_030,
+                resource_hash: [0; 32],
+                memo: None,
+            }
+        );
+
+        let auth = PaymentAuthorization::builder(to, 5)
+            .now(1_000)
+            .valid_until(2_000)
+            .build(from)
+            .unwrap();
+        assert_eq!(auth.valid_until, 2_000);
+
+        assert!(matches!(
+            PaymentAuthorization::builder(to, 5)
+                .now(i64::MAX)
+                .build(from),
+            Err(SignError::Clock)
+        ));
+    }
+
+    #[test]
+    fn test_resource_hash_is_canonical() {
+        let url = "https://example.com/weather?city=Lisbon";
+        let get = crate::resource_hash("GET", url, b"");
+        assert_eq!(crate::resource_hash("get", url, b""), get);
+        assert_ne!(crate::resource_hash("POST", url, b""), get);
+        assert_ne!(crate::resource_hash("GET", url, b"{}"), get);
+        assert_ne!(
+            crate::resource_hash("GET", "https://example.com/weather", b""),
+            get
+        );
+        // Lengths are encoded, so fields can't run into each other
+        assert_ne!(crate::resource_hash("GE", &format!("T{url}"), b""), get);
+
+        let auth = PaymentAuthorization::builder(Pubkey::new_unique(), 5)
+            .resource_hash(get)
+            .memo("order 42")
+            .build(Pubkey::new_unique())
+            .unwrap();
+        assert_eq!(auth.resource_hash, get);
+        assert_eq!(auth.memo.as_deref(), Some("order 42"));
+    }
+
+    #[test]
+    fn test_builder_defaults_to_random_nonce_and_system_clock() {
+        let builder = PaymentAuthorization::builder(Pubkey::new_unique(), 1);
+        let first = builder.clone().build(Pubkey::new_unique()).unwrap();
+        let second = builder.build(Pubkey::new_unique()).unwrap();
+        assert_ne!(first.nonce, second.nonce);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let ttl = DEFAULT_TTL.as_secs() as i64;
+        assert!((now + ttl..=now + ttl + 5).contains(&first.valid_until));
+    }
+
+    #[test]
+    fn test_keypair_and_closure_signers_agree() {
+        let keypair = Keypair::new();
+        let auth = PaymentAuthorization::builder(Pubkey::new_unique(), 10)
+            .now(0)
+            .build(keypair.pubkey())
+            .unwrap();
+
+        let from_keypair = SettlePayload::sign(auth.clone(), &keypair).unwrap();
+        let closure = FnSigner::new(keypair.pubkey(), |message: &[u8]| {
+            Ok(keypair.sign_message(message).into())
+        });
+        let from_closure = SettlePayload::sign(auth.clone(), &closure).unwrap();
+        assert_eq!(from_keypair, from_closure);
+        assert_eq!(from_keypair.signer_pubkey, keypair.pubkey().to_bytes());
+        assert!(keypair
+            .sign_message(&auth.message())
+            .verify(keypair.pubkey().as_ref(), &auth.message()));
+
+        // Boxed signers work too
+        let boxed: Box<dyn Signer> = Box::new(closure);
+        assert_eq!(SettlePayload::sign(auth, &*boxed).unwrap(), from_keypair);
+    }
+
+    #[test]
+    fn test_split_builder_lists_or_commits() {
+        let keypair = Keypair::new();
+        let splits = vec![
+            Split {
+                recipient: Pubkey::new_unique(),
+                amount: 90,
+            },
+            Split {
+                recipient: Pubkey::new_unique(),
+                amount: 10,
+            },
+        ];
+
+        let listed = SplitPaymentAuthorization::builder(splits.clone())
+            .now(0)
+            .nonce([2; 32])
+            .sign(&keypair)
+            .unwrap();
+        assert_eq!(
+            listed.payment_auth.to,
+            SplitRecipients::List(splits.clone())
+        );
+        assert_eq!(listed.payment_auth.amount, 100);
+        assert_eq!(
+            listed.payment_auth.valid_until,
+            DEFAULT_TTL.as_secs() as i64
+        );
+        assert!(listed.revealed.is_empty());
+
+        let committed = SplitPaymentAuthorization::builder(splits.clone())
+            .commit()
+            .now(0)
+            .nonce([2; 32])
+            .sign(&keypair)
+            .unwrap();
+        assert_eq!(
+            committed.payment_auth.to,
+            SplitRecipients::Commitment(splits_hash(&splits))
+        );
+        assert_eq!(committed.splits(), listed.splits());
+
+        // The domain keeps a split signature from ever verifying as a single payment
+        let message = committed.payment_auth.message();
+        assert!(message.starts_with(crate::types::SPLIT_PAYMENT_DOMAIN));
+        assert!(keypair
+            .sign_message(&message)
+            .verify(keypair.pubkey().as_ref(), &message));
+    }
+
+    #[test]
+    fn test_sign_rejects_other_signers() {
+        let auth = PaymentAuthorization::builder(Pubkey::new_unique(), 10)
+            .build(Pubkey::new_unique())
+            .unwrap();
+        assert!(matches!(
+            SettlePayload::sign(auth, &Keypair::new()),
+            Err(SignError::SignerMismatch { .. })
+        ));
+
+        let failing = FnSigner::new(Pubkey::new_unique(), |_: &[u8]| {
+            Err(SignerError::UserCancel("declined".into()))
+        });
+        assert!(matches!(
+            PaymentAuthorization::builder(Pubkey::new_unique(), 10).sign(&failing),
+            Err(SignError::Signer(SignerError::UserCancel(_)))
+        ));
+    }
+}
@@ -0,0 +1,22 @@
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("account is owned by {0}, not the xUSDC program")]
+    InvalidOwner(solana_program::pubkey::Pubkey),
+    #[error("account data does not start with the {0} discriminator")]
+    InvalidDiscriminator(&'static str),
+    #[error("malformed account data: {0}")]
+    InvalidData(#[from] std::io::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignError {
+    #[error("signer {signer} cannot sign for {from}")]
+    SignerMismatch {
+        from: solana_program::pubkey::Pubkey,
+        signer: solana_program::pubkey::Pubkey,
+    },
+    #[error("signing failed: {0}")]
+    Signer(#[from] solana_signer::SignerError),
+    #[error("the clock is out of range")]
+    Clock,
+}
@@ -0,0 +1,248 @@
+//! The `xusdc-hook` transfer hook program. Once the admin enables it with
+//! [`instruction::set_transfer_hook`](crate::instruction::set_transfer_hook), every
+//! xUSDC transfer must carry [`transfer_accounts`], including `settle_payment`.
+//! [`mint_transfer_accounts`] reads from the mint whether they are needed:
+//!
+//! ```ignore
+//! let mint = rpc.get_account(&XUSDC_MINT_KEY)?;
+//! let mut ix = instruction::settle_payment(&facilitator, &payload);
+//! let auth = &payload.payment_auth;
+//! ix.accounts.extend(hook::mint_transfer_accounts(&mint.data, &auth.from, &auth.to));
+//! ```
+
+use borsh::BorshSerialize;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use crate::{pda, XUSDC_MINT_KEY};
+
+pub const HOOK_PROGRAM_ID: Pubkey = pubkey!("9wWX5aM2whfNuu46sYaxcWuyU6gJF6nb2V7qvsMBn2Wr");
+
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+pub const POLICY_SEED: &[u8] = b"policy";
+
+/// Token-2022's `ExtensionType::TransferHook`
+const TRANSFER_HOOK_EXTENSION: u16 = 14;
+
+/// Where a Token-2022 mint's extensions start: the base mint padded to the size of a
+/// token account, then the account type
+const MINT_EXTENSIONS_OFFSET: usize = 165 + 1;
+
+/// Token-2022's `AccountType::Mint`
+const MINT_ACCOUNT_TYPE: u8 = 1;
+
+/// Why the hook fails a transfer. Each variant is the hook's `ErrorCode` variant of
+/// the same name and has the same error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[repr(u32)]
+pub enum HookError {
+    #[error("The hook was invoked outside a transfer")]
+    NotTransferring = 6000,
+    #[error("Wallet is blocked")]
+    WalletBlocked,
+    #[error("Transfer would exceed the wallet's spend limit")]
+    SpendLimitExceeded,
+    #[error("Invalid wallet policy")]
+    InvalidPolicy,
+    #[error("Arithmetic overflow")]
+    Overflow,
+}
+
+impl HookError {
+    pub const ALL: [Self; 5] = [
+        Self::NotTransferring,
+        Self::WalletBlocked,
+        Self::SpendLimitExceeded,
+        Self::InvalidPolicy,
+        Self::Overflow,
+    ];
+
+    /// The custom program error code the hook fails with
+    pub const fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|error| error.code() == code)
+    }
+}
+
+/// `sha256("global:<instruction>")[..8]`
+pub mod discriminator {
+    pub const INITIALIZE_EXTRA_ACCOUNT_META_LIST: [u8; 8] = [92, 197, 174, 197, 41, 124, 19, 3];
+    pub const SET_POLICY: [u8; 8] = [40, 133, 12, 157, 235, 202, 2, 132];
+}
+
+/// The meta list Token-2022 reads the hook's extra accounts from
+pub fn extra_account_metas() -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[EXTRA_ACCOUNT_METAS_SEED, XUSDC_MINT_KEY.as_ref()],
+        &HOOK_PROGRAM_ID,
+    )
+}
+
+/// `wallet`'s spend limit and totals, which may not exist
+pub fn policy(wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POLICY_SEED, wallet.as_ref()], &HOOK_PROGRAM_ID)
+}
+
+/// The accounts to append to a transfer from `from`'s xUSDC account to `to`'s, in the
+/// order Token-2022's own resolution produces them
+pub fn transfer_accounts(from: &Pubkey, to: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(policy(from).0, false),
+        AccountMeta::new_readonly(crate::ID, false),
+        AccountMeta::new_readonly(pda::blocked_account(from).0, false),
+        AccountMeta::new_readonly(pda::blocked_account(to).0, false),
+        AccountMeta::new_readonly(HOOK_PROGRAM_ID, false),
+        AccountMeta::new_readonly(extra_account_metas().0, false),
+    ]
+}
+
+/// The program the mint's `TransferHook` extension points at, read from the xUSDC mint
+/// account's data. `None` while the hook is disabled, or if the data isn't a
+/// Token-2022 mint with the extension.
+pub fn hook_program(mint_data: &[u8]) -> Option<Pubkey> {
+    if mint_data.get(MINT_EXTENSIONS_OFFSET - 1) != Some(&MINT_ACCOUNT_TYPE) {
+        return None;
+    }
+    // Type, length and value entries, the value holding the authority then the program,
+    // each all zeros for none
+    let mut extensions = &mint_data[MINT_EXTENSIONS_OFFSET..];
+    while let [kind_0, kind_1, len_0, len_1, rest @ ..] = extensions {
+        let len = u16::from_le_bytes([*len_0, *len_1]) as usize;
+        let value = rest.get(..len)?;
+        if u16::from_le_bytes([*kind_0, *kind_1]) == TRANSFER_HOOK_EXTENSION {
+            let program = Pubkey::try_from(value.get(32..64)?).ok()?;
+            return (program != Pubkey::default()).then_some(program);
+        }
+        extensions = &rest[len..];
+    }
+    None
+}
+
+/// [`transfer_accounts`] while the xUSDC mint, given its account data, has the hook
+/// enabled, and nothing otherwise
+pub fn mint_transfer_accounts(mint_data: &[u8], from: &Pubkey, to: &Pubkey) -> Vec<AccountMeta> {
+    match hook_program(mint_data) {
+        Some(_) => transfer_accounts(from, to),
+        None => Vec::new(),
+    }
+}
+
+/// The accounts to append to a split payment from `from`, covering the transfer to
+/// every recipient
+pub fn split_transfer_accounts(from: &Pubkey, recipients: &[Pubkey]) -> Vec<AccountMeta> {
+    let Some((first, rest)) = recipients.split_first() else {
+        return Vec::new();
+    };
+    let mut accounts = transfer_accounts(from, first);
+    for recipient in rest {
+        accounts.push(AccountMeta::new_readonly(
+            pda::blocked_account(recipient).0,
+            false,
+        ));
+    }
+    accounts
+}
+
+fn data(discriminator: [u8; 8], args: impl BorshSerialize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    args.serialize(&mut data)
+        .expect("serializing to a Vec cannot fail");
+    data
+}
+
+/// Create the meta list. Signed by the admin.
+pub fn initialize_extra_account_meta_list(authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: HOOK_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(extra_account_metas().0, false),
+            AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: data(discriminator::INITIALIZE_EXTRA_ACCOUNT_META_LIST, ()),
+    }
+}
+
+/// Limit what `wallet` can send per window. Signed by the admin, who pays for the
+/// policy account the first time. Wallets are blocked with xUSDC's own
+/// [`block_account`](crate::instruction::block_account).
+pub fn set_policy(
+    authority: &Pubkey,
+    wallet: &Pubkey,
+    spend_limit: u64,
+    window_seconds: i64,
+) -> Instruction {
+    let event_authority =
+        Pubkey::find_program_address(&[pda::EVENT_AUTHORITY_SEED], &HOOK_PROGRAM_ID).0;
+    Instruction {
+        program_id: HOOK_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(policy(wallet).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(HOOK_PROGRAM_ID, false),
+        ],
+        data: data(
+            discriminator::SET_POLICY,
+            (wallet, spend_limit, window_seconds),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litesvm_token::spl_token::extension::transfer_hook::TransferHook;
+    use litesvm_token::spl_token::extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    };
+    use litesvm_token::spl_token::state::Mint;
+    use solana_program::program_option::COption;
+    use solana_program::program_pack::Pack;
+
+    /// A Token-2022 mint with the `TransferHook` extension, pointed at `program`
+    fn mint_data(program: Option<Pubkey>) -> Vec<u8> {
+        let extensions = [ExtensionType::TransferHook];
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        let hook = mint.init_extension::<TransferHook>(true).unwrap();
+        hook.authority = Some(Pubkey::new_unique()).try_into().unwrap();
+        hook.program_id = program.try_into().unwrap();
+        mint.base = Mint {
+            mint_authority: COption::Some(Pubkey::new_unique()),
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn test_mint_transfer_accounts_follow_the_extension() {
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let enabled = mint_data(Some(HOOK_PROGRAM_ID));
+        assert_eq!(hook_program(&enabled), Some(HOOK_PROGRAM_ID));
+        assert_eq!(
+            mint_transfer_accounts(&enabled, &from, &to),
+            transfer_accounts(&from, &to)
+        );
+
+        let disabled = mint_data(None);
+        assert_eq!(hook_program(&disabled), None);
+        assert!(mint_transfer_accounts(&disabled, &from, &to).is_empty());
+
+        // A mint without extensions, and one cut short
+        assert_eq!(hook_program(&enabled[..Mint::LEN]), None);
+        assert_eq!(hook_program(&enabled[..enabled.len() - 1]), None);
+    }
+}
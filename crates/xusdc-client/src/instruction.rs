@@ -0,0 +1,571 @@
+//! Builders for every xUSDC instruction
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+
+use crate::accounts::Nonce;
+use crate::error::ClientError;
+use crate::pda;
+use crate::types::{MetadataField, MintMetadata, SettlePayload, SplitSettlePayload};
+use crate::{
+    ASSOCIATED_TOKEN_PROGRAM_ID, DEVNET_USDC_MINT_KEY, ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
+    USDC_MINT_KEY, XUSDC_MINT_KEY,
+};
+
+/// `sha256("global:<instruction>")[..8]`
+pub mod discriminator {
+    pub const INITIALIZE: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
+    pub const CONTRIBUTE_RENT: [u8; 8] = [36, 92, 13, 197, 17, 231, 40, 174];
+    pub const WITHDRAW_RENT: [u8; 8] = [226, 7, 41, 158, 173, 111, 192, 107];
+    pub const GARBAGE_COLLECT: [u8; 8] = [230, 24, 109, 129, 46, 107, 164, 126];
+    pub const SETTLE_PAYMENT: [u8; 8] = [129, 7, 163, 250, 122, 226, 158, 249];
+    pub const SETTLE_SPLIT_PAYMENT: [u8; 8] = [130, 169, 110, 155, 173, 178, 157, 11];
+    pub const REFUND_PAYMENT: [u8; 8] = [121, 205, 211, 181, 202, 147, 45, 248];
+    pub const MIGRATE_NONCE: [u8; 8] = [72, 81, 200, 164, 52, 37, 202, 91];
+    pub const GET_PAYMENT: [u8; 8] = [6, 147, 13, 60, 114, 66, 109, 249];
+    pub const DEPOSIT: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+    pub const WITHDRAW: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+    pub const ADD_COLLATERAL: [u8; 8] = [127, 82, 121, 42, 161, 176, 249, 206];
+    pub const INIT_COLLATERAL_REGISTRY: [u8; 8] = [222, 105, 1, 91, 99, 204, 37, 27];
+    pub const UPDATE_COLLATERAL: [u8; 8] = [218, 227, 184, 124, 133, 81, 157, 131];
+    pub const UPDATE_METADATA: [u8; 8] = [170, 182, 43, 239, 97, 78, 225, 186];
+    pub const ATTEST_RESERVES: [u8; 8] = [68, 20, 40, 240, 165, 2, 146, 10];
+    pub const SET_WITHDRAW_LIMITS: [u8; 8] = [251, 229, 34, 236, 139, 234, 136, 197];
+    pub const SET_TRANSFER_HOOK: [u8; 8] = [83, 141, 143, 233, 24, 194, 244, 8];
+    pub const BLOCK_ACCOUNT: [u8; 8] = [211, 224, 222, 101, 90, 246, 174, 47];
+    pub const UNBLOCK_ACCOUNT: [u8; 8] = [6, 27, 18, 22, 208, 252, 21, 71];
+    pub const SET_FROZEN: [u8; 8] = [62, 87, 99, 96, 206, 47, 204, 18];
+    pub const SEIZE: [u8; 8] = [129, 159, 143, 31, 161, 224, 241, 84];
+    pub const CLAIM_WITHDRAW: [u8; 8] = [232, 89, 154, 117, 16, 204, 182, 224];
+    pub const REQUEST_WITHDRAW: [u8; 8] = [137, 95, 187, 96, 250, 138, 31, 182];
+    pub const CANCEL_WITHDRAW_REQUEST: [u8; 8] = [167, 100, 110, 128, 113, 154, 224, 77];
+}
+
+/// A stablecoin registered as collateral
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collateral {
+    pub mint: Pubkey,
+    /// Token or Token-2022 program owning `mint`
+    pub token_program: Pubkey,
+}
+
+impl Collateral {
+    pub const USDC: Self = Self {
+        mint: USDC_MINT_KEY,
+        token_program: TOKEN_PROGRAM_ID,
+    };
+    pub const DEVNET_USDC: Self = Self {
+        mint: DEVNET_USDC_MINT_KEY,
+        token_program: TOKEN_PROGRAM_ID,
+    };
+
+    pub fn vault(&self) -> Pubkey {
+        pda::collateral_vault(&self.mint, &self.token_program)
+    }
+
+    pub fn token_address(&self, owner: &Pubkey) -> Pubkey {
+        pda::associated_token_address(owner, &self.mint, &self.token_program)
+    }
+}
+
+fn data(discriminator: [u8; 8], args: impl BorshSerialize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    args.serialize(&mut data)
+        .expect("serializing to a Vec cannot fail");
+    data
+}
+
+/// Append the accounts `#[event_cpi]` adds to an instruction
+fn with_event_cpi(mut accounts: Vec<AccountMeta>) -> Vec<AccountMeta> {
+    accounts.push(AccountMeta::new_readonly(pda::event_authority().0, false));
+    accounts.push(AccountMeta::new_readonly(ID, false));
+    accounts
+}
+
+/// Create the xUSDC mint with its metadata, the USDC vault, the collateral registry and the
+/// withdraw limits. Signed by the admin and the xUSDC mint keypair.
+pub fn initialize(authority: &Pubkey, usdc_mint: &Pubkey, metadata: &MintMetadata) -> Instruction {
+    let transfer_authority = pda::transfer_authority().0;
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new(XUSDC_MINT_KEY, true),
+            AccountMeta::new_readonly(*usdc_mint, false),
+            // Created through the associated token program, so it must be writable
+            AccountMeta::new(
+                pda::associated_token_address(&transfer_authority, usdc_mint, &TOKEN_PROGRAM_ID),
+                false,
+            ),
+            AccountMeta::new_readonly(transfer_authority, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new(pda::withdraw_limits().0, false),
+        ],
+        data: data(discriminator::INITIALIZE, metadata),
+    }
+}
+
+/// Create the collateral registry of a deployment initialized before it existed, with
+/// the whole xUSDC supply outstanding against USDC. Signed by the admin.
+pub fn init_collateral_registry(authority: &Pubkey, usdc_mint: &Pubkey) -> Instruction {
+    let usdc = Collateral {
+        mint: *usdc_mint,
+        token_program: TOKEN_PROGRAM_ID,
+    };
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(usdc.mint, false),
+            AccountMeta::new_readonly(usdc.vault(), false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(discriminator::INIT_COLLATERAL_REGISTRY, ()),
+    }
+}
+
+/// Put `amount` lamports into the rent pool that pays for nonce accounts
+pub fn contribute_rent(user: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pda::rent_contributor(user).0, false),
+            AccountMeta::new(pda::rent_pool().0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(discriminator::CONTRIBUTE_RENT, amount),
+    }
+}
+
+pub fn withdraw_rent(user: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pda::rent_contributor(user).0, false),
+            AccountMeta::new(pda::rent_pool().0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(discriminator::WITHDRAW_RENT, amount),
+    }
+}
+
+/// Close an expired nonce account, returning its rent to the pool. Permissionless.
+pub fn garbage_collect(nonce_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*nonce_account, false),
+            AccountMeta::new(pda::rent_pool().0, false),
+        ]),
+        data: data(discriminator::GARBAGE_COLLECT, ()),
+    }
+}
+
+/// Rewrite a nonce account of an older version in the current layout
+pub fn migrate_nonce(nonce_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*nonce_account, false),
+            AccountMeta::new(pda::rent_pool().0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(discriminator::MIGRATE_NONCE, ()),
+    }
+}
+
+/// Look up the payment settled with `nonce`. Simulate it and decode the return data
+/// with [`decode_payment`].
+pub fn get_payment(nonce: &[u8; 32]) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: vec![AccountMeta::new_readonly(pda::nonce(nonce).0, false)],
+        data: data(discriminator::GET_PAYMENT, nonce),
+    }
+}
+
+/// Decode `get_payment`'s return data: `None` if the nonce is unused or collected
+pub fn decode_payment(return_data: &[u8]) -> Result<Option<Nonce>, ClientError> {
+    Ok(Option::<Nonce>::try_from_slice(return_data)?)
+}
+
+/// Move xUSDC from `payload.payment_auth.from` to `payload.payment_auth.to`
+pub fn settle_payment(facilitator: &Pubkey, payload: &SettlePayload) -> Instruction {
+    let payment_auth = &payload.payment_auth;
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new_readonly(*facilitator, true),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+            AccountMeta::new(pda::xusdc_token_address(&payment_auth.from), false),
+            AccountMeta::new(pda::xusdc_token_address(&payment_auth.to), false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new(pda::nonce(&payment_auth.nonce).0, false),
+            AccountMeta::new(pda::rent_pool().0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(pda::blocked_account(&payment_auth.from).0, false),
+            AccountMeta::new_readonly(pda::blocked_account(&payment_auth.to).0, false),
+        ]),
+        data: data(discriminator::SETTLE_PAYMENT, payload),
+    }
+}
+
+/// Move xUSDC from `payload.payment_auth.from` to each of its splits, appending every
+/// recipient's xUSDC account and blocklist entry. With the transfer hook enabled, add
+/// [`hook::split_transfer_accounts`](crate::hook::split_transfer_accounts) after them.
+pub fn settle_split_payment(facilitator: &Pubkey, payload: &SplitSettlePayload) -> Instruction {
+    let payment_auth = &payload.payment_auth;
+    let mut accounts = with_event_cpi(vec![
+        AccountMeta::new_readonly(*facilitator, true),
+        AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+        AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+        AccountMeta::new(pda::xusdc_token_address(&payment_auth.from), false),
+        AccountMeta::new_readonly(pda::transfer_authority().0, false),
+        AccountMeta::new(pda::nonce(&payment_auth.nonce).0, false),
+        AccountMeta::new(pda::rent_pool().0, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(pda::blocked_account(&payment_auth.from).0, false),
+    ]);
+    for split in payload.splits() {
+        accounts.push(AccountMeta::new(
+            pda::xusdc_token_address(&split.recipient),
+            false,
+        ));
+        accounts.push(AccountMeta::new_readonly(
+            pda::blocked_account(&split.recipient).0,
+            false,
+        ));
+    }
+    Instruction {
+        program_id: ID,
+        accounts,
+        data: data(discriminator::SETTLE_SPLIT_PAYMENT, payload),
+    }
+}
+
+/// Give `amount` of the payment settled with `nonce` back from its payee `to` to its
+/// payer `from`. Signed by `to`.
+pub fn refund_payment(to: &Pubkey, from: &Pubkey, nonce: &[u8; 32], amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new_readonly(*to, true),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+            AccountMeta::new(pda::nonce(nonce).0, false),
+            AccountMeta::new(pda::xusdc_token_address(to), false),
+            AccountMeta::new(pda::xusdc_token_address(from), false),
+            AccountMeta::new_readonly(pda::blocked_account(from).0, false),
+            AccountMeta::new_readonly(pda::blocked_account(to).0, false),
+        ]),
+        data: data(discriminator::REFUND_PAYMENT, (nonce, amount)),
+    }
+}
+
+/// Exchange `amount` of a collateral for xUSDC
+pub fn deposit(user: &Pubkey, collateral: &Collateral, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(collateral.token_program, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(collateral.mint, false),
+            AccountMeta::new(collateral.token_address(user), false),
+            AccountMeta::new(pda::xusdc_token_address(user), false),
+            AccountMeta::new(collateral.vault(), false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new_readonly(pda::blocked_account(user).0, false),
+        ]),
+        data: data(discriminator::DEPOSIT, amount),
+    }
+}
+
+/// Exchange `amount` xUSDC for a collateral, queued if over the withdraw limits
+pub fn withdraw(user: &Pubkey, collateral: &Collateral, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pda::xusdc_token_address(user), false),
+            AccountMeta::new(collateral.token_address(user), false),
+            AccountMeta::new(collateral.vault(), false),
+            AccountMeta::new_readonly(collateral.mint, false),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(collateral.token_program, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new(pda::withdraw_limits().0, false),
+            AccountMeta::new(pda::wallet_outflow(user).0, false),
+            AccountMeta::new(pda::withdraw_request(user).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(pda::blocked_account(user).0, false),
+        ]),
+        data: data(discriminator::WITHDRAW, amount),
+    }
+}
+
+/// Register a collateral. Signed by the admin.
+pub fn add_collateral(
+    authority: &Pubkey,
+    collateral: &Collateral,
+    deposit_cap: u64,
+    withdraw_fee_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(collateral.mint, false),
+            AccountMeta::new(collateral.vault(), false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(collateral.token_program, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(
+            discriminator::ADD_COLLATERAL,
+            (deposit_cap, withdraw_fee_bps),
+        ),
+    }
+}
+
+/// Change a collateral's deposit cap and withdraw fee. Signed by the admin.
+pub fn update_collateral(
+    authority: &Pubkey,
+    collateral_mint: &Pubkey,
+    deposit_cap: u64,
+    withdraw_fee_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new_readonly(*collateral_mint, false),
+        ]),
+        data: data(
+            discriminator::UPDATE_COLLATERAL,
+            (deposit_cap, withdraw_fee_bps),
+        ),
+    }
+}
+
+/// Set one field of the xUSDC mint's metadata. Signed by the admin, who pays for any
+/// extra rent a longer value needs.
+pub fn update_metadata(authority: &Pubkey, field: MetadataField, value: &str) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(discriminator::UPDATE_METADATA, (field, value)),
+    }
+}
+
+/// Point the xUSDC mint's transfer hook at `program_id`, or turn it off with `None`.
+/// Signed by the admin.
+pub fn set_transfer_hook(authority: &Pubkey, program_id: Option<Pubkey>) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+        ]),
+        data: data(discriminator::SET_TRANSFER_HOOK, program_id),
+    }
+}
+
+/// Block `wallet` from settling, depositing and withdrawing. Signed by the admin, who
+/// pays for the blocklist entry.
+pub fn block_account(authority: &Pubkey, wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(pda::blocked_account(wallet).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(discriminator::BLOCK_ACCOUNT, wallet),
+    }
+}
+
+/// Lift a block, returning the entry's rent to the admin. Signed by the admin.
+pub fn unblock_account(authority: &Pubkey, wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(pda::blocked_account(wallet).0, false),
+        ]),
+        data: data(discriminator::UNBLOCK_ACCOUNT, wallet),
+    }
+}
+
+/// Freeze or thaw `wallet`'s xUSDC account. Signed by the admin.
+pub fn set_frozen(authority: &Pubkey, wallet: &Pubkey, frozen: bool) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+            AccountMeta::new(pda::xusdc_token_address(wallet), false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+        ]),
+        data: data(discriminator::SET_FROZEN, (wallet, frozen)),
+    }
+}
+
+/// Take `amount` xUSDC from a blocked `wallet` into `to`'s xUSDC account. Signed by
+/// the admin.
+pub fn seize(authority: &Pubkey, wallet: &Pubkey, to: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(pda::blocked_account(wallet).0, false),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new(pda::xusdc_token_address(wallet), false),
+            AccountMeta::new(pda::xusdc_token_address(to), false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+        ]),
+        data: data(discriminator::SEIZE, (wallet, amount)),
+    }
+}
+
+/// Prove the vaults cover the xUSDC supply. `vaults` must list every registered
+/// collateral's vault, in registry order.
+pub fn attest_reserves(vaults: &[Pubkey]) -> Instruction {
+    let mut accounts = with_event_cpi(vec![
+        AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+        AccountMeta::new_readonly(pda::collateral_registry().0, false),
+        AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+    ]);
+    accounts.extend(
+        vaults
+            .iter()
+            .map(|vault| AccountMeta::new_readonly(*vault, false)),
+    );
+    Instruction {
+        program_id: ID,
+        accounts,
+        data: data(discriminator::ATTEST_RESERVES, ()),
+    }
+}
+
+/// Signed by the admin, who pays for the limits account if it doesn't exist yet
+pub fn set_withdraw_limits(
+    authority: &Pubkey,
+    window_seconds: i64,
+    global_limit: u64,
+    wallet_limit: u64,
+    queue_delay_seconds: i64,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(pda::withdraw_limits().0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]),
+        data: data(
+            discriminator::SET_WITHDRAW_LIMITS,
+            (
+                window_seconds,
+                global_limit,
+                wallet_limit,
+                queue_delay_seconds,
+            ),
+        ),
+    }
+}
+
+/// Pay out a queued withdrawal once its delay has passed
+pub fn claim_withdraw(user: &Pubkey, collateral: &Collateral) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pda::withdraw_request(user).0, false),
+            AccountMeta::new(collateral.token_address(user), false),
+            AccountMeta::new(collateral.vault(), false),
+            AccountMeta::new_readonly(collateral.mint, false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(collateral.token_program, false),
+            AccountMeta::new(pda::collateral_registry().0, false),
+            AccountMeta::new(pda::withdraw_limits().0, false),
+            AccountMeta::new(pda::wallet_outflow(user).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(pda::blocked_account(user).0, false),
+        ]),
+        data: data(discriminator::CLAIM_WITHDRAW, ()),
+    }
+}
+
+/// Burn `amount` xUSDC and queue its redemption, which counts towards the withdraw
+/// limits only once claimed
+pub fn request_withdraw(user: &Pubkey, collateral: &Collateral, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pda::xusdc_token_address(user), false),
+            AccountMeta::new_readonly(collateral.vault(), false),
+            AccountMeta::new_readonly(collateral.mint, false),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(collateral.token_program, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(pda::collateral_registry().0, false),
+            AccountMeta::new_readonly(pda::withdraw_limits().0, false),
+            AccountMeta::new(pda::withdraw_request(user).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(pda::blocked_account(user).0, false),
+        ]),
+        data: data(discriminator::REQUEST_WITHDRAW, amount),
+    }
+}
+
+/// Drop a pending withdrawal and re-mint its xUSDC
+pub fn cancel_withdraw_request(user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: with_event_cpi(vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pda::withdraw_request(user).0, false),
+            AccountMeta::new(pda::xusdc_token_address(user), false),
+            AccountMeta::new(XUSDC_MINT_KEY, false),
+            AccountMeta::new_readonly(pda::transfer_authority().0, false),
+            AccountMeta::new_readonly(TOKEN_2022_PROGRAM_ID, false),
+            AccountMeta::new_readonly(pda::blocked_account(user).0, false),
+        ]),
+        data: data(discriminator::CANCEL_WITHDRAW_REQUEST, ()),
+    }
+}
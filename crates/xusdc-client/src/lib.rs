@@ -0,0 +1,38 @@
+//! Build xUSDC instructions and decode its accounts without depending on Anchor.
+//!
+//! Everything here mirrors the program byte for byte: instruction data is the Anchor
+//! discriminator followed by the Borsh arguments, and accounts are listed in the order
+//! of the program's `Accounts` structs.
+
+pub mod accounts;
+pub mod auth;
+pub mod error;
+pub mod hook;
+pub mod instruction;
+pub mod pda;
+pub mod types;
+pub mod verify;
+
+pub use auth::{FnSigner, Signer};
+pub use error::{ClientError, SignError};
+pub use types::{
+    resource_hash, splits_hash, MetadataField, MintMetadata, PaymentAuthorization, SettlePayload,
+    Split, SplitPaymentAuthorization, SplitRecipients, SplitSettlePayload,
+};
+pub use verify::{verify_payload, AccountFetcher, SettleError, VerifyContext, VerifyError};
+
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+
+pub const ID: Pubkey = pubkey!("AZzGDkysPRAZ9cfyRo1w4rHMS51NDDNT9XqHsC1WziLM");
+
+pub const ADMIN_KEY: Pubkey = pubkey!("CyJj5ejJAUveDXnLduJbkvwjxcmWJNqCuB9DR7AExrHn");
+pub const XUSDC_MINT_KEY: Pubkey = pubkey!("xUSD1YCoHxQGvNRhaSGnACc8Rj7gTEB3LmCUxSPLSzM");
+pub const USDC_MINT_KEY: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+/// The USDC mint a program built with the `devnet` feature accepts
+pub const DEVNET_USDC_MINT_KEY: Pubkey = pubkey!("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU");
+
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hR3hTrjtUPhcw4RG8nxUm9TL7m");
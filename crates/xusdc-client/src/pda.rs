@@ -0,0 +1,77 @@
+//! Program derived addresses. Every function returns the address with its bump.
+
+use solana_program::pubkey::Pubkey;
+
+use crate::{ID, TOKEN_2022_PROGRAM_ID, XUSDC_MINT_KEY};
+
+pub const TRANSFER_AUTHORITY_SEED: &[u8] = b"transfer-authority";
+pub const NONCE_SEED: &[u8] = b"nonce";
+pub const RENT_POOL_SEED: &[u8] = b"global_rent_pool";
+pub const RENT_CONTRIBUTOR_SEED: &[u8] = b"rent-contributor";
+pub const COLLATERAL_REGISTRY_SEED: &[u8] = b"collateral-registry";
+pub const WITHDRAW_LIMITS_SEED: &[u8] = b"withdraw-limits";
+pub const WALLET_OUTFLOW_SEED: &[u8] = b"wallet-outflow";
+pub const WITHDRAW_REQUEST_SEED: &[u8] = b"withdraw-request";
+pub const BLOCKED_ACCOUNT_SEED: &[u8] = b"blocked-account";
+/// Signs the self-invocations that carry `emit_cpi!` events
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+/// Mint and burn authority of xUSDC, permanent delegate, and owner of the collateral vaults
+pub fn transfer_authority() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TRANSFER_AUTHORITY_SEED], &ID)
+}
+
+/// Marks a payment authorization nonce as used
+pub fn nonce(nonce: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NONCE_SEED, nonce], &ID)
+}
+
+pub fn rent_pool() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RENT_POOL_SEED], &ID)
+}
+
+pub fn rent_contributor(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RENT_CONTRIBUTOR_SEED, user.as_ref()], &ID)
+}
+
+pub fn collateral_registry() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COLLATERAL_REGISTRY_SEED], &ID)
+}
+
+pub fn withdraw_limits() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[WITHDRAW_LIMITS_SEED], &ID)
+}
+
+pub fn wallet_outflow(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[WALLET_OUTFLOW_SEED, user.as_ref()], &ID)
+}
+
+pub fn withdraw_request(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[WITHDRAW_REQUEST_SEED, user.as_ref()], &ID)
+}
+
+/// Exists while the admin has `wallet` blocked
+pub fn blocked_account(wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BLOCKED_ACCOUNT_SEED, wallet.as_ref()], &ID)
+}
+
+pub fn event_authority() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &ID)
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    spl_associated_token_account_client::address::get_associated_token_address_with_program_id(
+        owner,
+        mint,
+        token_program,
+    )
+}
+
+pub fn xusdc_token_address(owner: &Pubkey) -> Pubkey {
+    associated_token_address(owner, &XUSDC_MINT_KEY, &TOKEN_2022_PROGRAM_ID)
+}
+
+/// The vault holding a collateral: the ATA of the transfer authority
+pub fn collateral_vault(mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    associated_token_address(&transfer_authority().0, mint, token_program)
+}
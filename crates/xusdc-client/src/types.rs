@@ -0,0 +1,155 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::hash::{hash, hashv};
+use solana_program::pubkey::Pubkey;
+
+/// Most recipients one split payment can pay
+pub const MAX_SPLIT_RECIPIENTS: usize = 5;
+
+/// Prefixed to a [`SplitPaymentAuthorization`]'s Borsh encoding to form the message
+/// the payer signs
+pub const SPLIT_PAYMENT_DOMAIN: &[u8] = b"xusdc:split-payment";
+
+/// Longest [`PaymentAuthorization::memo`] or [`SplitPaymentAuthorization::memo`] in
+/// bytes
+pub const MAX_MEMO_LEN: usize = 128;
+
+/// Prefixed to what [`resource_hash`] hashes
+pub const RESOURCE_DOMAIN: &[u8] = b"xusdc:resource";
+
+/// What the payer signs. The signed message is this struct's Borsh encoding.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PaymentAuthorization {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub nonce: [u8; 32],
+    pub valid_until: i64,
+    /// [`resource_hash`] of the HTTP request paid for, or all zeros when the payment
+    /// isn't for a request
+    pub resource_hash: [u8; 32],
+    /// Free text from the payer, at most [`MAX_MEMO_LEN`] bytes
+    pub memo: Option<String>,
+}
+
+/// The `settle_payment` argument
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SettlePayload {
+    pub payment_auth: PaymentAuthorization,
+    pub signature: [u8; 64],
+    pub signer_pubkey: [u8; 32],
+}
+
+/// One recipient of a split payment and its share
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Split {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// Who a split payment pays
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SplitRecipients {
+    /// The splits themselves
+    List(Vec<Split>),
+    /// [`splits_hash`] of the splits, which the facilitator reveals when settling
+    Commitment([u8; 32]),
+}
+
+/// What the payer signs to pay several recipients at once, e.g. a seller, a platform
+/// and an affiliate
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitPaymentAuthorization {
+    pub from: Pubkey,
+    pub to: SplitRecipients,
+    /// Sum of the split amounts
+    pub amount: u64,
+    pub nonce: [u8; 32],
+    pub valid_until: i64,
+    /// [`resource_hash`] of the HTTP request paid for, or all zeros when the payment
+    /// isn't for a request
+    pub resource_hash: [u8; 32],
+    /// Free text from the payer, at most [`MAX_MEMO_LEN`] bytes
+    pub memo: Option<String>,
+}
+
+/// The `settle_split_payment` argument
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitSettlePayload {
+    pub payment_auth: SplitPaymentAuthorization,
+    /// The splits a [`SplitRecipients::Commitment`] stands for; empty for a `List`
+    pub revealed: Vec<Split>,
+    pub signature: [u8; 64],
+    pub signer_pubkey: [u8; 32],
+}
+
+impl SplitSettlePayload {
+    /// The splits this payload pays, whether listed or revealed
+    pub fn splits(&self) -> &[Split] {
+        match &self.payment_auth.to {
+            SplitRecipients::List(splits) => splits,
+            SplitRecipients::Commitment(_) => &self.revealed,
+        }
+    }
+}
+
+/// Bind an authorization to one HTTP request: SHA-256 of [`RESOURCE_DOMAIN`] followed
+/// by the Borsh encoding of the method in upper case, the URL and the SHA-256 of the
+/// body.
+///
+/// The URL is hashed exactly as given, so payer and payee must agree on it; over x402
+/// it is the `resource` of the payment requirements. A request without a body hashes
+/// an empty one.
+pub fn resource_hash(method: &str, url: &str, body: &[u8]) -> [u8; 32] {
+    let request = (method.to_ascii_uppercase(), url, hash(body).to_bytes());
+    let request = borsh::to_vec(&request).expect("serializing to a Vec cannot fail");
+    hashv(&[RESOURCE_DOMAIN, &request]).to_bytes()
+}
+
+/// SHA-256 of the splits' Borsh encoding, as a [`SplitRecipients::Commitment`] holds it
+pub fn splits_hash(splits: &[Split]) -> [u8; 32] {
+    hash(&borsh::to_vec(splits).expect("serializing to a Vec cannot fail")).to_bytes()
+}
+
+/// The xUSDC mint's Token-2022 metadata, as `initialize` takes it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MintMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl MintMetadata {
+    /// Named and symbolized `xUSDC`, describing itself at `uri`
+    pub fn xusdc(uri: impl Into<String>) -> Self {
+        Self {
+            name: "xUSDC".to_string(),
+            symbol: "xUSDC".to_string(),
+            uri: uri.into(),
+        }
+    }
+}
+
+/// A metadata field `update_metadata` can change
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Name,
+    Symbol,
+    Uri,
+}
+
+impl PaymentAuthorization {
+    /// The bytes `from` signs
+    pub fn message(&self) -> Vec<u8> {
+        borsh::to_vec(self).expect("serializing to a Vec cannot fail")
+    }
+}
+
+impl SplitPaymentAuthorization {
+    /// The bytes `from` signs: [`SPLIT_PAYMENT_DOMAIN`] followed by the Borsh encoding
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = SPLIT_PAYMENT_DOMAIN.to_vec();
+        self.serialize(&mut message)
+            .expect("serializing to a Vec cannot fail");
+        message
+    }
+}
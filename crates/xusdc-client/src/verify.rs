@@ -0,0 +1,464 @@
+//! Checking a payment before settling it
+//!
+//! [`verify_payload`] runs the checks `settle_payment` runs, in the same order, so a
+//! facilitator can turn away a payload that would fail on-chain without paying fees.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use solana_account::Account;
+use solana_program::pubkey::Pubkey;
+
+use crate::pda;
+use crate::types::{SettlePayload, MAX_MEMO_LEN};
+use crate::{TOKEN_2022_PROGRAM_ID, XUSDC_MINT_KEY};
+
+/// Why `settle_payment` rejects a payload. Each variant is the program's settle
+/// `ErrorCode` variant of the same name and has the same error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[repr(u32)]
+pub enum SettleError {
+    #[error("Invalid payment authorization")]
+    InvalidPaymentAuthorization = 6000,
+    #[error("Payment has expired")]
+    PaymentExpired,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+    #[error("Unauthorized signer")]
+    UnauthorizedSigner,
+    #[error("Nonce already used")]
+    NonceAlreadyUsed,
+    #[error("Arithmetic overflow")]
+    Overflow,
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    #[error("Payment amount must be greater than zero")]
+    InvalidAmount,
+    #[error("Wallet is blocked")]
+    AccountBlocked,
+    #[error("Splits are empty, too many, or don't match the authorization")]
+    InvalidSplits,
+    #[error("Memo is too long")]
+    MemoTooLong,
+}
+
+impl SettleError {
+    pub const ALL: [Self; 12] = [
+        Self::InvalidPaymentAuthorization,
+        Self::PaymentExpired,
+        Self::InvalidSignature,
+        Self::InvalidPublicKey,
+        Self::UnauthorizedSigner,
+        Self::NonceAlreadyUsed,
+        Self::Overflow,
+        Self::InsufficientFunds,
+        Self::InvalidAmount,
+        Self::AccountBlocked,
+        Self::InvalidSplits,
+        Self::MemoTooLong,
+    ];
+
+    /// The custom program error code `settle_payment` fails with
+    pub const fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|error| error.code() == code)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError<E> {
+    #[error(transparent)]
+    Rejected(#[from] SettleError),
+    #[error("fetching {0}: {1}")]
+    Fetch(Pubkey, E),
+}
+
+/// Looks up accounts for [`verify_payload`], e.g. over RPC or from a local bank
+pub trait AccountFetcher {
+    type Error;
+
+    /// The account at `address`, or `None` if it does not exist
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, Self::Error>;
+}
+
+impl<T: AccountFetcher + ?Sized> AccountFetcher for &T {
+    type Error = T::Error;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, Self::Error> {
+        (**self).get_account(address)
+    }
+}
+
+impl AccountFetcher for HashMap<Pubkey, Account> {
+    type Error = Infallible;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, Self::Error> {
+        Ok(self.get(address).cloned())
+    }
+}
+
+/// What a payload is verified against
+#[derive(Debug, Clone)]
+pub struct VerifyContext<F> {
+    /// The cluster's Unix timestamp the payment will settle at
+    pub now: i64,
+    /// The most the facilitator accepts in one payment, if anything less than the
+    /// payer's balance
+    pub max_amount: Option<u64>,
+    pub accounts: F,
+}
+
+impl<F: AccountFetcher> VerifyContext<F> {
+    pub fn new(now: i64, accounts: F) -> Self {
+        Self {
+            now,
+            max_amount: None,
+            accounts,
+        }
+    }
+
+    fn fetch(&self, address: &Pubkey) -> Result<Option<Account>, VerifyError<F::Error>> {
+        self.accounts
+            .get_account(address)
+            .map_err(|e| VerifyError::Fetch(*address, e))
+    }
+
+    /// The xUSDC balance of `owner`'s token account, if settle could move xUSDC from or
+    /// to it
+    fn xusdc_balance(&self, owner: &Pubkey) -> Result<Option<u64>, VerifyError<F::Error>> {
+        let address = pda::xusdc_token_address(owner);
+        Ok(self
+            .fetch(&address)?
+            .and_then(|account| token_account_balance(&account, owner)))
+    }
+
+    /// Whether the admin has blocked `wallet`
+    fn is_blocked(&self, wallet: &Pubkey) -> Result<bool, VerifyError<F::Error>> {
+        let address = pda::blocked_account(wallet).0;
+        Ok(self
+            .fetch(&address)?
+            .is_some_and(|account| !account.data.is_empty()))
+    }
+}
+
+// The base SPL token account layout, shared by Token-2022
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_ACCOUNT_STATE_OFFSET: usize = 108;
+/// `AccountState::Initialized`, neither uninitialized nor frozen
+const TOKEN_ACCOUNT_INITIALIZED: u8 = 1;
+
+fn token_account_balance(account: &Account, owner: &Pubkey) -> Option<u64> {
+    let data = &account.data;
+    if account.owner != TOKEN_2022_PROGRAM_ID
+        || data.len() < TOKEN_ACCOUNT_LEN
+        || data[TOKEN_ACCOUNT_STATE_OFFSET] != TOKEN_ACCOUNT_INITIALIZED
+        || data[..32] != XUSDC_MINT_KEY.to_bytes()
+        || data[32..64] != owner.to_bytes()
+    {
+        return None;
+    }
+    Some(u64::from_le_bytes(data[64..72].try_into().unwrap()))
+}
+
+/// Check a payload the way `settle_payment` would if it landed at `ctx.now`, returning
+/// the error it would fail with.
+///
+/// A payer or payee without an xUSDC token account, or with a frozen one, is reported
+/// as `InvalidPaymentAuthorization`. On-chain, Anchor refuses to load a missing account
+/// before the instruction runs, and Token-2022 refuses to move xUSDC from or to a
+/// frozen one.
+pub fn verify_payload<F: AccountFetcher>(
+    payload: &SettlePayload,
+    ctx: &VerifyContext<F>,
+) -> Result<(), VerifyError<F::Error>> {
+    let payment_auth = &payload.payment_auth;
+
+    let nonce = pda::nonce(&payment_auth.nonce).0;
+    if ctx
+        .fetch(&nonce)?
+        .is_some_and(|account| !account.data.is_empty())
+    {
+        return Err(SettleError::NonceAlreadyUsed.into());
+    }
+
+    let balance = ctx
+        .xusdc_balance(&payment_auth.from)?
+        .ok_or(SettleError::InvalidPaymentAuthorization)?;
+    ctx.xusdc_balance(&payment_auth.to)?
+        .ok_or(SettleError::InvalidPaymentAuthorization)?;
+
+    if ctx.is_blocked(&payment_auth.from)? || ctx.is_blocked(&payment_auth.to)? {
+        return Err(SettleError::AccountBlocked.into());
+    }
+
+    if ctx.now > payment_auth.valid_until {
+        return Err(SettleError::PaymentExpired.into());
+    }
+
+    // The same verifier the program runs, so edge cases (non-canonical or
+    // small-order points) are judged alike
+    brine_ed25519::sig_verify(
+        &payload.signer_pubkey,
+        &payload.signature,
+        &payment_auth.message(),
+    )
+    .map_err(|_| SettleError::InvalidSignature)?;
+
+    if Pubkey::from(payload.signer_pubkey) != payment_auth.from {
+        return Err(SettleError::UnauthorizedSigner.into());
+    }
+
+    if payment_auth.amount == 0 || ctx.max_amount.is_some_and(|max| payment_auth.amount > max) {
+        return Err(SettleError::InvalidAmount.into());
+    }
+    if payment_auth.memo.as_ref().map_or(0, String::len) > MAX_MEMO_LEN {
+        return Err(SettleError::MemoTooLong.into());
+    }
+    if balance < payment_auth.amount {
+        return Err(SettleError::InsufficientFunds.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PaymentAuthorization;
+    use solana_keypair::Keypair;
+    use solana_signer::Signer as _;
+
+    fn token_account(owner: &Pubkey, amount: u64) -> Account {
+        let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+        data[..32].copy_from_slice(XUSDC_MINT_KEY.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[TOKEN_ACCOUNT_STATE_OFFSET] = TOKEN_ACCOUNT_INITIALIZED;
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: TOKEN_2022_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    struct Fixture {
+        payer: Keypair,
+        payee: Pubkey,
+        accounts: HashMap<Pubkey, Account>,
+    }
+
+    impl Fixture {
+        fn new(balance: u64) -> Self {
+            let payer = Keypair::new();
+            let payee = Pubkey::new_unique();
+            let accounts = HashMap::from([
+                (
+                    pda::xusdc_token_address(&payer.pubkey()),
+                    token_account(&payer.pubkey(), balance),
+                ),
+                (pda::xusdc_token_address(&payee), token_account(&payee, 0)),
+            ]);
+            Self {
+                payer,
+                payee,
+                accounts,
+            }
+        }
+
+        fn payload(&self, amount: u64) -> SettlePayload {
+            PaymentAuthorization::builder(self.payee, amount)
+                .valid_until(100)
+                .sign(&self.payer)
+                .unwrap()
+        }
+
+        fn verify(&self, payload: &SettlePayload) -> Result<(), SettleError> {
+            let mut ctx = VerifyContext::new(100, &self.accounts);
+            ctx.max_amount = Some(1_000);
+            verify_payload(payload, &ctx).map_err(|e| match e {
+                VerifyError::Rejected(error) => error,
+                VerifyError::Fetch(_, never) => match never {},
+            })
+        }
+    }
+
+    #[test]
+    fn test_verify_accepts_valid_payload() {
+        let fixture = Fixture::new(500);
+        assert_eq!(fixture.verify(&fixture.payload(500)), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_rejects_like_settle() {
+        let mut fixture = Fixture::new(500);
+
+        assert_eq!(
+            fixture.verify(&fixture.payload(0)),
+            Err(SettleError::InvalidAmount)
+        );
+        assert_eq!(
+            fixture.verify(&fixture.payload(1_001)),
+            Err(SettleError::InvalidAmount)
+        );
+        assert_eq!(
+            fixture.verify(&fixture.payload(501)),
+            Err(SettleError::InsufficientFunds)
+        );
+
+        let mut payload = fixture.payload(10);
+        payload.payment_auth.valid_until = 99;
+        // The signature no longer matches either, but expiry is checked first
+        assert_eq!(fixture.verify(&payload), Err(SettleError::PaymentExpired));
+
+        let mut payload = fixture.payload(10);
+        payload.payment_auth.amount = 20;
+        assert_eq!(fixture.verify(&payload), Err(SettleError::InvalidSignature));
+
+        let memo = "x".repeat(MAX_MEMO_LEN + 1);
+        let payload = PaymentAuthorization::builder(fixture.payee, 10)
+            .valid_until(100)
+            .memo(memo)
+            .sign(&fixture.payer)
+            .unwrap();
+        assert_eq!(fixture.verify(&payload), Err(SettleError::MemoTooLong));
+
+        // A valid signature, by someone other than the payer
+        let mallory = Keypair::new();
+        let mut payload = fixture.payload(10);
+        payload.signer_pubkey = mallory.pubkey().to_bytes();
+        payload.signature = mallory.sign_message(&payload.payment_auth.message()).into();
+        assert_eq!(
+            fixture.verify(&payload),
+            Err(SettleError::UnauthorizedSigner)
+        );
+
+        let mut payload = fixture.payload(10);
+        payload.payment_auth.to = Pubkey::new_unique();
+        assert_eq!(
+            fixture.verify(&payload),
+            Err(SettleError::InvalidPaymentAuthorization)
+        );
+
+        let payload = fixture.payload(10);
+        fixture.accounts.insert(
+            pda::nonce(&payload.payment_auth.nonce).0,
+            Account {
+                lamports: 1,
+                data: vec![0; 16],
+                owner: crate::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        assert_eq!(fixture.verify(&payload), Err(SettleError::NonceAlreadyUsed));
+
+        let payload = fixture.payload(10);
+        fixture.accounts.insert(
+            pda::blocked_account(&fixture.payee).0,
+            Account {
+                lamports: 1,
+                data: vec![0; 48],
+                owner: crate::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        assert_eq!(fixture.verify(&payload), Err(SettleError::AccountBlocked));
+    }
+
+    #[test]
+    fn test_verify_rejects_foreign_token_accounts() {
+        let mut fixture = Fixture::new(500);
+        let address = pda::xusdc_token_address(&fixture.payee);
+        let account = fixture.accounts.get_mut(&address).unwrap();
+        account.owner = crate::TOKEN_PROGRAM_ID;
+        assert_eq!(
+            fixture.verify(&fixture.payload(10)),
+            Err(SettleError::InvalidPaymentAuthorization)
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_frozen_token_accounts() {
+        // `AccountState::Frozen`
+        const FROZEN: u8 = 2;
+        let fixture = Fixture::new(500);
+        let payer = fixture.payer.pubkey();
+        for wallet in [payer, fixture.payee] {
+            let mut accounts = fixture.accounts.clone();
+            let address = pda::xusdc_token_address(&wallet);
+            accounts.get_mut(&address).unwrap().data[TOKEN_ACCOUNT_STATE_OFFSET] = FROZEN;
+            let ctx = VerifyContext::new(100, &accounts);
+            assert!(
+                matches!(
+                    verify_payload(&fixture.payload(10), &ctx),
+                    Err(VerifyError::Rejected(
+                        SettleError::InvalidPaymentAuthorization
+                    ))
+                ),
+                "{wallet}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_blocked_wallets() {
+        let fixture = Fixture::new(500);
+        let payer = fixture.payer.pubkey();
+        for wallet in [payer, fixture.payee] {
+            let mut accounts = fixture.accounts.clone();
+            accounts.insert(
+                pda::blocked_account(&wallet).0,
+                Account {
+                    lamports: 1,
+                    data: vec![0; 48],
+                    owner: crate::ID,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            );
+            let ctx = VerifyContext::new(100, &accounts);
+            assert!(
+                matches!(
+                    verify_payload(&fixture.payload(10), &ctx),
+                    Err(VerifyError::Rejected(SettleError::AccountBlocked))
+                ),
+                "{wallet}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_reports_fetch_errors() {
+        struct Offline;
+        impl AccountFetcher for Offline {
+            type Error = &'static str;
+            fn get_account(&self, _: &Pubkey) -> Result<Option<Account>, Self::Error> {
+                Err("offline")
+            }
+        }
+        let fixture = Fixture::new(500);
+        let payload = fixture.payload(10);
+        assert!(matches!(
+            verify_payload(&payload, &VerifyContext::new(0, Offline)),
+            Err(VerifyError::Fetch(address, "offline"))
+                if address == pda::nonce(&payload.payment_auth.nonce).0
+        ));
+    }
+
+    #[test]
+    fn test_error_codes_round_trip() {
+        for error in SettleError::ALL {
+            assert_eq!(SettleError::from_code(error.code()), Some(error));
+        }
+        assert_eq!(SettleError::AccountBlocked.code(), 6009);
+        assert_eq!(SettleError::MemoTooLong.code(), 6011);
+        assert_eq!(SettleError::from_code(6012), None);
+    }
+}
@@ -0,0 +1,862 @@
+//! Checks every builder against the account metas and data Anchor generates for the
+//! program, so the client cannot drift from it.
+
+use anchor_lang::{AccountSerialize, InstructionData, ToAccountMetas};
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use xusdc::state::{
+    ADMIN_KEY as PROGRAM_ADMIN_KEY, USDC_MINT_KEY as PROGRAM_USDC_MINT_KEY,
+    XUSDC_MINT_KEY as PROGRAM_XUSDC_MINT_KEY,
+};
+use xusdc_client::accounts::{
+    BlockedAccount, ContributorRentInfo, Nonce, ProgramAccount, NONCE_VERSION,
+};
+use xusdc_client::instruction::{self as ix, Collateral};
+use xusdc_client::{
+    hook, pda, splits_hash, ClientError, MetadataField, MintMetadata, PaymentAuthorization,
+    SettleError, SettlePayload, Split, SplitPaymentAuthorization, SplitRecipients,
+    SplitSettlePayload,
+};
+use xusdc_client::{ADMIN_KEY, ID, TOKEN_2022_PROGRAM_ID, USDC_MINT_KEY, XUSDC_MINT_KEY};
+
+fn anchor_ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: xusdc::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn hook_ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: xusdc_hook::ID,
+        ..anchor_ix(accounts, data)
+    }
+}
+
+fn event_authority() -> Pubkey {
+    pda::event_authority().0
+}
+
+fn token_2022_collateral() -> Collateral {
+    Collateral {
+        mint: Pubkey::new_unique(),
+        token_program: TOKEN_2022_PROGRAM_ID,
+    }
+}
+
+#[test]
+fn test_constants_match_program() {
+    assert_eq!(ID, xusdc::ID);
+    assert_eq!(ADMIN_KEY, PROGRAM_ADMIN_KEY);
+    assert_eq!(XUSDC_MINT_KEY, PROGRAM_XUSDC_MINT_KEY);
+    assert_eq!(USDC_MINT_KEY, PROGRAM_USDC_MINT_KEY);
+    assert_eq!(
+        pda::TRANSFER_AUTHORITY_SEED,
+        xusdc::state::TRANSFER_AUTHORITY_SEED
+    );
+    assert_eq!(pda::NONCE_SEED, xusdc::state::NONCE_SEED);
+    assert_eq!(pda::RENT_POOL_SEED, xusdc::state::RENT_POOL_SEED);
+    assert_eq!(
+        pda::RENT_CONTRIBUTOR_SEED,
+        xusdc::state::RENT_CONTRIBUTOR_SEED
+    );
+    assert_eq!(
+        pda::COLLATERAL_REGISTRY_SEED,
+        xusdc::state::COLLATERAL_REGISTRY_SEED
+    );
+    assert_eq!(
+        pda::WITHDRAW_LIMITS_SEED,
+        xusdc::state::WITHDRAW_LIMITS_SEED
+    );
+    assert_eq!(pda::WALLET_OUTFLOW_SEED, xusdc::state::WALLET_OUTFLOW_SEED);
+    assert_eq!(
+        pda::WITHDRAW_REQUEST_SEED,
+        xusdc::state::WITHDRAW_REQUEST_SEED
+    );
+    assert_eq!(
+        pda::BLOCKED_ACCOUNT_SEED,
+        xusdc::state::BLOCKED_ACCOUNT_SEED
+    );
+}
+
+#[test]
+fn test_initialize_matches_program() {
+    let authority = ADMIN_KEY;
+    let metadata = MintMetadata::xusdc("https://example.com/xusdc.json");
+    let client = ix::initialize(&authority, &USDC_MINT_KEY, &metadata);
+    let anchor = anchor_ix(
+        xusdc::accounts::Initialize {
+            authority,
+            system_program: anchor_lang::system_program::ID,
+            token_program: TOKEN_2022_PROGRAM_ID,
+            xusdc_mint: XUSDC_MINT_KEY,
+            usdc_mint: USDC_MINT_KEY,
+            usdc_global_ata: Collateral::USDC.vault(),
+            transfer_authority: pda::transfer_authority().0,
+            associated_token_program: xusdc_client::ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenkeg: xusdc_client::TOKEN_PROGRAM_ID,
+            collateral_registry: pda::collateral_registry().0,
+            withdraw_limits: pda::withdraw_limits().0,
+        },
+        xusdc::instruction::Initialize {
+            name: metadata.name.clone(),
+            symbol: metadata.symbol.clone(),
+            uri: metadata.uri.clone(),
+        },
+    );
+    assert_eq!(client.data, anchor.data);
+    // The program does not mark the USDC vault `mut`, but the associated token program
+    // has to write it, so the client does
+    let keys = |ix: &Instruction| {
+        ix.accounts
+            .iter()
+            .map(|meta| (meta.pubkey, meta.is_signer))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&client), keys(&anchor));
+    assert!(client.accounts[5].is_writable);
+}
+
+#[test]
+fn test_init_collateral_registry_matches_program() {
+    assert_eq!(
+        ix::init_collateral_registry(&ADMIN_KEY, &USDC_MINT_KEY),
+        anchor_ix(
+            xusdc::accounts::InitCollateralRegistry {
+                authority: ADMIN_KEY,
+                collateral_registry: pda::collateral_registry().0,
+                xusdc_mint: XUSDC_MINT_KEY,
+                usdc_mint: USDC_MINT_KEY,
+                usdc_vault: Collateral::USDC.vault(),
+                transfer_authority: pda::transfer_authority().0,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                tokenkeg: xusdc_client::TOKEN_PROGRAM_ID,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::InitCollateralRegistry {}
+        )
+    );
+}
+
+#[test]
+fn test_rent_instructions_match_program() {
+    let user = Pubkey::new_unique();
+    let accounts = || xusdc::accounts::ContributeRent {
+        user,
+        user_rent_info: pda::rent_contributor(&user).0,
+        global_rent_pool: pda::rent_pool().0,
+        system_program: anchor_lang::system_program::ID,
+        event_authority: event_authority(),
+        program: ID,
+    };
+    assert_eq!(
+        ix::contribute_rent(&user, 42),
+        anchor_ix(
+            accounts(),
+            xusdc::instruction::ContributeRent { amount: 42 }
+        )
+    );
+    assert_eq!(
+        ix::withdraw_rent(&user, 7),
+        anchor_ix(
+            xusdc::accounts::WithdrawRent {
+                user,
+                user_rent_info: pda::rent_contributor(&user).0,
+                global_rent_pool: pda::rent_pool().0,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::WithdrawRent { amount: 7 }
+        )
+    );
+
+    let nonce_account = pda::nonce(&[3; 32]).0;
+    assert_eq!(
+        ix::garbage_collect(&nonce_account),
+        anchor_ix(
+            xusdc::accounts::GarbageCollect {
+                nonce_account,
+                global_rent_pool: pda::rent_pool().0,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::GarbageCollect {}
+        )
+    );
+    assert_eq!(
+        ix::migrate_nonce(&nonce_account),
+        anchor_ix(
+            xusdc::accounts::MigrateNonce {
+                nonce_account,
+                rent_pool: pda::rent_pool().0,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::MigrateNonce {}
+        )
+    );
+    assert_eq!(
+        ix::get_payment(&[3; 32]),
+        anchor_ix(
+            xusdc::accounts::GetPayment { nonce_account },
+            xusdc::instruction::GetPayment { nonce: [3; 32] }
+        )
+    );
+}
+
+#[test]
+fn test_settle_payment_matches_program() {
+    let facilitator = Pubkey::new_unique();
+    let payload = SettlePayload {
+        payment_auth: PaymentAuthorization {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount: 1_000_000,
+            nonce: [9; 32],
+            valid_until: 1_700_000_000,
+            resource_hash: xusdc_client::resource_hash("GET", "https://example.com/", b""),
+            memo: Some("order 42".to_string()),
+        },
+        signature: [4; 64],
+        signer_pubkey: [5; 32],
+    };
+    let program_payload = xusdc::ixs::SettlePayload {
+        payment_auth: xusdc::ixs::PaymentAuthorization {
+            from: payload.payment_auth.from,
+            to: payload.payment_auth.to,
+            amount: payload.payment_auth.amount,
+            nonce: payload.payment_auth.nonce,
+            valid_until: payload.payment_auth.valid_until,
+            resource_hash: payload.payment_auth.resource_hash,
+            memo: payload.payment_auth.memo.clone(),
+        },
+        signature: payload.signature,
+        signer_pubkey: payload.signer_pubkey,
+    };
+
+    assert_eq!(
+        ix::settle_payment(&facilitator, &payload),
+        anchor_ix(
+            xusdc::accounts::SettlePayment {
+                facilitator,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                xusdc_mint: XUSDC_MINT_KEY,
+                from_user_xusdc_ata: pda::xusdc_token_address(&payload.payment_auth.from),
+                to_user_xusdc_ata: pda::xusdc_token_address(&payload.payment_auth.to),
+                transfer_authority: pda::transfer_authority().0,
+                nonce_account: pda::nonce(&payload.payment_auth.nonce).0,
+                rent_pool: pda::rent_pool().0,
+                system_program: anchor_lang::system_program::ID,
+                from_blocked: pda::blocked_account(&payload.payment_auth.from).0,
+                to_blocked: pda::blocked_account(&payload.payment_auth.to).0,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::SettlePayment {
+                payload: program_payload.clone(),
+            }
+        )
+    );
+    // The signed message is the same Borsh encoding
+    assert_eq!(
+        payload.payment_auth.message(),
+        anchor_lang::AnchorSerialize::try_to_vec(&program_payload.payment_auth).unwrap()
+    );
+    assert_eq!(xusdc_client::types::MAX_MEMO_LEN, xusdc::ixs::MAX_MEMO_LEN);
+}
+
+#[test]
+fn test_settle_split_payment_matches_program() {
+    let facilitator = Pubkey::new_unique();
+    let splits = [
+        (Pubkey::new_unique(), 900_000),
+        (Pubkey::new_unique(), 100_000),
+    ]
+    .map(|(recipient, amount)| Split { recipient, amount });
+    let program_splits = splits.map(|split| xusdc::ixs::Split {
+        recipient: split.recipient,
+        amount: split.amount,
+    });
+    let payload = SplitSettlePayload {
+        payment_auth: SplitPaymentAuthorization {
+            from: Pubkey::new_unique(),
+            to: SplitRecipients::Commitment(splits_hash(&splits)),
+            amount: 1_000_000,
+            nonce: [9; 32],
+            valid_until: 1_700_000_000,
+            resource_hash: xusdc_client::resource_hash("POST", "https://example.com/", b"{}"),
+            memo: Some("order 42".to_string()),
+        },
+        revealed: splits.to_vec(),
+        signature: [4; 64],
+        signer_pubkey: [5; 32],
+    };
+    let program_payload = xusdc::ixs::SplitSettlePayload {
+        payment_auth: xusdc::ixs::SplitPaymentAuthorization {
+            from: payload.payment_auth.from,
+            to: xusdc::ixs::SplitRecipients::Commitment(
+                xusdc::ixs::splits_hash(&program_splits).unwrap(),
+            ),
+            amount: payload.payment_auth.amount,
+            nonce: payload.payment_auth.nonce,
+            valid_until: payload.payment_auth.valid_until,
+            resource_hash: payload.payment_auth.resource_hash,
+            memo: payload.payment_auth.memo.clone(),
+        },
+        revealed: program_splits.to_vec(),
+        signature: payload.signature,
+        signer_pubkey: payload.signer_pubkey,
+    };
+
+    let mut expected = anchor_ix(
+        xusdc::accounts::SettleSplitPayment {
+            facilitator,
+            token_program: TOKEN_2022_PROGRAM_ID,
+            xusdc_mint: XUSDC_MINT_KEY,
+            from_user_xusdc_ata: pda::xusdc_token_address(&payload.payment_auth.from),
+            transfer_authority: pda::transfer_authority().0,
+            nonce_account: pda::nonce(&payload.payment_auth.nonce).0,
+            rent_pool: pda::rent_pool().0,
+            system_program: anchor_lang::system_program::ID,
+            from_blocked: pda::blocked_account(&payload.payment_auth.from).0,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        xusdc::instruction::SettleSplitPayment {
+            payload: program_payload.clone(),
+        },
+    );
+    for split in &splits {
+        expected.accounts.extend([
+            AccountMeta::new(pda::xusdc_token_address(&split.recipient), false),
+            AccountMeta::new_readonly(pda::blocked_account(&split.recipient).0, false),
+        ]);
+    }
+    assert_eq!(ix::settle_split_payment(&facilitator, &payload), expected);
+
+    // Listed splits encode the same way, and the message carries the program's domain
+    let listed = SplitPaymentAuthorization {
+        to: SplitRecipients::List(splits.to_vec()),
+        ..payload.payment_auth.clone()
+    };
+    let program_listed = xusdc::ixs::SplitPaymentAuthorization {
+        to: xusdc::ixs::SplitRecipients::List(program_splits.to_vec()),
+        ..program_payload.payment_auth.clone()
+    };
+    for (client, program) in [
+        (&payload.payment_auth, &program_payload.payment_auth),
+        (&listed, &program_listed),
+    ] {
+        assert_eq!(
+            client.message(),
+            [
+                xusdc::ixs::SPLIT_PAYMENT_DOMAIN,
+                &anchor_lang::AnchorSerialize::try_to_vec(program).unwrap(),
+            ]
+            .concat()
+        );
+    }
+    assert_eq!(
+        xusdc_client::types::MAX_SPLIT_RECIPIENTS,
+        xusdc::ixs::MAX_SPLIT_RECIPIENTS
+    );
+}
+
+#[test]
+fn test_collateral_instructions_match_program() {
+    let user = Pubkey::new_unique();
+    for collateral in [Collateral::USDC, token_2022_collateral()] {
+        assert_eq!(
+            ix::deposit(&user, &collateral, 5),
+            anchor_ix(
+                xusdc::accounts::Deposit {
+                    user,
+                    collateral_token_program: collateral.token_program,
+                    token_program: TOKEN_2022_PROGRAM_ID,
+                    xusdc_mint: XUSDC_MINT_KEY,
+                    collateral_mint: collateral.mint,
+                    user_collateral_ata: collateral.token_address(&user),
+                    user_xusdc_ata: pda::xusdc_token_address(&user),
+                    collateral_vault: collateral.vault(),
+                    transfer_authority: pda::transfer_authority().0,
+                    collateral_registry: pda::collateral_registry().0,
+                    user_blocked: pda::blocked_account(&user).0,
+                    event_authority: event_authority(),
+                    program: ID,
+                },
+                xusdc::instruction::Deposit { amount: 5 }
+            )
+        );
+        assert_eq!(
+            ix::withdraw(&user, &collateral, 6),
+            anchor_ix(
+                xusdc::accounts::Withdraw {
+                    user,
+                    user_xusdc_ata: pda::xusdc_token_address(&user),
+                    user_collateral_ata: collateral.token_address(&user),
+                    collateral_vault: collateral.vault(),
+                    collateral_mint: collateral.mint,
+                    xusdc_mint: XUSDC_MINT_KEY,
+                    transfer_authority: pda::transfer_authority().0,
+                    collateral_token_program: collateral.token_program,
+                    token_program: TOKEN_2022_PROGRAM_ID,
+                    collateral_registry: pda::collateral_registry().0,
+                    withdraw_limits: pda::withdraw_limits().0,
+                    wallet_outflow: pda::wallet_outflow(&user).0,
+                    withdraw_request: pda::withdraw_request(&user).0,
+                    system_program: anchor_lang::system_program::ID,
+                    user_blocked: pda::blocked_account(&user).0,
+                    event_authority: event_authority(),
+                    program: ID,
+                },
+                xusdc::instruction::Withdraw { amount: 6 }
+            )
+        );
+        assert_eq!(
+            ix::request_withdraw(&user, &collateral, 7),
+            anchor_ix(
+                xusdc::accounts::RequestWithdraw {
+                    user,
+                    user_xusdc_ata: pda::xusdc_token_address(&user),
+                    collateral_vault: collateral.vault(),
+                    collateral_mint: collateral.mint,
+                    xusdc_mint: XUSDC_MINT_KEY,
+                    transfer_authority: pda::transfer_authority().0,
+                    collateral_token_program: collateral.token_program,
+                    token_program: TOKEN_2022_PROGRAM_ID,
+                    collateral_registry: pda::collateral_registry().0,
+                    withdraw_limits: pda::withdraw_limits().0,
+                    withdraw_request: pda::withdraw_request(&user).0,
+                    system_program: anchor_lang::system_program::ID,
+                    user_blocked: pda::blocked_account(&user).0,
+                    event_authority: event_authority(),
+                    program: ID,
+                },
+                xusdc::instruction::RequestWithdraw { amount: 7 }
+            )
+        );
+        assert_eq!(
+            ix::claim_withdraw(&user, &collateral),
+            anchor_ix(
+                xusdc::accounts::ClaimWithdraw {
+                    user,
+                    withdraw_request: pda::withdraw_request(&user).0,
+                    user_collateral_ata: collateral.token_address(&user),
+                    collateral_vault: collateral.vault(),
+                    collateral_mint: collateral.mint,
+                    transfer_authority: pda::transfer_authority().0,
+                    collateral_token_program: collateral.token_program,
+                    collateral_registry: pda::collateral_registry().0,
+                    withdraw_limits: pda::withdraw_limits().0,
+                    wallet_outflow: pda::wallet_outflow(&user).0,
+                    system_program: anchor_lang::system_program::ID,
+                    user_blocked: pda::blocked_account(&user).0,
+                    event_authority: event_authority(),
+                    program: ID,
+                },
+                xusdc::instruction::ClaimWithdraw {}
+            )
+        );
+    }
+
+    assert_eq!(
+        ix::cancel_withdraw_request(&user),
+        anchor_ix(
+            xusdc::accounts::CancelWithdrawRequest {
+                user,
+                withdraw_request: pda::withdraw_request(&user).0,
+                user_xusdc_ata: pda::xusdc_token_address(&user),
+                xusdc_mint: XUSDC_MINT_KEY,
+                transfer_authority: pda::transfer_authority().0,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                user_blocked: pda::blocked_account(&user).0,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::CancelWithdrawRequest {}
+        )
+    );
+}
+
+#[test]
+fn test_admin_instructions_match_program() {
+    let collateral = token_2022_collateral();
+    assert_eq!(
+        ix::add_collateral(&ADMIN_KEY, &collateral, 100, 25),
+        anchor_ix(
+            xusdc::accounts::AddCollateral {
+                authority: ADMIN_KEY,
+                collateral_registry: pda::collateral_registry().0,
+                xusdc_mint: XUSDC_MINT_KEY,
+                collateral_mint: collateral.mint,
+                collateral_vault: collateral.vault(),
+                transfer_authority: pda::transfer_authority().0,
+                collateral_token_program: collateral.token_program,
+                associated_token_program: xusdc_client::ASSOCIATED_TOKEN_PROGRAM_ID,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::AddCollateral {
+                deposit_cap: 100,
+                withdraw_fee_bps: 25,
+            }
+        )
+    );
+    assert_eq!(
+        ix::update_collateral(&ADMIN_KEY, &collateral.mint, 200, 50),
+        anchor_ix(
+            xusdc::accounts::UpdateCollateral {
+                authority: ADMIN_KEY,
+                collateral_registry: pda::collateral_registry().0,
+                collateral_mint: collateral.mint,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::UpdateCollateral {
+                deposit_cap: 200,
+                withdraw_fee_bps: 50,
+            }
+        )
+    );
+    assert_eq!(
+        ix::update_metadata(&ADMIN_KEY, MetadataField::Symbol, "XUSDC"),
+        anchor_ix(
+            xusdc::accounts::UpdateMetadata {
+                authority: ADMIN_KEY,
+                xusdc_mint: XUSDC_MINT_KEY,
+                transfer_authority: pda::transfer_authority().0,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::UpdateMetadata {
+                field: xusdc::state::MetadataField::Symbol,
+                value: "XUSDC".to_string(),
+            }
+        )
+    );
+    assert_eq!(
+        ix::set_transfer_hook(&ADMIN_KEY, Some(hook::HOOK_PROGRAM_ID)),
+        anchor_ix(
+            xusdc::accounts::SetTransferHook {
+                authority: ADMIN_KEY,
+                xusdc_mint: XUSDC_MINT_KEY,
+                transfer_authority: pda::transfer_authority().0,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::SetTransferHook {
+                program_id: Some(hook::HOOK_PROGRAM_ID),
+            }
+        )
+    );
+    let (to, from) = (Pubkey::new_unique(), Pubkey::new_unique());
+    assert_eq!(
+        ix::refund_payment(&to, &from, &[7; 32], 9),
+        anchor_ix(
+            xusdc::accounts::RefundPayment {
+                payee: to,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                xusdc_mint: XUSDC_MINT_KEY,
+                nonce_account: pda::nonce(&[7; 32]).0,
+                payee_xusdc_ata: pda::xusdc_token_address(&to),
+                payer_xusdc_ata: pda::xusdc_token_address(&from),
+                from_blocked: pda::blocked_account(&from).0,
+                to_blocked: pda::blocked_account(&to).0,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::RefundPayment {
+                nonce: [7; 32],
+                amount: 9,
+            }
+        )
+    );
+    let wallet = Pubkey::new_unique();
+    assert_eq!(
+        ix::block_account(&ADMIN_KEY, &wallet),
+        anchor_ix(
+            xusdc::accounts::BlockAccount {
+                authority: ADMIN_KEY,
+                blocked_account: pda::blocked_account(&wallet).0,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::BlockAccount { wallet }
+        )
+    );
+    assert_eq!(
+        ix::unblock_account(&ADMIN_KEY, &wallet),
+        anchor_ix(
+            xusdc::accounts::UnblockAccount {
+                authority: ADMIN_KEY,
+                blocked_account: pda::blocked_account(&wallet).0,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::UnblockAccount { wallet }
+        )
+    );
+    assert_eq!(
+        ix::set_frozen(&ADMIN_KEY, &wallet, true),
+        anchor_ix(
+            xusdc::accounts::SetFrozen {
+                authority: ADMIN_KEY,
+                xusdc_mint: XUSDC_MINT_KEY,
+                token_account: pda::xusdc_token_address(&wallet),
+                transfer_authority: pda::transfer_authority().0,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::SetFrozen {
+                wallet,
+                frozen: true,
+            }
+        )
+    );
+    assert_eq!(
+        ix::seize(&ADMIN_KEY, &wallet, &ADMIN_KEY, 9),
+        anchor_ix(
+            xusdc::accounts::Seize {
+                authority: ADMIN_KEY,
+                blocked_account: pda::blocked_account(&wallet).0,
+                xusdc_mint: XUSDC_MINT_KEY,
+                from_token_account: pda::xusdc_token_address(&wallet),
+                to_token_account: pda::xusdc_token_address(&ADMIN_KEY),
+                transfer_authority: pda::transfer_authority().0,
+                token_program: TOKEN_2022_PROGRAM_ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::Seize { wallet, amount: 9 }
+        )
+    );
+    assert_eq!(
+        ix::set_withdraw_limits(&ADMIN_KEY, 60, 1, 2, 30),
+        anchor_ix(
+            xusdc::accounts::SetWithdrawLimits {
+                authority: ADMIN_KEY,
+                withdraw_limits: pda::withdraw_limits().0,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: event_authority(),
+                program: ID,
+            },
+            xusdc::instruction::SetWithdrawLimits {
+                window_seconds: 60,
+                global_limit: 1,
+                wallet_limit: 2,
+                queue_delay_seconds: 30,
+            }
+        )
+    );
+
+    let vaults = [Collateral::USDC.vault(), collateral.vault()];
+    let mut anchor = anchor_ix(
+        xusdc::accounts::AttestReserves {
+            xusdc_mint: XUSDC_MINT_KEY,
+            collateral_registry: pda::collateral_registry().0,
+            token_program: TOKEN_2022_PROGRAM_ID,
+            event_authority: event_authority(),
+            program: ID,
+        },
+        xusdc::instruction::AttestReserves {},
+    );
+    anchor.accounts.extend(
+        vaults
+            .iter()
+            .map(|vault| solana_program::instruction::AccountMeta::new_readonly(*vault, false)),
+    );
+    assert_eq!(ix::attest_reserves(&vaults), anchor);
+}
+
+#[test]
+fn test_hook_instructions_match_program() {
+    assert_eq!(hook::HOOK_PROGRAM_ID, xusdc_hook::ID);
+    assert_eq!(
+        hook::EXTRA_ACCOUNT_METAS_SEED,
+        xusdc_hook::state::EXTRA_ACCOUNT_METAS_SEED
+    );
+    assert_eq!(hook::POLICY_SEED, xusdc_hook::state::POLICY_SEED);
+    assert_eq!(XUSDC_MINT_KEY, xusdc_hook::state::XUSDC_MINT_KEY);
+    assert_eq!(ADMIN_KEY, xusdc_hook::state::ADMIN_KEY);
+
+    assert_eq!(
+        hook::initialize_extra_account_meta_list(&ADMIN_KEY),
+        hook_ix(
+            xusdc_hook::accounts::InitializeExtraAccountMetaList {
+                authority: ADMIN_KEY,
+                extra_account_meta_list: hook::extra_account_metas().0,
+                mint: XUSDC_MINT_KEY,
+                system_program: anchor_lang::system_program::ID,
+            },
+            xusdc_hook::instruction::InitializeExtraAccountMetaList {},
+        )
+    );
+    let wallet = Pubkey::new_unique();
+    assert_eq!(
+        hook::set_policy(&ADMIN_KEY, &wallet, 5, 60),
+        hook_ix(
+            xusdc_hook::accounts::SetPolicy {
+                authority: ADMIN_KEY,
+                policy: hook::policy(&wallet).0,
+                system_program: anchor_lang::system_program::ID,
+                event_authority: Pubkey::find_program_address(
+                    &[pda::EVENT_AUTHORITY_SEED],
+                    &xusdc_hook::ID
+                )
+                .0,
+                program: xusdc_hook::ID,
+            },
+            xusdc_hook::instruction::SetPolicy {
+                wallet,
+                spend_limit: 5,
+                window_seconds: 60,
+            },
+        )
+    );
+}
+
+#[test]
+fn test_account_deserializers_match_program() {
+    let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let program_nonce = xusdc::state::Nonce {
+        expires_at: 1_700_000_000,
+        version: xusdc::state::NONCE_VERSION,
+        from,
+        to,
+        amount: 5,
+        refunded: 2,
+        facilitator: ADMIN_KEY,
+        settled_at: 1_699_999_000,
+    };
+    let mut data = vec![];
+    program_nonce.try_serialize(&mut data).unwrap();
+    let nonce = Nonce {
+        expires_at: 1_700_000_000,
+        version: NONCE_VERSION,
+        from,
+        to,
+        amount: 5,
+        refunded: 2,
+        facilitator: ADMIN_KEY,
+        settled_at: 1_699_999_000,
+    };
+    assert_eq!(NONCE_VERSION, xusdc::state::NONCE_VERSION);
+    assert_eq!(Nonce::try_from_account(&ID, &data).unwrap(), nonce);
+    let return_data = anchor_lang::prelude::borsh::to_vec(&Some(program_nonce.clone())).unwrap();
+    assert_eq!(ix::decode_payment(&return_data).unwrap(), Some(nonce));
+    assert_eq!(ix::decode_payment(&[0]).unwrap(), None);
+
+    // Version 0 decodes the same way on both sides, from its length
+    let v0 = [&Nonce::DISCRIMINATOR[..], &1_700_000_000i64.to_le_bytes()].concat();
+    let program_nonce = xusdc::state::Nonce::read(&v0).unwrap();
+    let return_data = anchor_lang::prelude::borsh::to_vec(&Some(program_nonce)).unwrap();
+    let nonce = Nonce::try_from_bytes(&v0).unwrap();
+    assert_eq!(ix::decode_payment(&return_data).unwrap(), Some(nonce));
+    assert_eq!((nonce.version, nonce.amount), (0, 0));
+    assert_eq!(Nonce::expires_at(&v0).unwrap(), 1_700_000_000);
+    assert_eq!(Nonce::expires_at(&data).unwrap(), 1_700_000_000);
+    // The rent of a closed account may be topped up, leaving zeroed data behind
+    assert!(matches!(
+        Nonce::try_from_bytes(&[0; 16]),
+        Err(ClientError::InvalidDiscriminator("Nonce"))
+    ));
+    assert!(matches!(
+        Nonce::try_from_account(&Pubkey::new_unique(), &data),
+        Err(ClientError::InvalidOwner(_))
+    ));
+
+    let user = Pubkey::new_unique();
+    let mut data = vec![];
+    xusdc::state::ContributorRentInfo {
+        amount: 10,
+        user,
+        nonces_funded: 3,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    // Accounts are allocated with `size_of`, which may pad past the Borsh fields
+    data.resize(
+        8 + std::mem::size_of::<xusdc::state::ContributorRentInfo>(),
+        0,
+    );
+    assert_eq!(
+        ContributorRentInfo::try_from_bytes(&data).unwrap(),
+        ContributorRentInfo {
+            amount: 10,
+            user,
+            nonces_funded: 3,
+        }
+    );
+    assert!(matches!(
+        ContributorRentInfo::try_from_bytes(&data[..20]),
+        Err(ClientError::InvalidData(_))
+    ));
+    let mut data = vec![];
+    xusdc::state::BlockedAccount {
+        wallet: user,
+        blocked_at: 1_700_000_000,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    assert_eq!(
+        BlockedAccount::try_from_account(&ID, &data).unwrap(),
+        BlockedAccount {
+            wallet: user,
+            blocked_at: 1_700_000_000,
+        }
+    );
+}
+
+#[test]
+fn test_hook_errors_match_program() {
+    use xusdc_hook::error::ErrorCode;
+    let program = [
+        ErrorCode::NotTransferring,
+        ErrorCode::WalletBlocked,
+        ErrorCode::SpendLimitExceeded,
+        ErrorCode::InvalidPolicy,
+        ErrorCode::Overflow,
+    ];
+    assert_eq!(program.len(), hook::HookError::ALL.len());
+    for (program, client) in program.into_iter().zip(hook::HookError::ALL) {
+        assert_eq!(format!("{program:?}"), format!("{client:?}"));
+        assert_eq!(program.to_string(), client.to_string());
+        assert_eq!(u32::from(program), client.code());
+    }
+}
+
+#[test]
+fn test_settle_errors_match_program() {
+    use xusdc::ixs::settle_payment::ErrorCode;
+    let program = [
+        ErrorCode::InvalidPaymentAuthorization,
+        ErrorCode::PaymentExpired,
+        ErrorCode::InvalidSignature,
+        ErrorCode::InvalidPublicKey,
+        ErrorCode::UnauthorizedSigner,
+        ErrorCode::NonceAlreadyUsed,
+        ErrorCode::Overflow,
+        ErrorCode::InsufficientFunds,
+        ErrorCode::InvalidAmount,
+        ErrorCode::AccountBlocked,
+        ErrorCode::InvalidSplits,
+        ErrorCode::MemoTooLong,
+    ];
+    assert_eq!(program.len(), SettleError::ALL.len());
+    for (program, client) in program.into_iter().zip(SettleError::ALL) {
+        assert_eq!(format!("{program:?}"), format!("{client:?}"));
+        assert_eq!(program.to_string(), client.to_string());
+        assert_eq!(u32::from(program), client.code());
+    }
+}
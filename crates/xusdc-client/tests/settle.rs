@@ -0,0 +1,187 @@
+//! Authorizations signed by the client against the program's own verifier: first
+//! `brine_ed25519::sig_verify`, which `settle_payment` calls, then a full settlement
+//! in litesvm.
+//!
+//! The bank comes from `xusdc-harness`, which doesn't verify transaction signatures;
+//! payment authorizations are still checked.
+
+use std::time::Duration;
+
+use anchor_lang::AnchorDeserialize;
+use litesvm::LiteSVM;
+use solana_keypair::Keypair;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+use solana_signer::Signer as _;
+use xusdc_client::accounts::{Nonce, ProgramAccount};
+use xusdc_client::{
+    pda, verify_payload, AccountFetcher, FnSigner, PaymentAuthorization, SettleError,
+    SettlePayload, VerifyContext, VerifyError,
+};
+use xusdc_harness::Env;
+
+const TEN_USDC: u64 = 10_000_000;
+
+#[test]
+fn test_signature_passes_program_verifier() {
+    let payer = Keypair::new();
+    let payload = PaymentAuthorization::builder(Pubkey::new_unique(), 1_000)
+        .sign(&payer)
+        .unwrap();
+    // What settle_payment hashes: the Borsh encoding of the program's own struct
+    let program_auth = xusdc::ixs::PaymentAuthorization::try_from_slice(
+        &borsh::to_vec(&payload.payment_auth).unwrap(),
+    )
+    .unwrap();
+    let message = anchor_lang::AnchorSerialize::try_to_vec(&program_auth).unwrap();
+    assert_eq!(message, payload.payment_auth.message());
+    brine_ed25519::sig_verify(&payload.signer_pubkey, &payload.signature, &message).unwrap();
+
+    let mut tampered = payload.payment_auth.clone();
+    tampered.amount += 1;
+    assert!(brine_ed25519::sig_verify(
+        &payload.signer_pubkey,
+        &payload.signature,
+        &tampered.message()
+    )
+    .is_err());
+}
+
+/// Settle `payload`, returning the custom error code it fails with
+fn settle(env: &mut Env, payload: &SettlePayload) -> Result<(), u32> {
+    env.settle(payload).map(|_| ()).map_err(|e| match e.err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        err => panic!("{err:?}: {}", e.meta.logs.join("\n")),
+    })
+}
+
+#[test]
+fn test_client_signed_payment_settles() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.user();
+
+    let payload = PaymentAuthorization::builder(bob.pubkey(), 3_000_000)
+        .now(env.now())
+        .ttl(Duration::from_secs(60))
+        .sign(&alice)
+        .unwrap();
+    env.settle(&payload).unwrap();
+    assert_eq!(env.xusdc_balance(&alice.pubkey()), 7_000_000);
+    assert_eq!(env.xusdc_balance(&bob.pubkey()), 3_000_000);
+
+    let nonce_account = env
+        .svm
+        .get_account(&pda::nonce(&payload.payment_auth.nonce).0)
+        .unwrap();
+    let nonce = Nonce::try_from_account(&nonce_account.owner, &nonce_account.data).unwrap();
+    assert_eq!(nonce.expires_at, env.now() + 60);
+
+    // The same authorization cannot be replayed
+    assert_eq!(
+        settle(&mut env, &payload),
+        Err(SettleError::NonceAlreadyUsed.code())
+    );
+
+    // A closure signer over the same key settles just the same
+    let closure = FnSigner::new(alice.pubkey(), |message: &[u8]| {
+        Ok(alice.sign_message(message).into())
+    });
+    let payload = PaymentAuthorization::builder(bob.pubkey(), 1_000_000)
+        .now(env.now())
+        .sign(&closure)
+        .unwrap();
+    env.settle(&payload).unwrap();
+    assert_eq!(env.xusdc_balance(&bob.pubkey()), 4_000_000);
+
+    // Signed by someone else on alice's behalf
+    let mallory = Keypair::new();
+    let forged = FnSigner::new(alice.pubkey(), |message: &[u8]| {
+        Ok(mallory.sign_message(message).into())
+    });
+    let payload = PaymentAuthorization::builder(bob.pubkey(), 1_000_000)
+        .now(env.now())
+        .sign(&forged)
+        .unwrap();
+    assert_eq!(
+        settle(&mut env, &payload),
+        Err(SettleError::InvalidSignature.code())
+    );
+
+    // Already expired
+    let payload = SettlePayload::sign(
+        PaymentAuthorization::builder(bob.pubkey(), 1_000_000)
+            .valid_until(env.now() - 1)
+            .build(alice.pubkey())
+            .unwrap(),
+        &alice,
+    )
+    .unwrap();
+    assert_eq!(
+        settle(&mut env, &payload),
+        Err(SettleError::PaymentExpired.code())
+    );
+}
+
+/// Reads accounts straight out of litesvm
+struct Bank<'a>(&'a LiteSVM);
+
+impl AccountFetcher for Bank<'_> {
+    type Error = std::convert::Infallible;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, Self::Error> {
+        Ok(self.0.get_account(address))
+    }
+}
+
+#[test]
+fn test_verify_payload_predicts_settlement() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.user();
+
+    let mallory = Keypair::new();
+    let signed = |amount: u64| {
+        PaymentAuthorization::builder(bob.pubkey(), amount)
+            .now(env.now())
+            .sign(&alice)
+            .unwrap()
+    };
+    let valid = signed(1_000_000);
+    let mut tampered = signed(1_000_000);
+    tampered.payment_auth.amount = 2_000_000;
+    let mut expired = signed(1_000_000);
+    expired.payment_auth.valid_until = env.now() - 1;
+    let mut unauthorized = signed(1_000_000);
+    unauthorized.signer_pubkey = mallory.pubkey().to_bytes();
+    unauthorized.signature = mallory
+        .sign_message(&unauthorized.payment_auth.message())
+        .into();
+    let cases = [
+        (valid.clone(), None),
+        // Settled just above, so its nonce is spent
+        (valid, Some(SettleError::NonceAlreadyUsed)),
+        (tampered, Some(SettleError::InvalidSignature)),
+        (expired, Some(SettleError::PaymentExpired)),
+        (unauthorized, Some(SettleError::UnauthorizedSigner)),
+        (signed(0), Some(SettleError::InvalidAmount)),
+        (signed(TEN_USDC), Some(SettleError::InsufficientFunds)),
+    ];
+
+    for (payload, expected) in cases {
+        let ctx = VerifyContext::new(env.now(), Bank(&env.svm));
+        let verified = verify_payload(&payload, &ctx).map_err(|e| match e {
+            VerifyError::Rejected(error) => error,
+            VerifyError::Fetch(_, never) => match never {},
+        });
+        assert_eq!(verified.err(), expected, "{payload:?}");
+        let settled = settle(&mut env, &payload);
+        assert_eq!(
+            settled.err(),
+            expected.map(SettleError::code),
+            "{payload:?}"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Where the facilitator reads accounts and sends transactions
+
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::client_error::ErrorKind;
+use solana_rpc_client_api::request::{RpcError, RpcResponseErrorData};
+use solana_rpc_client_api::response::RpcSimulateTransactionResult;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::sysvar;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    /// The transaction was executed and failed, logging `logs` on the way
+    #[error("transaction failed: {error}")]
+    Transaction {
+        error: TransactionError,
+        logs: Vec<String>,
+    },
+    #[error("{0}")]
+    Rpc(String),
+}
+
+impl BackendError {
+    /// The program whose failure aborted the transaction: the first one its logs
+    /// report as failed, which for a failed CPI is the callee
+    pub fn failed_program(&self) -> Option<Pubkey> {
+        let Self::Transaction { logs, .. } = self else {
+            return None;
+        };
+        logs.iter().find_map(|log| {
+            let (program, _) = log.strip_prefix("Program ")?.split_once(" failed: ")?;
+            program.parse().ok()
+        })
+    }
+}
+
+/// A cluster connection. Calls block, so the server runs them off the async runtime.
+pub trait Backend: Send + Sync {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, BackendError>;
+    fn clock(&self) -> Result<Clock, BackendError>;
+    fn latest_blockhash(&self) -> Result<Hash, BackendError>;
+    /// Send a signed transaction and wait until it is confirmed
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BackendError>;
+}
+
+/// A backend talking to a cluster's JSON RPC
+pub struct RpcBackend {
+    client: RpcClient,
+}
+
+impl RpcBackend {
+    pub fn new(url: impl ToString) -> Self {
+        Self {
+            client: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+        }
+    }
+}
+
+fn rpc_error(error: solana_rpc_client_api::client_error::Error) -> BackendError {
+    let Some(transaction_error) = error.get_transaction_error() else {
+        return BackendError::Rpc(error.to_string());
+    };
+    // Only a failed preflight simulation reports logs
+    let logs = match error.kind() {
+        ErrorKind::RpcError(RpcError::RpcResponseError {
+            data:
+                RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+                    logs: Some(logs),
+                    ..
+                }),
+            ..
+        }) => logs.clone(),
+        _ => Vec::new(),
+    };
+    BackendError::Transaction {
+        error: transaction_error,
+        logs,
+    }
+}
+
+impl Backend for RpcBackend {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, BackendError> {
+        Ok(self
+            .client
+            .get_account_with_commitment(address, self.client.commitment())
+            .map_err(rpc_error)?
+            .value)
+    }
+
+    fn clock(&self) -> Result<Clock, BackendError> {
+        let account = self
+            .get_account(&sysvar::clock::ID)?
+            .ok_or_else(|| BackendError::Rpc("the clock sysvar is missing".to_string()))?;
+        bincode::deserialize(&account.data).map_err(|e| BackendError::Rpc(e.to_string()))
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash, BackendError> {
+        self.client.get_latest_blockhash().map_err(rpc_error)
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BackendError> {
+        self.client
+            .send_and_confirm_transaction(transaction)
+            .map_err(rpc_error)
+    }
+}
+
+/// An in-process litesvm bank, for tests and local simulation
+#[cfg(feature = "litesvm")]
+pub struct LiteSvmBackend {
+    svm: std::sync::Mutex<litesvm::LiteSVM>,
+}
+
+#[cfg(feature = "litesvm")]
+impl LiteSvmBackend {
+    pub fn new(svm: litesvm::LiteSVM) -> Self {
+        Self {
+            svm: std::sync::Mutex::new(svm),
+        }
+    }
+
+    /// Run `f` with the bank, e.g. to set up accounts or warp the clock
+    pub fn with_svm<T>(&self, f: impl FnOnce(&mut litesvm::LiteSVM) -> T) -> T {
+        f(&mut self.svm.lock().unwrap())
+    }
+}
+
+#[cfg(feature = "litesvm")]
+impl Backend for LiteSvmBackend {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, BackendError> {
+        Ok(self.with_svm(|svm| svm.get_account(address)))
+    }
+
+    fn clock(&self) -> Result<Clock, BackendError> {
+        Ok(self.with_svm(|svm| svm.get_sysvar::<Clock>()))
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash, BackendError> {
+        Ok(self.with_svm(|svm| svm.latest_blockhash()))
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BackendError> {
+        self.with_svm(|svm| {
+            svm.send_transaction(transaction.clone())
+                .map(|meta| meta.signature)
+                .map_err(|e| BackendError::Transaction {
+                    error: e.err,
+                    logs: e.meta.logs,
+                })
+        })
+    }
+}
@@ -0,0 +1,475 @@
+use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use xusdc_client::hook::{self, HookError};
+use xusdc_client::{
+    instruction, verify_payload, AccountFetcher, SettleError, SettlePayload, VerifyContext,
+    VerifyError, XUSDC_MINT_KEY,
+};
+
+use crate::backend::{Backend, BackendError};
+use xusdc_x402::{Mismatch, Network, PaymentPayload, PaymentRequirements};
+
+/// Why a payment was turned down
+#[derive(Debug, thiserror::Error)]
+pub enum Rejection {
+    #[error(transparent)]
+    Requirements(#[from] Mismatch),
+    #[error("the authorization is valid for longer than {0} seconds")]
+    TimeoutTooLong(u64),
+    #[error(transparent)]
+    Settle(#[from] SettleError),
+    /// The mint's transfer hook turned the transfer down
+    #[error(transparent)]
+    Hook(#[from] HookError),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+impl Rejection {
+    /// The x402 `invalidReason` / `errorReason`
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Requirements(mismatch) => mismatch.reason(),
+            Self::TimeoutTooLong(_) => "invalid_timeout",
+            Self::Settle(error) => match error {
+                SettleError::InvalidPaymentAuthorization => "invalid_payment_authorization",
+                SettleError::PaymentExpired => "payment_expired",
+                SettleError::InvalidSignature => "invalid_signature",
+                SettleError::InvalidPublicKey => "invalid_public_key",
+                SettleError::UnauthorizedSigner => "unauthorized_signer",
+                SettleError::NonceAlreadyUsed => "nonce_already_used",
+                SettleError::Overflow => "overflow",
+                SettleError::InsufficientFunds => "insufficient_funds",
+                SettleError::InvalidAmount => "invalid_amount",
+                SettleError::AccountBlocked => "account_blocked",
+                SettleError::InvalidSplits => "invalid_splits",
+                SettleError::MemoTooLong => "memo_too_long",
+            },
+            Self::Hook(error) => match error {
+                HookError::NotTransferring => "hook_not_transferring",
+                HookError::WalletBlocked => "wallet_blocked",
+                HookError::SpendLimitExceeded => "spend_limit_exceeded",
+                HookError::InvalidPolicy => "invalid_wallet_policy",
+                HookError::Overflow => "hook_overflow",
+            },
+            Self::Backend(_) => "unexpected_settle_error",
+        }
+    }
+}
+
+/// Verifies and settles payments on one network, paying fees from its keypair
+pub struct Facilitator<B> {
+    backend: B,
+    keypair: Keypair,
+    network: Network,
+}
+
+/// Lets `verify_payload` read accounts through the backend
+struct Accounts<'a, B>(&'a B);
+
+impl<B: Backend> AccountFetcher for Accounts<'_, B> {
+    type Error = BackendError;
+
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, Self::Error> {
+        self.0.get_account(address)
+    }
+}
+
+impl<B: Backend> Facilitator<B> {
+    pub fn new(backend: B, keypair: Keypair, network: Network) -> Self {
+        Self {
+            backend,
+            keypair,
+            network,
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// The fee payer of settlements
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    /// Check a payment against the requirements and the cluster, returning the
+    /// authorization it carries
+    pub fn verify(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<SettlePayload, Rejection> {
+        if requirements.network != self.network {
+            return Err(Mismatch::NetworkMismatch {
+                expected: self.network,
+                actual: requirements.network,
+            }
+            .into());
+        }
+        let payload = requirements.check(payment)?;
+        let payment_auth = &payload.payment_auth;
+
+        let now = self.backend.clock()?.unix_timestamp;
+        // A longer-lived authorization would keep its nonce's rent locked up longer
+        // than the resource server agreed to
+        let timeout = i64::try_from(requirements.max_timeout_seconds).unwrap_or(i64::MAX);
+        if payment_auth.valid_until > now.saturating_add(timeout) {
+            return Err(Rejection::TimeoutTooLong(requirements.max_timeout_seconds));
+        }
+
+        let ctx = VerifyContext::new(now, Accounts(&self.backend));
+        verify_payload(&payload, &ctx).map_err(|e| match e {
+            VerifyError::Rejected(error) => Rejection::Settle(error),
+            VerifyError::Fetch(_, error) => Rejection::Backend(error),
+        })?;
+        Ok(payload)
+    }
+
+    /// Verify a payment, then submit `settle_payment` for it, with the transfer hook's
+    /// accounts while the mint has it enabled
+    pub fn settle(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<(SettlePayload, Signature), Rejection> {
+        let payload = self.verify(payment, requirements)?;
+        let mint = self
+            .backend
+            .get_account(&XUSDC_MINT_KEY)?
+            .ok_or_else(|| BackendError::Rpc("the xUSDC mint is missing".to_string()))?;
+        let mut settle = instruction::settle_payment(&self.pubkey(), &payload);
+        let auth = &payload.payment_auth;
+        settle.accounts.extend(hook::mint_transfer_accounts(
+            &mint.data, &auth.from, &auth.to,
+        ));
+        let transaction = Transaction::new_signed_with_payer(
+            &[settle],
+            Some(&self.pubkey()),
+            &[&self.keypair],
+            self.backend.latest_blockhash()?,
+        );
+        let signature = self
+            .backend
+            .send_transaction(&transaction)
+            .map_err(settle_rejection)?;
+        Ok((payload, signature))
+    }
+}
+
+/// Why a submitted settlement failed: the program rejected it after all, e.g. a
+/// concurrent settlement spent the nonce, or the transfer hook turned the transfer
+/// down. Each program's custom error codes only mean something coming from it.
+fn settle_rejection(e: BackendError) -> Rejection {
+    let BackendError::Transaction {
+        error: TransactionError::InstructionError(_, InstructionError::Custom(code)),
+        ..
+    } = e
+    else {
+        return Rejection::Backend(e);
+    };
+    let rejection = match e.failed_program() {
+        Some(program) if program == xusdc_client::ID => {
+            SettleError::from_code(code).map(Rejection::Settle)
+        }
+        Some(program) if program == hook::HOOK_PROGRAM_ID => {
+            HookError::from_code(code).map(Rejection::Hook)
+        }
+        _ => None,
+    };
+    rejection.unwrap_or(Rejection::Backend(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litesvm_token::spl_token::extension::transfer_hook::TransferHook;
+    use litesvm_token::spl_token::extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    };
+    use litesvm_token::spl_token::state::Mint;
+    use solana_sdk::clock::Clock;
+    use solana_sdk::hash::Hash;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use xusdc_client::{pda, PaymentAuthorization, TOKEN_2022_PROGRAM_ID};
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[derive(Default)]
+    struct MockBackend {
+        accounts: HashMap<Pubkey, Account>,
+        sent: Mutex<Vec<Transaction>>,
+        /// Fail every transaction with this custom error code and these logs
+        failure: Option<(u32, Vec<String>)>,
+    }
+
+    impl Backend for MockBackend {
+        fn get_account(&self, address: &Pubkey) -> Result<Option<Account>, BackendError> {
+            Ok(self.accounts.get(address).cloned())
+        }
+
+        fn clock(&self) -> Result<Clock, BackendError> {
+            Ok(Clock {
+                unix_timestamp: NOW,
+                ..Clock::default()
+            })
+        }
+
+        fn latest_blockhash(&self) -> Result<Hash, BackendError> {
+            Ok(Hash::new_unique())
+        }
+
+        fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, BackendError> {
+            if let Some((code, logs)) = &self.failure {
+                return Err(BackendError::Transaction {
+                    error: TransactionError::InstructionError(0, InstructionError::Custom(*code)),
+                    logs: logs.clone(),
+                });
+            }
+            self.sent.lock().unwrap().push(transaction.clone());
+            Ok(transaction.signatures[0])
+        }
+    }
+
+    fn token_account(owner: &Pubkey, amount: u64) -> Account {
+        let mut data = vec![0; 165];
+        data[..32].copy_from_slice(XUSDC_MINT_KEY.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[108] = 1;
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: TOKEN_2022_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// The xUSDC mint, its transfer hook pointed at `hook`
+    fn mint(hook: Option<Pubkey>) -> Account {
+        let extensions = [ExtensionType::TransferHook];
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        let extension = mint.init_extension::<TransferHook>(true).unwrap();
+        extension.program_id = hook.try_into().unwrap();
+        mint.base.decimals = 6;
+        mint.base.is_initialized = true;
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        Account {
+            lamports: 1_000_000,
+            data,
+            owner: TOKEN_2022_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    struct Fixture {
+        facilitator: Facilitator<MockBackend>,
+        payer: Keypair,
+        requirements: PaymentRequirements,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let payer = Keypair::new();
+            let merchant = Pubkey::new_unique();
+            let mut backend = MockBackend::default();
+            backend.accounts.insert(XUSDC_MINT_KEY, mint(None));
+            for (owner, amount) in [(payer.pubkey(), 5_000), (merchant, 0)] {
+                backend.accounts.insert(
+                    pda::xusdc_token_address(&owner),
+                    token_account(&owner, amount),
+                );
+            }
+            Self {
+                facilitator: Facilitator::new(backend, Keypair::new(), Network::SolanaDevnet),
+                payer,
+                requirements: PaymentRequirements::new(Network::SolanaDevnet, merchant, 1_000),
+            }
+        }
+
+        fn payment(&self, to: Pubkey, amount: u64, ttl: u64) -> PaymentPayload {
+            let payload = PaymentAuthorization::builder(to, amount)
+                .now(NOW)
+                .ttl(std::time::Duration::from_secs(ttl))
+                .sign(&self.payer)
+                .unwrap();
+            PaymentPayload::new(Network::SolanaDevnet, &payload)
+        }
+
+        fn reason(&self, payment: &PaymentPayload, requirements: &PaymentRequirements) -> &str {
+            self.facilitator
+                .verify(payment, requirements)
+                .unwrap_err()
+                .reason()
+        }
+    }
+
+    #[test]
+    fn test_settle_submits_verified_payment() {
+        let fixture = Fixture::new();
+        let requirements = &fixture.requirements;
+        let payment = fixture.payment(requirements.pay_to, 1_000, 60);
+        let (payload, signature) = fixture.facilitator.settle(&payment, requirements).unwrap();
+        assert_eq!(payload.payment_auth.from, fixture.payer.pubkey());
+
+        let sent = fixture.facilitator.backend().sent.lock().unwrap();
+        let [transaction] = sent.as_slice() else {
+            panic!("expected one transaction, sent {}", sent.len());
+        };
+        assert_eq!(transaction.signatures[0], signature);
+        assert_eq!(
+            transaction.message.account_keys[0],
+            fixture.facilitator.pubkey()
+        );
+        let ix = instruction::settle_payment(&fixture.facilitator.pubkey(), &payload);
+        assert_eq!(transaction.message.instructions[0].data, ix.data);
+    }
+
+    #[test]
+    fn test_settle_passes_hook_accounts_while_enabled() {
+        let mut fixture = Fixture::new();
+        let requirements = fixture.requirements.clone();
+        let payment = fixture.payment(requirements.pay_to, 1_000, 60);
+        fixture
+            .facilitator
+            .backend
+            .accounts
+            .insert(XUSDC_MINT_KEY, mint(Some(hook::HOOK_PROGRAM_ID)));
+        let (payload, _) = fixture.facilitator.settle(&payment, &requirements).unwrap();
+
+        let sent = fixture.facilitator.backend().sent.lock().unwrap();
+        let message = &sent[0].message;
+        let accounts: Vec<_> = message.instructions[0]
+            .accounts
+            .iter()
+            .map(|&i| message.account_keys[i as usize])
+            .collect();
+        let mut expected = instruction::settle_payment(&fixture.facilitator.pubkey(), &payload);
+        expected.accounts.extend(hook::transfer_accounts(
+            &payload.payment_auth.from,
+            &requirements.pay_to,
+        ));
+        assert_eq!(
+            accounts,
+            expected
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_settle_failures_map_by_failing_program() {
+        let mut fixture = Fixture::new();
+        let requirements = fixture.requirements.clone();
+        let payment = fixture.payment(requirements.pay_to, 1_000, 60);
+        let failed = |program: Pubkey, code: u32| {
+            vec![
+                format!("Program {} invoke [1]", xusdc_client::ID),
+                format!("Program {program} failed: custom program error: {code:#x}"),
+                format!(
+                    "Program {} failed: Cross-program invocation failed",
+                    xusdc_client::ID
+                ),
+            ]
+        };
+        let mut reason = |failure| {
+            fixture.facilitator.backend.failure = Some(failure);
+            fixture
+                .facilitator
+                .settle(&payment, &requirements)
+                .unwrap_err()
+                .reason()
+        };
+
+        let nonce_used = SettleError::NonceAlreadyUsed.code();
+        assert_eq!(
+            reason((nonce_used, failed(xusdc_client::ID, nonce_used))),
+            "nonce_already_used"
+        );
+        // The same codes from the hook mean its own errors
+        for (error, expected) in [
+            (HookError::WalletBlocked, "wallet_blocked"),
+            (HookError::SpendLimitExceeded, "spend_limit_exceeded"),
+        ] {
+            assert_eq!(
+                reason((error.code(), failed(hook::HOOK_PROGRAM_ID, error.code()))),
+                expected
+            );
+        }
+        // Any other program's, or an unattributed, code is unexpected
+        assert_eq!(
+            reason((nonce_used, failed(TOKEN_2022_PROGRAM_ID, nonce_used))),
+            "unexpected_settle_error"
+        );
+        assert_eq!(reason((nonce_used, Vec::new())), "unexpected_settle_error");
+    }
+
+    #[test]
+    fn test_verify_checks_requirements() {
+        let fixture = Fixture::new();
+        let requirements = &fixture.requirements;
+        let valid = fixture.payment(requirements.pay_to, 1_000, 60);
+        assert!(fixture.facilitator.verify(&valid, requirements).is_ok());
+
+        let other = Pubkey::new_unique();
+        assert_eq!(
+            fixture.reason(&fixture.payment(other, 1_000, 60), requirements),
+            "invalid_pay_to"
+        );
+        assert_eq!(
+            fixture.reason(&fixture.payment(requirements.pay_to, 999, 60), requirements),
+            "invalid_amount"
+        );
+        assert_eq!(
+            fixture.reason(
+                &fixture.payment(requirements.pay_to, 1_000, 61),
+                requirements
+            ),
+            "invalid_timeout"
+        );
+
+        let mut mainnet = valid.clone();
+        mainnet.network = Network::Solana;
+        assert_eq!(fixture.reason(&mainnet, requirements), "invalid_network");
+        let mut upto = valid.clone();
+        upto.scheme = "upto".to_string();
+        assert_eq!(fixture.reason(&upto, requirements), "unsupported_scheme");
+        let mut garbled = valid.clone();
+        garbled.payload.settle_payload = "AAAA".to_string();
+        assert_eq!(fixture.reason(&garbled, requirements), "invalid_payload");
+
+        let mut usdc = requirements.clone();
+        usdc.asset = xusdc_client::USDC_MINT_KEY;
+        assert_eq!(fixture.reason(&valid, &usdc), "invalid_asset");
+
+        // Checks only the program makes come from verify_payload
+        let mut pricey = requirements.clone();
+        pricey.max_amount_required = 6_000;
+        assert_eq!(
+            fixture.reason(&fixture.payment(pricey.pay_to, 6_000, 60), &pricey),
+            "insufficient_funds"
+        );
+        assert!(fixture
+            .facilitator
+            .backend()
+            .sent
+            .lock()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+//! An x402 facilitator for xUSDC: resource servers post the `X-PAYMENT` they were
+//! given to `/verify` and `/settle`, and the facilitator checks the `SettlePayload`
+//! it carries off-chain before submitting `settle_payment`, paying the fees.
+
+pub mod backend;
+pub mod facilitator;
+pub mod server;
+
+#[cfg(feature = "litesvm")]
+pub use backend::LiteSvmBackend;
+pub use backend::{Backend, BackendError, RpcBackend};
+pub use facilitator::{Facilitator, Rejection};
+pub use server::router;
@@ -0,0 +1,47 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::Parser;
+use solana_sdk::signature::read_keypair_file;
+use xusdc_facilitator::{router, Facilitator, RpcBackend};
+use xusdc_x402::Network;
+
+#[derive(Parser)]
+#[command(about = "x402 facilitator for xUSDC payments")]
+struct Args {
+    /// JSON RPC endpoint of the cluster
+    #[arg(
+        long,
+        env = "XUSDC_RPC_URL",
+        default_value = "https://api.devnet.solana.com"
+    )]
+    rpc_url: String,
+    /// Keypair that pays settlement fees
+    #[arg(long, env = "XUSDC_FACILITATOR_KEYPAIR")]
+    keypair: PathBuf,
+    /// x402 network the cluster is: solana or solana-devnet
+    #[arg(long, default_value = "solana-devnet")]
+    network: Network,
+    #[arg(long, default_value = "127.0.0.1:8402")]
+    listen: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let keypair = read_keypair_file(&args.keypair)
+        .map_err(|e| format!("reading {}: {e}", args.keypair.display()))?;
+    let facilitator = Facilitator::new(RpcBackend::new(&args.rpc_url), keypair, args.network);
+    println!(
+        "Facilitator {} settling on {} via {}, listening on {}",
+        facilitator.pubkey(),
+        args.network,
+        args.rpc_url,
+        args.listen
+    );
+
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    axum::serve(listener, router(Arc::new(facilitator))).await?;
+    Ok(())
+}
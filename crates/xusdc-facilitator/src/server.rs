@@ -0,0 +1,131 @@
+//! The x402 facilitator HTTP API: `POST /verify`, `POST /settle` and `GET /supported`
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use crate::backend::Backend;
+use crate::facilitator::{Facilitator, Rejection};
+use xusdc_x402::Mismatch;
+use xusdc_x402::{
+    FacilitatorRequest, PaymentPayload, SettleResponse, Supported, SupportedKind, VerifyResponse,
+    SCHEME, X402_VERSION,
+};
+
+pub fn router<B: Backend + 'static>(facilitator: Arc<Facilitator<B>>) -> Router {
+    Router::new()
+        .route("/verify", post(verify::<B>))
+        .route("/settle", post(settle::<B>))
+        .route("/supported", get(supported::<B>))
+        .with_state(facilitator)
+}
+
+type HttpError = (StatusCode, String);
+
+fn bad_request(message: String) -> HttpError {
+    (StatusCode::BAD_REQUEST, message)
+}
+
+/// Malformed payments are still answered in the x402 format; only a request with no
+/// payment at all is a client error
+fn payment(request: &FacilitatorRequest) -> Result<Result<PaymentPayload, Rejection>, HttpError> {
+    if request.x402_version != X402_VERSION {
+        return Err(bad_request(format!(
+            "unsupported x402 version {}",
+            request.x402_version
+        )));
+    }
+    match request.payment() {
+        Some(payment) => Ok(payment.map_err(|e| Mismatch::from(e).into())),
+        None => Err(bad_request(
+            "expected paymentPayload or paymentHeader".to_string(),
+        )),
+    }
+}
+
+/// Backend failures are ours, not the payer's
+fn status(rejection: &Rejection) -> StatusCode {
+    match rejection {
+        Rejection::Backend(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::OK,
+    }
+}
+
+async fn verify<B: Backend + 'static>(
+    State(facilitator): State<Arc<Facilitator<B>>>,
+    Json(request): Json<FacilitatorRequest>,
+) -> Result<Response, HttpError> {
+    let payment = payment(&request)?;
+    let result = tokio::task::spawn_blocking(move || {
+        facilitator.verify(&payment?, &request.payment_requirements)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(match result {
+        Ok(payload) => Json(VerifyResponse {
+            is_valid: true,
+            invalid_reason: None,
+            payer: Some(payload.payment_auth.from.to_string()),
+        })
+        .into_response(),
+        Err(rejection) => (
+            status(&rejection),
+            Json(VerifyResponse {
+                is_valid: false,
+                invalid_reason: Some(rejection.reason().to_string()),
+                payer: None,
+            }),
+        )
+            .into_response(),
+    })
+}
+
+async fn settle<B: Backend + 'static>(
+    State(facilitator): State<Arc<Facilitator<B>>>,
+    Json(request): Json<FacilitatorRequest>,
+) -> Result<Response, HttpError> {
+    let payment = payment(&request)?;
+    let network = facilitator.network();
+    let result = tokio::task::spawn_blocking(move || {
+        facilitator.settle(&payment?, &request.payment_requirements)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(match result {
+        Ok((payload, signature)) => Json(SettleResponse {
+            success: true,
+            error_reason: None,
+            transaction: Some(signature.to_string()),
+            network,
+            payer: Some(payload.payment_auth.from.to_string()),
+        })
+        .into_response(),
+        Err(rejection) => (
+            status(&rejection),
+            Json(SettleResponse {
+                success: false,
+                error_reason: Some(rejection.reason().to_string()),
+                transaction: None,
+                network,
+                payer: None,
+            }),
+        )
+            .into_response(),
+    })
+}
+
+async fn supported<B: Backend + 'static>(
+    State(facilitator): State<Arc<Facilitator<B>>>,
+) -> Json<Supported> {
+    Json(Supported {
+        kinds: vec![SupportedKind {
+            x402_version: X402_VERSION,
+            scheme: SCHEME.to_string(),
+            network: facilitator.network(),
+        }],
+    })
+}
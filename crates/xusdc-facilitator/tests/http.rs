@@ -0,0 +1,205 @@
+//! The facilitator's HTTP API end to end, settling against an in-process litesvm bank.
+//!
+//! The bank comes from `xusdc-harness`, which doesn't verify transaction signatures;
+//! payment authorizations are still checked.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde::de::DeserializeOwned;
+use solana_sdk::clock::Clock;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use tower::ServiceExt;
+use xusdc_client::{hook, PaymentAuthorization, ADMIN_KEY};
+use xusdc_facilitator::{router, Backend, Facilitator, LiteSvmBackend};
+use xusdc_harness::{xusdc_balance, Env};
+use xusdc_x402::{
+    FacilitatorRequest, Network, PaymentPayload, PaymentRequirements, SettleResponse, Supported,
+    VerifyResponse, X402_VERSION,
+};
+
+const TEN_USDC: u64 = 10_000_000;
+
+async fn call<T: DeserializeOwned>(
+    app: &Router,
+    method: &str,
+    path: &str,
+    body: Option<&FacilitatorRequest>,
+) -> (StatusCode, T) {
+    let request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header("content-type", "application/json")
+        .body(match body {
+            Some(body) => Body::from(serde_json::to_vec(body).unwrap()),
+            None => Body::empty(),
+        })
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn test_verify_and_settle_over_http() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let merchant = env.user().pubkey();
+    let now = env.now();
+
+    let facilitator = Arc::new(Facilitator::new(
+        LiteSvmBackend::new(env.svm),
+        env.facilitator,
+        Network::Solana,
+    ));
+    let app = router(facilitator.clone());
+
+    let (status, supported): (_, Supported) = call(&app, "GET", "/supported", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(supported.kinds[0].network, Network::Solana);
+
+    let requirements = PaymentRequirements {
+        max_timeout_seconds: 120,
+        ..PaymentRequirements::new(Network::Solana, merchant, 2_500_000)
+    };
+    let payload = PaymentAuthorization::builder(merchant, 2_500_000)
+        .now(now)
+        .ttl(Duration::from_secs(60))
+        .sign(&alice)
+        .unwrap();
+    // Resource servers may forward the X-PAYMENT header as is
+    let request = FacilitatorRequest {
+        x402_version: X402_VERSION,
+        payment_payload: None,
+        payment_header: Some(PaymentPayload::new(Network::Solana, &payload).to_header()),
+        payment_requirements: requirements.clone(),
+    };
+
+    let (status, verified): (_, VerifyResponse) =
+        call(&app, "POST", "/verify", Some(&request)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        verified,
+        VerifyResponse {
+            is_valid: true,
+            invalid_reason: None,
+            payer: Some(alice.pubkey().to_string()),
+        }
+    );
+
+    let (status, settled): (_, SettleResponse) =
+        call(&app, "POST", "/settle", Some(&request)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(settled.success, "{settled:?}");
+    assert_eq!(settled.network, Network::Solana);
+    assert!(settled.transaction.is_some());
+    facilitator.backend().with_svm(|svm| {
+        assert_eq!(xusdc_balance(svm, &alice.pubkey()), 7_500_000);
+        assert_eq!(xusdc_balance(svm, &merchant), 2_500_000);
+    });
+
+    // The same payment again: its nonce is spent
+    let (_, replayed): (_, SettleResponse) = call(&app, "POST", "/settle", Some(&request)).await;
+    assert!(!replayed.success);
+    assert_eq!(replayed.error_reason.as_deref(), Some("nonce_already_used"));
+
+    // Paid to someone else, sent decoded this time
+    let elsewhere = PaymentAuthorization::builder(Pubkey::new_unique(), 2_500_000)
+        .now(now)
+        .sign(&alice)
+        .unwrap();
+    let request = FacilitatorRequest {
+        x402_version: X402_VERSION,
+        payment_payload: Some(PaymentPayload::new(Network::Solana, &elsewhere)),
+        payment_header: None,
+        payment_requirements: requirements,
+    };
+    let (_, verified): (_, VerifyResponse) = call(&app, "POST", "/verify", Some(&request)).await;
+    assert_eq!(verified.invalid_reason.as_deref(), Some("invalid_pay_to"));
+
+    // Expired by the time it reaches the facilitator
+    facilitator.backend().with_svm(|svm| {
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3_600;
+        svm.set_sysvar(&clock);
+    });
+    let stale = PaymentAuthorization::builder(merchant, 2_500_000)
+        .valid_until(now + 60)
+        .build(alice.pubkey())
+        .unwrap();
+    let stale = xusdc_client::SettlePayload::sign(stale, &alice).unwrap();
+    let request = FacilitatorRequest {
+        payment_payload: Some(PaymentPayload::new(Network::Solana, &stale)),
+        ..request
+    };
+    let (_, settled): (_, SettleResponse) = call(&app, "POST", "/settle", Some(&request)).await;
+    assert_eq!(settled.error_reason.as_deref(), Some("payment_expired"));
+    assert!(facilitator.backend().clock().unwrap().unix_timestamp > now + 60);
+}
+
+#[tokio::test]
+async fn test_settle_with_the_transfer_hook_enabled() {
+    let mut env = Env::new();
+    env.enable_transfer_hook();
+    let alice = env.funded_user(TEN_USDC);
+    let merchant = env.user().pubkey();
+    // Room for one payment an hour
+    env.send(
+        &ADMIN_KEY,
+        &[hook::set_policy(
+            &ADMIN_KEY,
+            &alice.pubkey(),
+            3_000_000,
+            3_600,
+        )],
+    )
+    .unwrap();
+    let payments: Vec<_> = (0..2)
+        .map(|_| {
+            let payload = PaymentAuthorization::builder(merchant, 2_500_000)
+                .now(env.now())
+                .ttl(Duration::from_secs(60))
+                .sign(&alice)
+                .unwrap();
+            PaymentPayload::new(Network::Solana, &payload)
+        })
+        .collect();
+
+    let facilitator = Arc::new(Facilitator::new(
+        LiteSvmBackend::new(env.svm),
+        env.facilitator,
+        Network::Solana,
+    ));
+    let app = router(facilitator.clone());
+    let settle = |payment: &PaymentPayload| {
+        let request = FacilitatorRequest {
+            x402_version: X402_VERSION,
+            payment_payload: Some(payment.clone()),
+            payment_header: None,
+            payment_requirements: PaymentRequirements::new(Network::Solana, merchant, 2_500_000),
+        };
+        let app = app.clone();
+        async move { call::<SettleResponse>(&app, "POST", "/settle", Some(&request)).await }
+    };
+
+    let (status, settled) = settle(&payments[0]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(settled.success, "{settled:?}");
+    // The hook's own error, not one of xUSDC's with the same code
+    let (status, refused) = settle(&payments[1]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!refused.success);
+    assert_eq!(
+        refused.error_reason.as_deref(),
+        Some("spend_limit_exceeded")
+    );
+    facilitator.backend().with_svm(|svm| {
+        assert_eq!(xusdc_balance(svm, &merchant), 2_500_000);
+    });
+}
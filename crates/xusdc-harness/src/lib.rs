@@ -0,0 +1,558 @@
+//! A hermetic litesvm bank with the xUSDC program deployed, for tests.
+//!
+//! Nothing is read from outside the repository. Transaction signatures are not
+//! verified, so the admin and the xUSDC mint sign as [`Impersonated`] signers without
+//! their keypairs; payment authorizations are still checked by the program. The USDC
+//! mint is created in code with the admin as its mint authority, and users come from
+//! fixed seeds, so every run sees the same addresses.
+//!
+//! ```ignore
+//! let mut env = Env::new();
+//! let alice = env.user();
+//! let bob = env.user();
+//! env.deposit(&alice.pubkey(), 10_000_000).warp(60);
+//! env.sign_and_settle(&alice, &bob.pubkey(), 2_500_000);
+//! assert_eq!(env.xusdc_balance(&bob.pubkey()), 2_500_000);
+//! ```
+
+// Results are litesvm's own `TransactionResult`
+#![allow(clippy::result_large_err)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use litesvm::types::TransactionResult;
+use litesvm::LiteSVM;
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::hashv;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::Message;
+use solana_sdk::program_option::COption;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::keypair::keypair_from_seed;
+use solana_sdk::signer::{Signer, SignerError};
+use solana_sdk::transaction::Transaction;
+use spl_associated_token_account_client::instruction::create_associated_token_account_idempotent;
+use spl_token_2022::extension::StateWithExtensions;
+use xusdc_client::accounts::Nonce;
+use xusdc_client::instruction::{self, Collateral};
+use xusdc_client::{
+    hook, pda, MintMetadata, PaymentAuthorization, SettlePayload, Split, SplitPaymentAuthorization,
+    SplitSettlePayload, ADMIN_KEY, TOKEN_2022_PROGRAM_ID, USDC_MINT_KEY, XUSDC_MINT_KEY,
+};
+
+/// Path of a prebuilt program to test instead of `target/deploy/xusdc.so`
+pub const PROGRAM_ENV: &str = "XUSDC_PROGRAM_SO";
+
+/// Path of a prebuilt transfer hook program, instead of `target/deploy/xusdc_hook.so`
+pub const HOOK_PROGRAM_ENV: &str = "XUSDC_HOOK_PROGRAM_SO";
+
+/// Where `initialize` says the xUSDC mint's metadata lives
+pub const METADATA_URI: &str = "https://xusdc.example/metadata.json";
+
+/// SOL airdropped to each user for fees
+const USER_LAMPORTS: u64 = 1_000_000_000;
+
+/// SOL the facilitator puts into the rent pool, enough for a few hundred nonces
+const FACILITATOR_RENT: u64 = 100_000_000;
+
+/// Signs as a key without its secret, for banks that don't verify transaction
+/// signatures. The signature is a hash of the key and the message, so the same
+/// transaction signed twice is still a duplicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Impersonated(pub Pubkey);
+
+/// The program's admin
+pub const ADMIN: Impersonated = Impersonated(ADMIN_KEY);
+
+/// The xUSDC mint, which signs its own creation in `initialize`
+pub const XUSDC_MINT: Impersonated = Impersonated(XUSDC_MINT_KEY);
+
+impl Signer for Impersonated {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.0)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        Ok(fake_signature(&[self.0.as_ref(), message]))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+fn fake_signature(parts: &[&[u8]]) -> Signature {
+    let first = hashv(parts);
+    let second = hashv(&[first.as_ref()]);
+    let mut signature = [0; 64];
+    signature[..32].copy_from_slice(first.as_ref());
+    signature[32..].copy_from_slice(second.as_ref());
+    Signature::from(signature)
+}
+
+fn seeded_keypair(kind: &str, index: u64) -> Keypair {
+    let seed = hashv(&[b"xusdc-harness", kind.as_bytes(), &index.to_le_bytes()]);
+    keypair_from_seed(seed.as_ref()).unwrap()
+}
+
+fn workspace_root() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))
+}
+
+/// `$env` if set, otherwise `target/deploy/<name>.so`, built from `programs/<package>`
+/// when it is missing
+fn load_program(name: &str, package: &str, env: &str) -> Result<Vec<u8>, String> {
+    let path = match std::env::var_os(env) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let root = workspace_root();
+            let path = root.join(format!("target/deploy/{name}.so"));
+            if !path.exists() {
+                build_program(&root, name, package, env)?;
+            }
+            path
+        }
+    };
+    std::fs::read(&path).map_err(|e| format!("reading {}: {e}", path.display()))
+}
+
+fn build_program(root: &Path, name: &str, package: &str, env: &str) -> Result<(), String> {
+    let built = Command::new("cargo")
+        .arg("build-sbf")
+        .arg("--manifest-path")
+        .arg(root.join(format!("programs/{package}/Cargo.toml")))
+        .arg("--sbf-out-dir")
+        .arg(root.join("target/deploy"))
+        .status()
+        .is_ok_and(|status| status.success());
+    if built {
+        Ok(())
+    } else {
+        Err(format!(
+            "target/deploy/{name}.so is missing and `cargo build-sbf` failed; install the \
+             Solana toolchain or set {env} to a built program"
+        ))
+    }
+}
+
+/// The program under test: `$XUSDC_PROGRAM_SO` if set, otherwise
+/// `target/deploy/xusdc.so`, built with `cargo build-sbf` when it is missing. Only
+/// the first call in a process tries to build it.
+pub fn program() -> &'static [u8] {
+    static PROGRAM: OnceLock<Result<Vec<u8>, String>> = OnceLock::new();
+    match PROGRAM.get_or_init(|| load_program("xusdc", "xusdc", PROGRAM_ENV)) {
+        Ok(program) => program,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// The transfer hook program, found like [`program`] from `$XUSDC_HOOK_PROGRAM_SO` or
+/// `target/deploy/xusdc_hook.so`
+pub fn hook_program() -> &'static [u8] {
+    static PROGRAM: OnceLock<Result<Vec<u8>, String>> = OnceLock::new();
+    match PROGRAM.get_or_init(|| load_program("xusdc_hook", "xusdc-hook", HOOK_PROGRAM_ENV)) {
+        Ok(program) => program,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// A bank holding the program and a USDC mint the admin can mint from, with the admin
+/// funded but the program not yet initialized
+pub fn bank() -> LiteSVM {
+    let mut svm = LiteSVM::new().with_sigverify(false);
+    svm.add_program(xusdc_client::ID, program());
+
+    let mint = spl_token::state::Mint {
+        mint_authority: COption::Some(ADMIN_KEY),
+        supply: 0,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    mint.pack_into_slice(&mut data);
+    svm.set_account(
+        USDC_MINT_KEY,
+        Account {
+            lamports: svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    svm.airdrop(&ADMIN_KEY, 10_000_000_000).unwrap();
+    svm
+}
+
+/// Send `instructions` paid for by `payer`, impersonating every signer. Each call
+/// signs afresh, so sending the same instructions twice runs them twice.
+pub fn send(svm: &mut LiteSVM, payer: &Pubkey, instructions: &[Instruction]) -> TransactionResult {
+    static SENT: AtomicU64 = AtomicU64::new(0);
+    let sent = SENT.fetch_add(1, Ordering::Relaxed).to_le_bytes();
+    let mut message = Message::new(instructions, Some(payer));
+    message.recent_blockhash = svm.latest_blockhash();
+    let serialized = message.serialize();
+    let signatures = message.account_keys[..message.header.num_required_signatures as usize]
+        .iter()
+        .map(|signer| fake_signature(&[signer.as_ref(), &serialized, &sent]))
+        .collect();
+    svm.send_transaction(Transaction {
+        signatures,
+        message,
+    })
+}
+
+/// `owner`'s xUSDC balance, zero without a token account
+pub fn xusdc_balance(svm: &LiteSVM, owner: &Pubkey) -> u64 {
+    svm.get_account(&pda::xusdc_token_address(owner))
+        .map_or(0, |account| {
+            StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        })
+}
+
+/// xUSDC in circulation
+pub fn xusdc_supply(svm: &LiteSVM) -> u64 {
+    let mint = svm.get_account(&XUSDC_MINT_KEY).unwrap();
+    StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data)
+        .unwrap()
+        .base
+        .supply
+}
+
+/// `owner`'s USDC balance, zero without a token account
+pub fn usdc_balance(svm: &LiteSVM, owner: &Pubkey) -> u64 {
+    svm.get_account(&Collateral::USDC.token_address(owner))
+        .map_or(0, |account| {
+            spl_token::state::Account::unpack(&account.data)
+                .unwrap()
+                .amount
+        })
+}
+
+/// A bank with xUSDC deployed, and the accounts tests act as
+pub struct Env {
+    pub svm: LiteSVM,
+    /// Pays for and settles payments, having contributed to the rent pool
+    pub facilitator: Keypair,
+    users: u64,
+    nonces: u64,
+    /// Whether transfers need the hook's extra accounts
+    hook_enabled: bool,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Env {
+    /// xUSDC initialized and a facilitator ready to settle
+    pub fn new() -> Self {
+        let mut env = Self::uninitialized();
+        env.initialize();
+        let facilitator = env.facilitator.pubkey();
+        env.svm.airdrop(&facilitator, USER_LAMPORTS).unwrap();
+        env.must_send(
+            &facilitator,
+            &[instruction::contribute_rent(&facilitator, FACILITATOR_RENT)],
+        );
+        env
+    }
+
+    /// Only the program, the USDC mint and a funded admin
+    pub fn uninitialized() -> Self {
+        Self {
+            svm: bank(),
+            facilitator: seeded_keypair("facilitator", 0),
+            users: 0,
+            nonces: 0,
+            hook_enabled: false,
+        }
+    }
+
+    /// Run `initialize` as the admin
+    pub fn initialize(&mut self) -> &mut Self {
+        self.must_send(
+            &ADMIN_KEY,
+            &[instruction::initialize(
+                &ADMIN_KEY,
+                &USDC_MINT_KEY,
+                &MintMetadata::xusdc(METADATA_URI),
+            )],
+        )
+    }
+
+    /// Deploy the transfer hook program, set up its meta list and point the xUSDC mint
+    /// at it. [`Env::settle`] and [`Env::transfer`] then pass the hook's accounts.
+    pub fn enable_transfer_hook(&mut self) -> &mut Self {
+        self.svm.add_program(hook::HOOK_PROGRAM_ID, hook_program());
+        self.hook_enabled = true;
+        self.must_send(
+            &ADMIN_KEY,
+            &[
+                hook::initialize_extra_account_meta_list(&ADMIN_KEY),
+                instruction::set_transfer_hook(&ADMIN_KEY, Some(hook::HOOK_PROGRAM_ID)),
+            ],
+        )
+    }
+
+    /// The accounts a transfer from `from`'s xUSDC account to `to`'s needs beyond its
+    /// own, none while the hook is disabled
+    pub fn transfer_accounts(&self, from: &Pubkey, to: &Pubkey) -> Vec<AccountMeta> {
+        if self.hook_enabled {
+            hook::transfer_accounts(from, to)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Send `amount` xUSDC from `from` to `to` directly, without the program
+    pub fn transfer(&mut self, from: &Pubkey, to: &Pubkey, amount: u64) -> TransactionResult {
+        let mut transfer = spl_token_2022::instruction::transfer_checked(
+            &TOKEN_2022_PROGRAM_ID,
+            &pda::xusdc_token_address(from),
+            &XUSDC_MINT_KEY,
+            &pda::xusdc_token_address(to),
+            from,
+            &[],
+            amount,
+            6,
+        )
+        .unwrap();
+        transfer.accounts.extend(self.transfer_accounts(from, to));
+        self.send(from, &[transfer])
+    }
+
+    /// Send `instructions` paid for by `payer`, impersonating every signer
+    pub fn send(&mut self, payer: &Pubkey, instructions: &[Instruction]) -> TransactionResult {
+        send(&mut self.svm, payer, instructions)
+    }
+
+    fn must_send(&mut self, payer: &Pubkey, instructions: &[Instruction]) -> &mut Self {
+        if let Err(failed) = self.send(payer, instructions) {
+            panic!("{:?}\n{}", failed.err, failed.meta.logs.join("\n"));
+        }
+        self
+    }
+
+    /// The next user, with SOL for fees and empty USDC and xUSDC accounts. The nth
+    /// user is the same keypair in every `Env`.
+    pub fn user(&mut self) -> Keypair {
+        self.users += 1;
+        let user = seeded_keypair("user", self.users);
+        let user_key = user.pubkey();
+        self.svm.airdrop(&user_key, USER_LAMPORTS).unwrap();
+        let usdc = Collateral::USDC;
+        self.must_send(
+            &ADMIN_KEY,
+            &[
+                create_associated_token_account_idempotent(
+                    &ADMIN_KEY,
+                    &user_key,
+                    &usdc.mint,
+                    &usdc.token_program,
+                ),
+                create_associated_token_account_idempotent(
+                    &ADMIN_KEY,
+                    &user_key,
+                    &XUSDC_MINT_KEY,
+                    &TOKEN_2022_PROGRAM_ID,
+                ),
+            ],
+        );
+        user
+    }
+
+    /// The next user, holding `amount` xUSDC
+    pub fn funded_user(&mut self, amount: u64) -> Keypair {
+        let user = self.user();
+        if amount > 0 {
+            self.deposit(&user.pubkey(), amount);
+        }
+        user
+    }
+
+    /// Mint `amount` USDC to `owner`, who must already have a USDC account
+    pub fn mint_usdc(&mut self, owner: &Pubkey, amount: u64) -> &mut Self {
+        let usdc = Collateral::USDC;
+        let mint_to = spl_token::instruction::mint_to(
+            &usdc.token_program,
+            &usdc.mint,
+            &usdc.token_address(owner),
+            &ADMIN_KEY,
+            &[],
+            amount,
+        )
+        .unwrap();
+        self.must_send(&ADMIN_KEY, &[mint_to])
+    }
+
+    /// Mint `amount` USDC to `owner` and deposit it for as much xUSDC
+    pub fn deposit(&mut self, owner: &Pubkey, amount: u64) -> &mut Self {
+        self.mint_usdc(owner, amount).must_send(
+            owner,
+            &[instruction::deposit(owner, &Collateral::USDC, amount)],
+        )
+    }
+
+    /// An authorization from `from` to `to`, valid for the default TTL from the bank's
+    /// clock. Nonces are numbered, so they repeat between runs but not within one.
+    pub fn sign(&mut self, from: &Keypair, to: &Pubkey, amount: u64) -> SettlePayload {
+        self.nonces += 1;
+        let nonce = hashv(&[b"nonce", &self.nonces.to_le_bytes()]);
+        PaymentAuthorization::builder(*to, amount)
+            .now(self.now())
+            .nonce(nonce.to_bytes())
+            .sign(from)
+            .unwrap()
+    }
+
+    /// Submit `payload` as the facilitator
+    pub fn settle(&mut self, payload: &SettlePayload) -> TransactionResult {
+        let facilitator = self.facilitator.pubkey();
+        let mut settle = instruction::settle_payment(&facilitator, payload);
+        let auth = &payload.payment_auth;
+        settle
+            .accounts
+            .extend(self.transfer_accounts(&auth.from, &auth.to));
+        self.send(&facilitator, &[settle])
+    }
+
+    /// Sign a payment and settle it, panicking with the logs if it fails
+    pub fn sign_and_settle(&mut self, from: &Keypair, to: &Pubkey, amount: u64) -> SettlePayload {
+        let payload = self.sign(from, to, amount);
+        if let Err(failed) = self.settle(&payload) {
+            panic!("{:?}\n{}", failed.err, failed.meta.logs.join("\n"));
+        }
+        payload
+    }
+
+    /// A split authorization from `from`, listing `splits` or committing to them, valid
+    /// for the default TTL from the bank's clock
+    pub fn sign_split(
+        &mut self,
+        from: &Keypair,
+        splits: &[Split],
+        commit: bool,
+    ) -> SplitSettlePayload {
+        self.nonces += 1;
+        let nonce = hashv(&[b"nonce", &self.nonces.to_le_bytes()]);
+        let builder = SplitPaymentAuthorization::builder(splits.to_vec())
+            .now(self.now())
+            .nonce(nonce.to_bytes());
+        let builder = if commit { builder.commit() } else { builder };
+        builder.sign(from).unwrap()
+    }
+
+    /// Submit a split `payload` as the facilitator
+    pub fn settle_split(&mut self, payload: &SplitSettlePayload) -> TransactionResult {
+        let facilitator = self.facilitator.pubkey();
+        let mut settle = instruction::settle_split_payment(&facilitator, payload);
+        if self.hook_enabled {
+            let recipients: Vec<_> = payload
+                .splits()
+                .iter()
+                .map(|split| split.recipient)
+                .collect();
+            settle.accounts.extend(hook::split_transfer_accounts(
+                &payload.payment_auth.from,
+                &recipients,
+            ));
+        }
+        self.send(&facilitator, &[settle])
+    }
+
+    /// Refund `amount` of `payload`'s payment, signed by its payee
+    pub fn refund(&mut self, payload: &SettlePayload, amount: u64) -> TransactionResult {
+        let auth = &payload.payment_auth;
+        let mut refund = instruction::refund_payment(&auth.to, &auth.from, &auth.nonce, amount);
+        refund
+            .accounts
+            .extend(self.transfer_accounts(&auth.to, &auth.from));
+        self.send(&auth.to, &[refund])
+    }
+
+    /// The payment settled with `nonce`, read through `get_payment`'s return data
+    pub fn get_payment(&mut self, nonce: &[u8; 32]) -> Option<Nonce> {
+        let facilitator = self.facilitator.pubkey();
+        match self.send(&facilitator, &[instruction::get_payment(nonce)]) {
+            Ok(meta) => instruction::decode_payment(&meta.return_data.data).unwrap(),
+            Err(failed) => panic!("{:?}\n{}", failed.err, failed.meta.logs.join("\n")),
+        }
+    }
+
+    /// Move the clock `seconds` forward, with a new blockhash
+    pub fn warp(&mut self, seconds: i64) -> &mut Self {
+        self.svm.expire_blockhash();
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar(&clock);
+        self
+    }
+
+    /// The bank's Unix timestamp
+    pub fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    pub fn xusdc_balance(&self, owner: &Pubkey) -> u64 {
+        xusdc_balance(&self.svm, owner)
+    }
+
+    pub fn usdc_balance(&self, owner: &Pubkey) -> u64 {
+        usdc_balance(&self.svm, owner)
+    }
+
+    pub fn xusdc_supply(&self) -> u64 {
+        xusdc_supply(&self.svm)
+    }
+
+    pub fn into_svm(self) -> LiteSVM {
+        self.svm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::system_instruction;
+
+    use super::*;
+
+    #[test]
+    fn test_impersonated_transactions() {
+        // A bank without the program, which isn't needed to move SOL
+        let mut svm = LiteSVM::new().with_sigverify(false);
+        svm.airdrop(&ADMIN_KEY, 1_000_000_000).unwrap();
+        let to = seeded_keypair("user", 1).pubkey();
+        assert_eq!(to, seeded_keypair("user", 1).pubkey());
+
+        let transfer = [system_instruction::transfer(&ADMIN_KEY, &to, 1_000_000)];
+        send(&mut svm, &ADMIN_KEY, &transfer).unwrap();
+        // Sent again, it is a new transaction
+        send(&mut svm, &ADMIN_KEY, &transfer).unwrap();
+        assert_eq!(svm.get_balance(&to), Some(2_000_000));
+
+        // Signed by an impersonated signer, the same transaction twice is a duplicate
+        let tx = Transaction::new_signed_with_payer(
+            &transfer,
+            Some(&ADMIN_KEY),
+            &[&ADMIN],
+            svm.latest_blockhash(),
+        );
+        svm.send_transaction(tx.clone()).unwrap();
+        assert!(svm.send_transaction(tx).is_err());
+        assert_eq!(svm.get_balance(&to), Some(3_000_000));
+    }
+}
@@ -0,0 +1,661 @@
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::CompiledInstruction;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use xusdc::events::{
+    AccountBlocked, AccountUnblocked, CollateralUpdated, Deposited, FreezeUpdated, MetadataUpdated,
+    NonceCollected, NonceMigrated, PaymentRefunded, PaymentSettled, RentContributed, RentWithdrawn,
+    ReservesAttested, Seized, SplitPaymentSettled, TransferHookUpdated, WithdrawLimitsUpdated,
+    WithdrawRequestCancelled, WithdrawRequested, Withdrawn,
+};
+use xusdc::ixs::{PaymentAuthorization, SettlePayload, SplitSettlePayload};
+use xusdc::state::MetadataField;
+
+use crate::error::{IndexerError, Result};
+use crate::transaction::TransactionRecord;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XusdcInstruction {
+    Initialize {
+        name: String,
+        symbol: String,
+        uri: String,
+    },
+    InitCollateralRegistry,
+    ContributeRent {
+        amount: u64,
+    },
+    WithdrawRent {
+        amount: u64,
+    },
+    GarbageCollect,
+    MigrateNonce,
+    GetPayment {
+        nonce: [u8; 32],
+    },
+    SettlePayment {
+        payload: SettlePayload,
+    },
+    SettleSplitPayment {
+        payload: SplitSettlePayload,
+    },
+    RefundPayment {
+        nonce: [u8; 32],
+        amount: u64,
+    },
+    Deposit {
+        amount: u64,
+    },
+    Withdraw {
+        amount: u64,
+    },
+    AddCollateral {
+        deposit_cap: u64,
+        withdraw_fee_bps: u16,
+    },
+    UpdateCollateral {
+        deposit_cap: u64,
+        withdraw_fee_bps: u16,
+    },
+    UpdateMetadata {
+        field: MetadataField,
+        value: String,
+    },
+    SetTransferHook {
+        program_id: Option<Pubkey>,
+    },
+    AttestReserves,
+    SetWithdrawLimits {
+        window_seconds: i64,
+        global_limit: u64,
+        wallet_limit: u64,
+        queue_delay_seconds: i64,
+    },
+    ClaimWithdraw,
+    RequestWithdraw {
+        amount: u64,
+    },
+    CancelWithdrawRequest,
+    BlockAccount {
+        wallet: Pubkey,
+    },
+    UnblockAccount {
+        wallet: Pubkey,
+    },
+    SetFrozen {
+        wallet: Pubkey,
+        frozen: bool,
+    },
+    Seize {
+        wallet: Pubkey,
+        amount: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XusdcEvent {
+    PaymentSettled(PaymentSettled),
+    SplitPaymentSettled(SplitPaymentSettled),
+    PaymentRefunded(PaymentRefunded),
+    Deposited(Deposited),
+    Withdrawn(Withdrawn),
+    WithdrawRequested(WithdrawRequested),
+    WithdrawRequestCancelled(WithdrawRequestCancelled),
+    RentContributed(RentContributed),
+    RentWithdrawn(RentWithdrawn),
+    NonceCollected(NonceCollected),
+    NonceMigrated(NonceMigrated),
+    CollateralUpdated(CollateralUpdated),
+    MetadataUpdated(MetadataUpdated),
+    WithdrawLimitsUpdated(WithdrawLimitsUpdated),
+    TransferHookUpdated(TransferHookUpdated),
+    ReservesAttested(ReservesAttested),
+    AccountBlocked(AccountBlocked),
+    AccountUnblocked(AccountUnblocked),
+    FreezeUpdated(FreezeUpdated),
+    Seized(Seized),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    /// Index of the top-level instruction this is, or was invoked from
+    pub index: usize,
+    /// Position within the inner instructions when xUSDC was invoked through a CPI
+    pub inner_index: Option<usize>,
+    pub instruction: XusdcInstruction,
+    /// Accounts in the order the instruction received them
+    pub accounts: Vec<Pubkey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub succeeded: bool,
+    pub instructions: Vec<DecodedInstruction>,
+    pub events: Vec<XusdcEvent>,
+    /// Why xUSDC instructions and events that couldn't be decoded were skipped, e.g.
+    /// ones from a program version newer than this crate
+    pub warnings: Vec<String>,
+}
+
+/// Layouts the program has since changed, so transactions from before the change can
+/// still be decoded
+mod legacy {
+    use super::*;
+    // The derives refer to `borsh` by name
+    use anchor_lang::prelude::borsh;
+
+    /// A payment authorization from before it carried `resource_hash` and `memo`
+    #[derive(AnchorDeserialize)]
+    struct PaymentAuthorizationV0 {
+        from: Pubkey,
+        to: Pubkey,
+        amount: u64,
+        nonce: [u8; 32],
+        valid_until: i64,
+    }
+
+    #[derive(AnchorDeserialize)]
+    struct SettlePayloadV0 {
+        payment_auth: PaymentAuthorizationV0,
+        signature: [u8; 64],
+        signer_pubkey: [u8; 32],
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub(super) struct SettlePaymentV0 {
+        payload: SettlePayloadV0,
+    }
+
+    impl Discriminator for SettlePaymentV0 {
+        const DISCRIMINATOR: &'static [u8] = xusdc::instruction::SettlePayment::DISCRIMINATOR;
+    }
+
+    impl From<SettlePaymentV0> for SettlePayload {
+        fn from(SettlePaymentV0 { payload }: SettlePaymentV0) -> Self {
+            let auth = payload.payment_auth;
+            Self {
+                payment_auth: PaymentAuthorization {
+                    from: auth.from,
+                    to: auth.to,
+                    amount: auth.amount,
+                    nonce: auth.nonce,
+                    valid_until: auth.valid_until,
+                    resource_hash: [0; 32],
+                    memo: None,
+                },
+                signature: payload.signature,
+                signer_pubkey: payload.signer_pubkey,
+            }
+        }
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub(super) struct PaymentSettledV0 {
+        from: Pubkey,
+        to: Pubkey,
+        amount: u64,
+        nonce: [u8; 32],
+        facilitator: Pubkey,
+        valid_until: i64,
+    }
+
+    impl Discriminator for PaymentSettledV0 {
+        const DISCRIMINATOR: &'static [u8] = PaymentSettled::DISCRIMINATOR;
+    }
+
+    impl From<PaymentSettledV0> for PaymentSettled {
+        fn from(event: PaymentSettledV0) -> Self {
+            Self {
+                from: event.from,
+                to: event.to,
+                amount: event.amount,
+                nonce: event.nonce,
+                facilitator: event.facilitator,
+                valid_until: event.valid_until,
+                resource_hash: [0; 32],
+                memo: None,
+            }
+        }
+    }
+}
+
+/// Strip the Anchor discriminator of `T` and Borsh-decode the rest, or `None` if `data`
+/// is for something else
+fn decode_as<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> Option<Result<T>> {
+    data.strip_prefix(T::DISCRIMINATOR)
+        .map(|args| T::try_from_slice(args).map_err(IndexerError::from))
+}
+
+/// Decode xUSDC instruction data, in its current layout or one it had before
+pub fn decode_instruction(data: &[u8]) -> Result<XusdcInstruction> {
+    use xusdc::instruction as ix;
+
+    // Borsh needs every byte, so old data never decodes as the current layout and
+    // current data never decodes as the old one
+    if let Some(Ok(settle)) = decode_as::<legacy::SettlePaymentV0>(data) {
+        return Ok(XusdcInstruction::SettlePayment {
+            payload: settle.into(),
+        });
+    }
+
+    macro_rules! decode {
+        ($($ty:ident => |$args:pat_param| $variant:expr),* $(,)?) => {
+            $(
+                if let Some(args) = decode_as::<ix::$ty>(data) {
+                    let $args = args?;
+                    return Ok($variant);
+                }
+            )*
+        };
+    }
+
+    decode! {
+        Initialize => |a| XusdcInstruction::Initialize {
+            name: a.name,
+            symbol: a.symbol,
+            uri: a.uri,
+        },
+        InitCollateralRegistry => |_| XusdcInstruction::InitCollateralRegistry,
+        ContributeRent => |a| XusdcInstruction::ContributeRent { amount: a.amount },
+        WithdrawRent => |a| XusdcInstruction::WithdrawRent { amount: a.amount },
+        GarbageCollect => |_| XusdcInstruction::GarbageCollect,
+        MigrateNonce => |_| XusdcInstruction::MigrateNonce,
+        GetPayment => |a| XusdcInstruction::GetPayment { nonce: a.nonce },
+        SettlePayment => |a| XusdcInstruction::SettlePayment { payload: a.payload },
+        SettleSplitPayment => |a| XusdcInstruction::SettleSplitPayment { payload: a.payload },
+        RefundPayment => |a| XusdcInstruction::RefundPayment {
+            nonce: a.nonce,
+            amount: a.amount,
+        },
+        Deposit => |a| XusdcInstruction::Deposit { amount: a.amount },
+        Withdraw => |a| XusdcInstruction::Withdraw { amount: a.amount },
+        AddCollateral => |a| XusdcInstruction::AddCollateral {
+            deposit_cap: a.deposit_cap,
+            withdraw_fee_bps: a.withdraw_fee_bps,
+        },
+        UpdateCollateral => |a| XusdcInstruction::UpdateCollateral {
+            deposit_cap: a.deposit_cap,
+            withdraw_fee_bps: a.withdraw_fee_bps,
+        },
+        UpdateMetadata => |a| XusdcInstruction::UpdateMetadata {
+            field: a.field,
+            value: a.value,
+        },
+        SetTransferHook => |a| XusdcInstruction::SetTransferHook {
+            program_id: a.program_id,
+        },
+        AttestReserves => |_| XusdcInstruction::AttestReserves,
+        SetWithdrawLimits => |a| XusdcInstruction::SetWithdrawLimits {
+            window_seconds: a.window_seconds,
+            global_limit: a.global_limit,
+            wallet_limit: a.wallet_limit,
+            queue_delay_seconds: a.queue_delay_seconds,
+        },
+        ClaimWithdraw => |_| XusdcInstruction::ClaimWithdraw,
+        RequestWithdraw => |a| XusdcInstruction::RequestWithdraw { amount: a.amount },
+        CancelWithdrawRequest => |_| XusdcInstruction::CancelWithdrawRequest,
+        BlockAccount => |a| XusdcInstruction::BlockAccount { wallet: a.wallet },
+        UnblockAccount => |a| XusdcInstruction::UnblockAccount { wallet: a.wallet },
+        SetFrozen => |a| XusdcInstruction::SetFrozen {
+            wallet: a.wallet,
+            frozen: a.frozen,
+        },
+        Seize => |a| XusdcInstruction::Seize {
+            wallet: a.wallet,
+            amount: a.amount,
+        },
+    }
+
+    Err(IndexerError::UnknownInstruction(
+        data.iter().take(8).copied().collect(),
+    ))
+}
+
+/// Decode an event from its discriminator and Borsh data, without the `emit_cpi!` tag,
+/// in its current layout or one it had before
+pub fn decode_event(data: &[u8]) -> Result<XusdcEvent> {
+    if let Some(Ok(event)) = decode_as::<legacy::PaymentSettledV0>(data) {
+        return Ok(XusdcEvent::PaymentSettled(event.into()));
+    }
+
+    macro_rules! decode {
+        ($($ty:ident),* $(,)?) => {
+            $(
+                if let Some(event) = decode_as::<$ty>(data) {
+                    return Ok(XusdcEvent::$ty(event?));
+                }
+            )*
+        };
+    }
+
+    decode!(
+        PaymentSettled,
+        SplitPaymentSettled,
+        PaymentRefunded,
+        Deposited,
+        Withdrawn,
+        WithdrawRequested,
+        WithdrawRequestCancelled,
+        RentContributed,
+        RentWithdrawn,
+        NonceCollected,
+        NonceMigrated,
+        CollateralUpdated,
+        MetadataUpdated,
+        WithdrawLimitsUpdated,
+        TransferHookUpdated,
+        ReservesAttested,
+        AccountBlocked,
+        AccountUnblocked,
+        FreezeUpdated,
+        Seized,
+    );
+
+    Err(IndexerError::UnknownEvent(
+        data.iter().take(8).copied().collect(),
+    ))
+}
+
+fn program_id(tx: &TransactionRecord, ix: &CompiledInstruction) -> Result<Pubkey> {
+    tx.account_keys
+        .get(ix.program_id_index as usize)
+        .copied()
+        .ok_or(IndexerError::InvalidAccountIndex(ix.program_id_index))
+}
+
+fn accounts(tx: &TransactionRecord, ix: &CompiledInstruction) -> Result<Vec<Pubkey>> {
+    ix.accounts
+        .iter()
+        .map(|&i| {
+            tx.account_keys
+                .get(i as usize)
+                .copied()
+                .ok_or(IndexerError::InvalidAccountIndex(i))
+        })
+        .collect()
+}
+
+/// Decode every xUSDC instruction and event in a transaction. Ones that can't be
+/// decoded are skipped with a warning rather than failing the whole transaction.
+pub fn decode_transaction(tx: &TransactionRecord) -> Result<DecodedTransaction> {
+    let mut instructions = vec![];
+    let mut events = vec![];
+    let mut warnings = vec![];
+
+    for (index, ix) in tx.instructions.iter().enumerate() {
+        if program_id(tx, ix)? == xusdc::ID {
+            match decode_instruction(&ix.data) {
+                Ok(instruction) => instructions.push(DecodedInstruction {
+                    index,
+                    inner_index: None,
+                    instruction,
+                    accounts: accounts(tx, ix)?,
+                }),
+                Err(e) => warnings.push(format!("skipped instruction {index}: {e}")),
+            }
+        }
+
+        let Some(inner_instructions) = tx.inner_instructions.get(index) else {
+            continue;
+        };
+        for (inner_index, inner) in inner_instructions.iter().enumerate() {
+            let ix = &inner.instruction;
+            if program_id(tx, ix)? != xusdc::ID {
+                continue;
+            }
+            // `emit_cpi!` events are self-invocations tagged with `EVENT_IX_TAG_LE`
+            if let Some(event) = ix.data.strip_prefix(EVENT_IX_TAG_LE) {
+                match decode_event(event) {
+                    Ok(event) => events.push(event),
+                    Err(e) => warnings.push(format!(
+                        "skipped event in instruction {index}.{inner_index}: {e}"
+                    )),
+                }
+            } else {
+                match decode_instruction(&ix.data) {
+                    Ok(instruction) => instructions.push(DecodedInstruction {
+                        index,
+                        inner_index: Some(inner_index),
+                        instruction,
+                        accounts: accounts(tx, ix)?,
+                    }),
+                    Err(e) => {
+                        warnings.push(format!("skipped instruction {index}.{inner_index}: {e}"))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(DecodedTransaction {
+        signature: tx.signature.clone(),
+        slot: tx.slot,
+        block_time: tx.block_time,
+        succeeded: tx.succeeded(),
+        instructions,
+        events,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::message::inner_instruction::InnerInstruction;
+    use anchor_lang::{AnchorSerialize, InstructionData};
+    use xusdc::ixs::PaymentAuthorization;
+
+    fn event_data<T: Discriminator + AnchorSerialize>(event: &T) -> Vec<u8> {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        event.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn payment_settled() -> PaymentSettled {
+        PaymentSettled {
+            from: Pubkey::new_unique(),
+            to: Pubkey::new_unique(),
+            amount: 1_000_000,
+            nonce: [7; 32],
+            facilitator: Pubkey::new_unique(),
+            valid_until: 1_700_000_000,
+            resource_hash: [3; 32],
+            memo: Some("order 42".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_decode_settle_payment_with_cpi_event() {
+        let event = payment_settled();
+        let payload = SettlePayload {
+            payment_auth: PaymentAuthorization {
+                from: event.from,
+                to: event.to,
+                amount: event.amount,
+                nonce: event.nonce,
+                valid_until: event.valid_until,
+                resource_hash: event.resource_hash,
+                memo: event.memo.clone(),
+            },
+            signature: [1; 64],
+            signer_pubkey: event.from.to_bytes(),
+        };
+        let facilitator = event.facilitator;
+        let tx = TransactionRecord {
+            signature: "sig".into(),
+            slot: 42,
+            account_keys: vec![facilitator, xusdc::ID],
+            instructions: vec![CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![0],
+                data: xusdc::instruction::SettlePayment {
+                    payload: payload.clone(),
+                }
+                .data(),
+            }],
+            inner_instructions: vec![vec![InnerInstruction {
+                instruction: CompiledInstruction {
+                    program_id_index: 1,
+                    accounts: vec![],
+                    data: [EVENT_IX_TAG_LE, &event_data(&event)].concat(),
+                },
+                stack_height: 2,
+            }]],
+            ..Default::default()
+        };
+
+        let decoded = decode_transaction(&tx).unwrap();
+        assert!(decoded.succeeded);
+        assert_eq!(decoded.slot, 42);
+        assert_eq!(
+            decoded.instructions,
+            vec![DecodedInstruction {
+                index: 0,
+                inner_index: None,
+                instruction: XusdcInstruction::SettlePayment { payload },
+                accounts: vec![facilitator],
+            }]
+        );
+        assert_eq!(decoded.events, vec![XusdcEvent::PaymentSettled(event)]);
+    }
+
+    #[test]
+    fn test_decode_ignores_other_programs() {
+        let other_program = Pubkey::new_unique();
+        let tx = TransactionRecord {
+            account_keys: vec![other_program],
+            instructions: vec![CompiledInstruction {
+                program_id_index: 0,
+                accounts: vec![],
+                data: vec![0xff; 12],
+            }],
+            inner_instructions: vec![vec![InnerInstruction {
+                instruction: CompiledInstruction {
+                    program_id_index: 0,
+                    accounts: vec![],
+                    data: [EVENT_IX_TAG_LE, &event_data(&payment_settled())].concat(),
+                },
+                stack_height: 2,
+            }]],
+            ..Default::default()
+        };
+
+        let decoded = decode_transaction(&tx).unwrap();
+        assert!(decoded.instructions.is_empty());
+        assert!(decoded.events.is_empty());
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_discriminator() {
+        assert!(matches!(
+            decode_instruction(&[0; 16]),
+            Err(IndexerError::UnknownInstruction(_))
+        ));
+        assert!(matches!(
+            decode_event(&[0; 16]),
+            Err(IndexerError::UnknownEvent(_))
+        ));
+        // Known discriminator, truncated arguments
+        let data = xusdc::instruction::Deposit { amount: 1 }.data();
+        assert!(matches!(
+            decode_instruction(&data[..12]),
+            Err(IndexerError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_layouts_from_before_resource_binding() {
+        let event = PaymentSettled {
+            resource_hash: [0; 32],
+            memo: None,
+            ..payment_settled()
+        };
+        // The fields every version has, in their order
+        let mut auth = event.from.to_bytes().to_vec();
+        auth.extend(event.to.to_bytes());
+        auth.extend(event.amount.to_le_bytes());
+        auth.extend(event.nonce);
+        auth.extend(event.valid_until.to_le_bytes());
+
+        let settle = [
+            xusdc::instruction::SettlePayment::DISCRIMINATOR,
+            &auth,
+            &[1; 64],
+            &event.from.to_bytes(),
+        ]
+        .concat();
+        let XusdcInstruction::SettlePayment { payload } = decode_instruction(&settle).unwrap()
+        else {
+            panic!("expected settle_payment");
+        };
+        assert_eq!(payload.payment_auth.nonce, event.nonce);
+        assert_eq!(payload.payment_auth.resource_hash, [0; 32]);
+        assert_eq!(payload.payment_auth.memo, None);
+        assert_eq!(payload.signature, [1; 64]);
+
+        let mut settled = PaymentSettled::DISCRIMINATOR.to_vec();
+        settled.extend(&auth[..72 + 32]);
+        settled.extend(event.facilitator.to_bytes());
+        settled.extend(event.valid_until.to_le_bytes());
+        assert_eq!(
+            decode_event(&settled).unwrap(),
+            XusdcEvent::PaymentSettled(event)
+        );
+    }
+
+    #[test]
+    fn test_decode_skips_what_it_cannot_decode() {
+        let event = payment_settled();
+        let tx = TransactionRecord {
+            account_keys: vec![xusdc::ID],
+            instructions: vec![
+                CompiledInstruction {
+                    program_id_index: 0,
+                    accounts: vec![],
+                    data: vec![0xff; 12],
+                },
+                CompiledInstruction {
+                    program_id_index: 0,
+                    accounts: vec![],
+                    data: xusdc::instruction::GarbageCollect {}.data(),
+                },
+            ],
+            inner_instructions: vec![
+                vec![],
+                vec![
+                    InnerInstruction {
+                        instruction: CompiledInstruction {
+                            program_id_index: 0,
+                            accounts: vec![],
+                            data: [EVENT_IX_TAG_LE, &[0xff; 12]].concat(),
+                        },
+                        stack_height: 2,
+                    },
+                    InnerInstruction {
+                        instruction: CompiledInstruction {
+                            program_id_index: 0,
+                            accounts: vec![],
+                            data: [EVENT_IX_TAG_LE, &event_data(&event)].concat(),
+                        },
+                        stack_height: 2,
+                    },
+                ],
+            ],
+            ..Default::default()
+        };
+
+        let decoded = decode_transaction(&tx).unwrap();
+        assert_eq!(decoded.instructions.len(), 1);
+        assert_eq!(
+            decoded.instructions[0].instruction,
+            XusdcInstruction::GarbageCollect
+        );
+        assert_eq!(decoded.events, vec![XusdcEvent::PaymentSettled(event)]);
+        assert_eq!(decoded.warnings.len(), 2, "{:?}", decoded.warnings);
+        assert!(decoded.warnings[0].starts_with("skipped instruction 0:"));
+        assert!(decoded.warnings[1].starts_with("skipped event in instruction 1.0:"));
+    }
+}
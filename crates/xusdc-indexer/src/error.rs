@@ -0,0 +1,16 @@
+#[derive(Debug, thiserror::Error)]
+pub enum IndexerError {
+    #[error("unknown xUSDC instruction discriminator {0:?}")]
+    UnknownInstruction(Vec<u8>),
+    #[error("unknown xUSDC event discriminator {0:?}")]
+    UnknownEvent(Vec<u8>),
+    #[error("malformed xUSDC instruction or event data: {0}")]
+    InvalidData(#[from] std::io::Error),
+    #[error("account index {0} is out of range for the transaction's account keys")]
+    InvalidAccountIndex(u8),
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+pub type Result<T> = std::result::Result<T, IndexerError>;
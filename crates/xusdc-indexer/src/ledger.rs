@@ -0,0 +1,181 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::decode::{decode_transaction, DecodedTransaction, XusdcEvent};
+use crate::error::Result;
+use crate::store::PaymentStore;
+use crate::transaction::TransactionRecord;
+
+/// A settled payment, recorded from its `PaymentSettled` event, or one recipient's
+/// share of a split payment, recorded from its `SplitPaymentSettled` event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    /// Unique per payment, enforced on-chain by the nonce PDA
+    pub nonce: [u8; 32],
+    /// The recipient's position among a split payment's splits, `None` for a payment
+    /// to a single recipient
+    pub split: Option<u8>,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    /// Returned to `from` by `refund_payment` so far, from its `PaymentRefunded` events
+    pub refunded: u64,
+    pub facilitator: Pubkey,
+    pub valid_until: i64,
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+}
+
+/// Payments an account sent and received. Amounts are net of refunds, and a split
+/// payment counts once per recipient.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountSummary {
+    pub payments_sent: u64,
+    pub amount_sent: u128,
+    pub payments_received: u64,
+    pub amount_received: u128,
+}
+
+impl Payment {
+    /// What `to` kept after refunds
+    pub fn net_amount(&self) -> u64 {
+        self.amount.saturating_sub(self.refunded)
+    }
+}
+
+pub struct Ledger<S> {
+    store: S,
+}
+
+impl<S: PaymentStore> Ledger<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Decode a transaction and record the payments it settled and refunded, with one
+    /// payment per recipient of a split payment. Failed transactions aren't decoded
+    /// and record nothing, and payments that are already recorded are left as they
+    /// are.
+    pub fn index(&mut self, tx: &TransactionRecord) -> Result<DecodedTransaction> {
+        if !tx.succeeded() {
+            return Ok(DecodedTransaction {
+                signature: tx.signature.clone(),
+                slot: tx.slot,
+                block_time: tx.block_time,
+                succeeded: false,
+                instructions: vec![],
+                events: vec![],
+                warnings: vec![],
+            });
+        }
+        let decoded = decode_transaction(tx)?;
+
+        for event in &decoded.events {
+            match event {
+                XusdcEvent::PaymentSettled(event) => {
+                    self.store.insert(&Payment {
+                        nonce: event.nonce,
+                        split: None,
+                        from: event.from,
+                        to: event.to,
+                        amount: event.amount,
+                        refunded: 0,
+                        facilitator: event.facilitator,
+                        valid_until: event.valid_until,
+                        signature: decoded.signature.clone(),
+                        slot: decoded.slot,
+                        block_time: decoded.block_time,
+                    })?;
+                }
+                XusdcEvent::SplitPaymentSettled(event) => {
+                    for (i, split) in event.splits.iter().enumerate() {
+                        self.store.insert(&Payment {
+                            nonce: event.nonce,
+                            split: Some(i as u8),
+                            from: event.from,
+                            to: split.recipient,
+                            amount: split.amount,
+                            refunded: 0,
+                            facilitator: event.facilitator,
+                            valid_until: event.valid_until,
+                            signature: decoded.signature.clone(),
+                            slot: decoded.slot,
+                            block_time: decoded.block_time,
+                        })?;
+                    }
+                }
+                // Each event carries the running total, so replaying one changes nothing
+                XusdcEvent::PaymentRefunded(event) => {
+                    self.store.set_refunded(&event.nonce, event.refunded)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(decoded)
+    }
+
+    /// The payment settled with `nonce`, or each recipient's share of a split payment
+    /// in split order. Empty if nothing was settled with it.
+    pub fn payments(&self, nonce: &[u8; 32]) -> Result<Vec<Payment>> {
+        self.store.get(nonce)
+    }
+
+    /// Payments sent by `payer`, oldest first
+    pub fn payments_by_payer(&self, payer: &Pubkey) -> Result<Vec<Payment>> {
+        self.store.by_payer(payer)
+    }
+
+    /// Payments received by `payee`, oldest first
+    pub fn payments_by_payee(&self, payee: &Pubkey) -> Result<Vec<Payment>> {
+        self.store.by_payee(payee)
+    }
+
+    pub fn summary(&self, account: &Pubkey) -> Result<AccountSummary> {
+        let mut summary = AccountSummary::default();
+        for payment in self.store.by_payer(account)? {
+            summary.payments_sent += 1;
+            summary.amount_sent += payment.net_amount() as u128;
+        }
+        for payment in self.store.by_payee(account)? {
+            summary.payments_received += 1;
+            summary.amount_received += payment.net_amount() as u128;
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use anchor_lang::solana_program::instruction::CompiledInstruction;
+
+    #[test]
+    fn test_index_skips_failed_transactions() {
+        let mut ledger = Ledger::new(MemoryStore::default());
+        // Failed transactions aren't decoded, so nothing in them can fail indexing
+        let tx = TransactionRecord {
+            signature: "failed".into(),
+            err: Some("InstructionError(0, Custom(6005))".into()),
+            account_keys: vec![xusdc::ID],
+            instructions: vec![CompiledInstruction {
+                program_id_index: 9,
+                accounts: vec![],
+                data: vec![],
+            }],
+            ..Default::default()
+        };
+        let decoded = ledger.index(&tx).unwrap();
+        assert!(!decoded.succeeded);
+        assert!(decoded.instructions.is_empty());
+        assert!(ledger.store().by_payer(&xusdc::ID).unwrap().is_empty());
+    }
+}
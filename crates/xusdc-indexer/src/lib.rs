@@ -0,0 +1,23 @@
+//! Decodes xUSDC instructions and events from confirmed transactions and keeps a
+//! ledger of settled payments per payer and payee, net of refunds.
+//!
+//! Feed every transaction that touches the program to [`Ledger::index`], in the order
+//! they were confirmed. Payments are keyed by their nonce, and by their position in a
+//! split payment, so replaying a transaction is harmless.
+
+mod decode;
+mod error;
+mod ledger;
+mod store;
+mod transaction;
+
+pub use decode::{
+    decode_event, decode_instruction, decode_transaction, DecodedInstruction, DecodedTransaction,
+    XusdcEvent, XusdcInstruction,
+};
+pub use error::{IndexerError, Result};
+pub use ledger::{AccountSummary, Ledger, Payment};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+pub use store::{MemoryStore, PaymentStore};
+pub use transaction::TransactionRecord;
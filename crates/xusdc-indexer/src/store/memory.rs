@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+
+use super::PaymentStore;
+use crate::error::Result;
+use crate::ledger::Payment;
+
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    payments: Vec<Payment>,
+    by_nonce: HashMap<([u8; 32], Option<u8>), usize>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.payments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.payments.is_empty()
+    }
+}
+
+impl PaymentStore for MemoryStore {
+    fn insert(&mut self, payment: &Payment) -> Result<bool> {
+        let key = (payment.nonce, payment.split);
+        if self.by_nonce.contains_key(&key) {
+            return Ok(false);
+        }
+        self.by_nonce.insert(key, self.payments.len());
+        self.payments.push(payment.clone());
+        Ok(true)
+    }
+
+    fn set_refunded(&mut self, nonce: &[u8; 32], refunded: u64) -> Result<bool> {
+        let Some(&i) = self.by_nonce.get(&(*nonce, None)) else {
+            return Ok(false);
+        };
+        let payment = &mut self.payments[i];
+        payment.refunded = payment.refunded.max(refunded);
+        Ok(true)
+    }
+
+    fn get(&self, nonce: &[u8; 32]) -> Result<Vec<Payment>> {
+        Ok(self
+            .payments
+            .iter()
+            .filter(|p| p.nonce == *nonce)
+            .cloned()
+            .collect())
+    }
+
+    fn by_payer(&self, payer: &Pubkey) -> Result<Vec<Payment>> {
+        Ok(self
+            .payments
+            .iter()
+            .filter(|p| p.from == *payer)
+            .cloned()
+            .collect())
+    }
+
+    fn by_payee(&self, payee: &Pubkey) -> Result<Vec<Payment>> {
+        Ok(self
+            .payments
+            .iter()
+            .filter(|p| p.to == *payee)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        let mut store = MemoryStore::new();
+        super::super::tests::check_store(&mut store);
+        assert_eq!(store.len(), 5);
+    }
+}
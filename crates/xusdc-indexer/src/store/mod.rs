@@ -0,0 +1,108 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::error::Result;
+use crate::ledger::Payment;
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Where the ledger keeps its payments. Lookups return payments in the order they
+/// were inserted.
+pub trait PaymentStore {
+    /// Record a payment, returning `false` if one with the same nonce and split
+    /// already exists
+    fn insert(&mut self, payment: &Payment) -> Result<bool>;
+    /// Record that `refunded` of the payment with `nonce` has been returned in total,
+    /// keeping the larger total if one is already recorded. Returns `false` if no such
+    /// payment exists; split payments can't be refunded.
+    fn set_refunded(&mut self, nonce: &[u8; 32], refunded: u64) -> Result<bool>;
+    /// The payments recorded with `nonce`: one, or one per split
+    fn get(&self, nonce: &[u8; 32]) -> Result<Vec<Payment>>;
+    fn by_payer(&self, payer: &Pubkey) -> Result<Vec<Payment>>;
+    fn by_payee(&self, payee: &Pubkey) -> Result<Vec<Payment>>;
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn payment(nonce: u8, from: Pubkey, to: Pubkey, amount: u64) -> Payment {
+        Payment {
+            nonce: [nonce; 32],
+            split: None,
+            from,
+            to,
+            amount,
+            refunded: 0,
+            facilitator: Pubkey::new_unique(),
+            valid_until: 1_700_000_000 + nonce as i64,
+            signature: format!("sig-{nonce}"),
+            slot: nonce as u64,
+            block_time: nonce.is_multiple_of(2).then_some(1_600_000_000),
+        }
+    }
+
+    /// Behaviour every `PaymentStore` must share
+    pub(crate) fn check_store(store: &mut impl PaymentStore) {
+        let (alice, bob, carol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let first = payment(1, alice, bob, 10);
+        let second = payment(2, bob, carol, u64::MAX);
+        let third = payment(3, alice, carol, 30);
+
+        assert!(store.insert(&first).unwrap());
+        assert!(store.insert(&second).unwrap());
+        assert!(store.insert(&third).unwrap());
+        // Same nonce again: the first record wins
+        assert!(!store.insert(&payment(1, carol, alice, 99)).unwrap());
+
+        assert_eq!(store.get(&[2; 32]).unwrap(), vec![second.clone()]);
+        assert!(store.get(&[9; 32]).unwrap().is_empty());
+        assert_eq!(
+            store.by_payer(&alice).unwrap(),
+            vec![first.clone(), third.clone()]
+        );
+        assert_eq!(store.by_payee(&carol).unwrap(), vec![second, third.clone()]);
+        assert_eq!(store.by_payee(&bob).unwrap(), vec![first.clone()]);
+        assert!(store.by_payer(&carol).unwrap().is_empty());
+
+        // Refund totals only grow, and only apply to recorded payments
+        assert!(store.set_refunded(&[1; 32], 4).unwrap());
+        assert!(store.set_refunded(&[1; 32], 3).unwrap());
+        assert!(!store.set_refunded(&[9; 32], 1).unwrap());
+        let refunded = Payment {
+            refunded: 4,
+            ..first
+        };
+        assert_eq!(store.get(&[1; 32]).unwrap(), vec![refunded.clone()]);
+        assert_eq!(store.by_payer(&alice).unwrap(), vec![refunded, third]);
+
+        // Each split shares the nonce, and is recorded once
+        let splits = [
+            Payment {
+                split: Some(0),
+                ..payment(4, bob, alice, 5)
+            },
+            Payment {
+                split: Some(1),
+                ..payment(4, bob, carol, 6)
+            },
+        ];
+        for split in &splits {
+            assert!(store.insert(split).unwrap());
+        }
+        assert!(!store.insert(&splits[1]).unwrap());
+        assert!(!store.set_refunded(&[4; 32], 1).unwrap());
+        assert_eq!(store.get(&[4; 32]).unwrap(), splits.to_vec());
+        assert_eq!(store.by_payee(&alice).unwrap(), vec![splits[0].clone()]);
+        assert_eq!(store.by_payer(&bob).unwrap()[1..], splits);
+    }
+}
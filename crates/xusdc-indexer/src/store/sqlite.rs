@@ -0,0 +1,230 @@
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use rusqlite::{params, Connection, Row};
+
+use super::PaymentStore;
+use crate::error::Result;
+use crate::ledger::Payment;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS payments (
+    nonce BLOB PRIMARY KEY NOT NULL,
+    payer BLOB NOT NULL,
+    payee BLOB NOT NULL,
+    amount INTEGER NOT NULL,
+    facilitator BLOB NOT NULL,
+    valid_until INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER
+);
+CREATE INDEX IF NOT EXISTS payments_payer ON payments (payer);
+CREATE INDEX IF NOT EXISTS payments_payee ON payments (payee);
+";
+
+/// Changes to `SCHEMA`, in order. A database's `user_version` counts the ones it has.
+const MIGRATIONS: &[&str] = &[
+    // Refunds, netted against the payment they return
+    "ALTER TABLE payments ADD COLUMN refunded INTEGER NOT NULL DEFAULT 0;",
+    // Split payments, one row per recipient under the payment's nonce. Rows keep their
+    // rowid, which orders lookups.
+    "CREATE TABLE payments_by_split (
+        nonce BLOB NOT NULL,
+        payer BLOB NOT NULL,
+        payee BLOB NOT NULL,
+        amount INTEGER NOT NULL,
+        facilitator BLOB NOT NULL,
+        valid_until INTEGER NOT NULL,
+        signature TEXT NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        refunded INTEGER NOT NULL DEFAULT 0,
+        split INTEGER
+    );
+    INSERT INTO payments_by_split (rowid, nonce, payer, payee, amount, facilitator,
+        valid_until, signature, slot, block_time, refunded)
+        SELECT rowid, nonce, payer, payee, amount, facilitator, valid_until, signature,
+            slot, block_time, refunded FROM payments;
+    DROP TABLE payments;
+    ALTER TABLE payments_by_split RENAME TO payments;
+    CREATE UNIQUE INDEX payments_nonce ON payments (nonce, IFNULL(split, -1));
+    CREATE INDEX payments_payer ON payments (payer);
+    CREATE INDEX payments_payee ON payments (payee);",
+];
+
+const COLUMNS: &str = "nonce, payer, payee, amount, facilitator, valid_until, signature, slot, \
+                       block_time, refunded, split";
+
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    pub fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        let applied: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied as usize) {
+            conn.execute_batch(&format!(
+                "BEGIN; {migration} PRAGMA user_version = {}; COMMIT;",
+                version + 1
+            ))?;
+        }
+        Ok(Self { conn })
+    }
+
+    fn query(&self, filter: &str, key: &[u8]) -> Result<Vec<Payment>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {COLUMNS} FROM payments WHERE {filter} = ?1 ORDER BY rowid"
+        ))?;
+        let payments = stmt
+            .query_map([key], payment_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(payments)
+    }
+}
+
+// SQLite integers are signed, so `u64` columns are stored as their `i64` bit pattern
+fn payment_from_row(row: &Row) -> rusqlite::Result<Payment> {
+    let pubkey = |i| row.get::<_, [u8; 32]>(i).map(Pubkey::new_from_array);
+    Ok(Payment {
+        nonce: row.get(0)?,
+        from: pubkey(1)?,
+        to: pubkey(2)?,
+        amount: row.get::<_, i64>(3)? as u64,
+        facilitator: pubkey(4)?,
+        valid_until: row.get(5)?,
+        signature: row.get(6)?,
+        slot: row.get::<_, i64>(7)? as u64,
+        block_time: row.get(8)?,
+        refunded: row.get::<_, i64>(9)? as u64,
+        split: row.get(10)?,
+    })
+}
+
+impl PaymentStore for SqliteStore {
+    fn insert(&mut self, payment: &Payment) -> Result<bool> {
+        let inserted = self.conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO payments ({COLUMNS}) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"
+            ),
+            params![
+                payment.nonce,
+                payment.from.to_bytes(),
+                payment.to.to_bytes(),
+                payment.amount as i64,
+                payment.facilitator.to_bytes(),
+                payment.valid_until,
+                payment.signature,
+                payment.slot as i64,
+                payment.block_time,
+                payment.refunded as i64,
+                payment.split,
+            ],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    fn set_refunded(&mut self, nonce: &[u8; 32], refunded: u64) -> Result<bool> {
+        // Compared here rather than in SQL, which would see the stored bit pattern as
+        // signed
+        let Some(payment) = self.get(nonce)?.into_iter().find(|p| p.split.is_none()) else {
+            return Ok(false);
+        };
+        self.conn.execute(
+            "UPDATE payments SET refunded = ?2 WHERE nonce = ?1 AND split IS NULL",
+            params![nonce, payment.refunded.max(refunded) as i64],
+        )?;
+        Ok(true)
+    }
+
+    fn get(&self, nonce: &[u8; 32]) -> Result<Vec<Payment>> {
+        self.query("nonce", nonce)
+    }
+
+    fn by_payer(&self, payer: &Pubkey) -> Result<Vec<Payment>> {
+        self.query("payer", payer.as_ref())
+    }
+
+    fn by_payee(&self, payee: &Pubkey) -> Result<Vec<Payment>> {
+        self.query("payee", payee.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite_store() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        super::super::tests::check_store(&mut store);
+    }
+
+    #[test]
+    fn test_sqlite_store_migrates_old_databases() {
+        // A database written before refunds and split payments were recorded
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.execute(
+            "INSERT INTO payments (nonce, payer, payee, amount, facilitator, valid_until, \
+             signature, slot, block_time) VALUES (?1, ?2, ?3, 10, ?4, 0, 'sig', 1, NULL)",
+            params![
+                [1u8; 32],
+                [2u8; 32],
+                [3u8; 32],
+                Pubkey::new_unique().to_bytes()
+            ],
+        )
+        .unwrap();
+
+        let mut store = SqliteStore::from_connection(conn).unwrap();
+        let [old] = store.get(&[1; 32]).unwrap().try_into().unwrap();
+        assert_eq!((old.refunded, old.split), (0, None));
+        assert!(store.set_refunded(&[1; 32], 5).unwrap());
+        assert_eq!(store.get(&[1; 32]).unwrap()[0].net_amount(), 5);
+        assert!(!store.insert(&old).unwrap());
+
+        // Splits of one payment now share its nonce, after the rows before them
+        let splits = [0, 1].map(|split| Payment {
+            nonce: [4; 32],
+            split: Some(split),
+            ..old.clone()
+        });
+        for split in &splits {
+            assert!(store.insert(split).unwrap());
+        }
+        assert_eq!(store.get(&[4; 32]).unwrap(), splits);
+        assert_eq!(store.by_payer(&old.from).unwrap()[1..], splits);
+
+        // Reopening applies nothing twice
+        let store = SqliteStore::from_connection(store.conn).unwrap();
+        assert_eq!(store.get(&[1; 32]).unwrap()[0].refunded, 5);
+        assert_eq!(store.get(&[4; 32]).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_sqlite_store_persists() {
+        let path = std::env::temp_dir().join(format!(
+            "xusdc-indexer-{}-{}.sqlite",
+            std::process::id(),
+            Pubkey::new_unique()
+        ));
+        {
+            let mut store = SqliteStore::open(&path).unwrap();
+            super::super::tests::check_store(&mut store);
+        }
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get(&[1; 32]).unwrap().len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
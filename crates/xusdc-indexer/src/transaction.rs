@@ -0,0 +1,46 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::CompiledInstruction;
+use anchor_lang::solana_program::message::inner_instruction::InnerInstructionsList;
+use anchor_lang::solana_program::message::Message;
+
+/// A confirmed transaction, as returned by `getTransaction` or recorded by litesvm
+#[derive(Debug, Clone, Default)]
+pub struct TransactionRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// `None` when the transaction succeeded
+    pub err: Option<String>,
+    /// Static keys followed by any keys loaded from address lookup tables
+    pub account_keys: Vec<Pubkey>,
+    pub instructions: Vec<CompiledInstruction>,
+    /// One list per top-level instruction, as in the transaction status metadata
+    pub inner_instructions: InnerInstructionsList,
+    pub log_messages: Vec<String>,
+}
+
+impl TransactionRecord {
+    /// Build a record for a legacy transaction
+    pub fn new(
+        signature: impl Into<String>,
+        slot: u64,
+        message: &Message,
+        inner_instructions: InnerInstructionsList,
+        log_messages: Vec<String>,
+    ) -> Self {
+        Self {
+            signature: signature.into(),
+            slot,
+            block_time: None,
+            err: None,
+            account_keys: message.account_keys.clone(),
+            instructions: message.instructions.clone(),
+            inner_instructions,
+            log_messages,
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.err.is_none()
+    }
+}
@@ -0,0 +1,357 @@
+//! Replays xUSDC transactions through litesvm and indexes what they recorded.
+//!
+//! The bank comes from `xusdc-harness`, which doesn't verify transaction signatures,
+//! so transactions can be sent as the admin and the xUSDC mint without their keypairs;
+//! payment authorizations are still signed and checked by the program.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::message::Message;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use litesvm::LiteSVM;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use xusdc::state::*;
+use xusdc_client::instruction::{self, Collateral};
+use xusdc_client::{MintMetadata, PaymentAuthorization, Split, SplitPaymentAuthorization};
+use xusdc_indexer::{
+    Ledger, MemoryStore, SqliteStore, TransactionRecord, XusdcEvent, XusdcInstruction,
+};
+
+const TEN_USDC: u64 = 10_000_000;
+
+struct Replay {
+    svm: LiteSVM,
+    records: Vec<TransactionRecord>,
+}
+
+impl Replay {
+    fn new() -> Self {
+        Self {
+            svm: xusdc_harness::bank(),
+            records: vec![],
+        }
+    }
+
+    fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    /// Send a transaction and keep its record for indexing
+    fn send(&mut self, payer: &Pubkey, ixs: &[Instruction]) {
+        let mut message = Message::new(ixs, Some(payer));
+        message.recent_blockhash = self.svm.latest_blockhash();
+        // Only the first signature has to be unique; nothing is verified
+        let signatures = (0..message.header.num_required_signatures)
+            .map(|_| Keypair::new().sign_message(b"replay"))
+            .collect();
+        let tx = Transaction {
+            signatures,
+            message,
+        };
+        let meta = self
+            .svm
+            .send_transaction(tx.clone())
+            .unwrap_or_else(|e| panic!("Error: {}", e.meta.logs.join("\n")));
+        let slot = self.svm.get_sysvar::<Clock>().slot;
+        self.records.push(TransactionRecord::new(
+            tx.signatures[0].to_string(),
+            slot,
+            &tx.message,
+            meta.inner_instructions,
+            meta.logs,
+        ));
+    }
+
+    fn initialize(&mut self) {
+        let ix = instruction::initialize(
+            &ADMIN_KEY,
+            &USDC_MINT_KEY,
+            &MintMetadata::xusdc(xusdc_harness::METADATA_URI),
+        );
+        self.send(&ADMIN_KEY, &[ix]);
+    }
+
+    /// A user with xUSDC and SOL for fees
+    fn funded_user(&mut self, amount: u64) -> Keypair {
+        let user = Keypair::new();
+        let user_key = user.pubkey();
+        self.svm.airdrop(&user_key, 1_000_000_000).unwrap();
+        let usdc = Collateral::USDC;
+
+        let mut ixs = vec![
+            create_associated_token_account_idempotent(
+                &ADMIN_KEY,
+                &user_key,
+                &USDC_MINT_KEY,
+                &spl_token::ID,
+            ),
+            create_associated_token_account_idempotent(
+                &ADMIN_KEY,
+                &user_key,
+                &XUSDC_MINT_KEY,
+                &spl_token_2022::ID,
+            ),
+        ];
+        if amount > 0 {
+            ixs.push(
+                spl_token::instruction::mint_to(
+                    &spl_token::ID,
+                    &USDC_MINT_KEY,
+                    &usdc.token_address(&user_key),
+                    &ADMIN_KEY,
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+            );
+            ixs.push(instruction::deposit(&user_key, &usdc, amount));
+        }
+        self.send(&ADMIN_KEY, &ixs);
+        user
+    }
+
+    fn contribute_rent(&mut self, user: &Pubkey, amount: u64) {
+        self.send(user, &[instruction::contribute_rent(user, amount)]);
+    }
+
+    fn settle(
+        &mut self,
+        facilitator: &Pubkey,
+        from: &Keypair,
+        to: &Pubkey,
+        amount: u64,
+        nonce: [u8; 32],
+    ) -> PaymentAuthorization {
+        let payload = PaymentAuthorization::builder(*to, amount)
+            .nonce(nonce)
+            .now(self.now())
+            .sign(from)
+            .unwrap();
+        self.send(
+            facilitator,
+            &[instruction::settle_payment(facilitator, &payload)],
+        );
+        payload.payment_auth
+    }
+
+    fn settle_split(
+        &mut self,
+        facilitator: &Pubkey,
+        from: &Keypair,
+        splits: Vec<Split>,
+        nonce: [u8; 32],
+    ) {
+        let payload = SplitPaymentAuthorization::builder(splits)
+            .nonce(nonce)
+            .now(self.now())
+            .sign(from)
+            .unwrap();
+        self.send(
+            facilitator,
+            &[instruction::settle_split_payment(facilitator, &payload)],
+        );
+    }
+
+    fn refund(&mut self, payee: &Pubkey, payer: &Pubkey, nonce: [u8; 32], amount: u64) {
+        self.send(
+            payee,
+            &[instruction::refund_payment(payee, payer, &nonce, amount)],
+        );
+    }
+}
+
+#[test]
+fn test_replay_payments_into_ledgers() {
+    let mut replay = Replay::new();
+    replay.initialize();
+    let alice = replay.funded_user(TEN_USDC);
+    let bob = replay.funded_user(TEN_USDC);
+    let carol = replay.funded_user(0);
+    let facilitator = replay.funded_user(0).pubkey();
+    replay.contribute_rent(&facilitator, 100_000_000);
+
+    let first = replay.settle(&facilitator, &alice, &bob.pubkey(), 3_000_000, [1; 32]);
+    let second = replay.settle(&facilitator, &alice, &carol.pubkey(), 2_000_000, [2; 32]);
+    let third = replay.settle(&facilitator, &bob, &carol.pubkey(), 1_000_000, [3; 32]);
+
+    let mut memory = Ledger::new(MemoryStore::new());
+    let mut sqlite = Ledger::new(SqliteStore::open_in_memory().unwrap());
+    for record in &replay.records {
+        let decoded = memory.index(record).unwrap();
+        assert_eq!(sqlite.index(record).unwrap(), decoded);
+    }
+    // Replaying the same history records nothing new
+    for record in &replay.records {
+        memory.index(record).unwrap();
+        sqlite.index(record).unwrap();
+    }
+    assert_eq!(memory.store().len(), 3);
+
+    let alice_payments = memory.payments_by_payer(&alice.pubkey()).unwrap();
+    assert_eq!(
+        alice_payments,
+        sqlite.payments_by_payer(&alice.pubkey()).unwrap()
+    );
+    assert_eq!(alice_payments.len(), 2);
+    for (payment, auth) in alice_payments.iter().zip([&first, &second]) {
+        assert_eq!(payment.to, auth.to);
+        assert_eq!(payment.amount, auth.amount);
+        assert_eq!(payment.nonce, auth.nonce);
+        assert_eq!(payment.valid_until, auth.valid_until);
+        assert_eq!(payment.facilitator, facilitator);
+    }
+
+    let carol_payments = sqlite.payments_by_payee(&carol.pubkey()).unwrap();
+    assert_eq!(
+        carol_payments.iter().map(|p| p.from).collect::<Vec<_>>(),
+        vec![alice.pubkey(), bob.pubkey()]
+    );
+    assert_eq!(carol_payments[1].nonce, third.nonce);
+
+    let summary = memory.summary(&bob.pubkey()).unwrap();
+    assert_eq!(summary, sqlite.summary(&bob.pubkey()).unwrap());
+    assert_eq!((summary.payments_sent, summary.amount_sent), (1, 1_000_000));
+    assert_eq!(
+        (summary.payments_received, summary.amount_received),
+        (1, 3_000_000)
+    );
+
+    // Settlement signatures point back at the transactions
+    let [payment] = sqlite.payments(&[1; 32]).unwrap().try_into().unwrap();
+    assert!(replay
+        .records
+        .iter()
+        .any(|record| record.signature == payment.signature && record.slot == payment.slot));
+}
+
+#[test]
+fn test_replay_nets_refunds_against_their_payment() {
+    let mut replay = Replay::new();
+    replay.initialize();
+    let alice = replay.funded_user(TEN_USDC);
+    let bob = replay.funded_user(0);
+    let facilitator = replay.funded_user(0).pubkey();
+    replay.contribute_rent(&facilitator, 100_000_000);
+    let (alice_key, bob_key) = (alice.pubkey(), bob.pubkey());
+
+    replay.settle(&facilitator, &alice, &bob_key, 3_000_000, [1; 32]);
+    replay.settle(&facilitator, &alice, &bob_key, 2_000_000, [2; 32]);
+    replay.refund(&bob_key, &alice_key, [1; 32], 1_000_000);
+    replay.refund(&bob_key, &alice_key, [1; 32], 500_000);
+
+    let mut memory = Ledger::new(MemoryStore::new());
+    let mut sqlite = Ledger::new(SqliteStore::open_in_memory().unwrap());
+    // Twice, so replayed refunds are seen to count once
+    for record in replay.records.iter().chain(&replay.records) {
+        memory.index(record).unwrap();
+        sqlite.index(record).unwrap();
+    }
+
+    let [refunded] = memory.payments(&[1; 32]).unwrap().try_into().unwrap();
+    assert_eq!(vec![refunded.clone()], sqlite.payments(&[1; 32]).unwrap());
+    assert_eq!((refunded.amount, refunded.refunded), (3_000_000, 1_500_000));
+    assert_eq!(refunded.net_amount(), 1_500_000);
+    assert_eq!(memory.payments(&[2; 32]).unwrap()[0].refunded, 0);
+
+    let summary = memory.summary(&alice_key).unwrap();
+    assert_eq!(summary, sqlite.summary(&alice_key).unwrap());
+    assert_eq!((summary.payments_sent, summary.amount_sent), (2, 3_500_000));
+    let summary = sqlite.summary(&bob_key).unwrap();
+    assert_eq!(
+        (summary.payments_received, summary.amount_received),
+        (2, 3_500_000)
+    );
+}
+
+#[test]
+fn test_replay_records_each_split_recipient() {
+    let mut replay = Replay::new();
+    replay.initialize();
+    let alice = replay.funded_user(TEN_USDC);
+    let bob = replay.funded_user(0).pubkey();
+    let carol = replay.funded_user(0).pubkey();
+    let facilitator = replay.funded_user(0).pubkey();
+    replay.contribute_rent(&facilitator, 100_000_000);
+
+    let splits = vec![
+        Split {
+            recipient: bob,
+            amount: 3_000_000,
+        },
+        Split {
+            recipient: carol,
+            amount: 1_000_000,
+        },
+    ];
+    replay.settle_split(&facilitator, &alice, splits.clone(), [1; 32]);
+    replay.settle(&facilitator, &alice, &carol, 2_000_000, [2; 32]);
+
+    let mut memory = Ledger::new(MemoryStore::new());
+    let mut sqlite = Ledger::new(SqliteStore::open_in_memory().unwrap());
+    // Twice, so replayed splits are seen to count once
+    for record in replay.records.iter().chain(&replay.records) {
+        memory.index(record).unwrap();
+        sqlite.index(record).unwrap();
+    }
+    assert_eq!(memory.store().len(), 3);
+
+    let legs = memory.payments(&[1; 32]).unwrap();
+    assert_eq!(legs, sqlite.payments(&[1; 32]).unwrap());
+    assert_eq!(legs.len(), 2);
+    for (i, (leg, split)) in legs.iter().zip(&splits).enumerate() {
+        assert_eq!(leg.split, Some(i as u8));
+        assert_eq!((leg.from, leg.to), (alice.pubkey(), split.recipient));
+        assert_eq!(leg.amount, split.amount);
+        assert_eq!(leg.facilitator, facilitator);
+        assert_eq!(leg.signature, legs[0].signature);
+    }
+    assert_eq!(memory.payments(&[2; 32]).unwrap()[0].split, None);
+
+    let summary = memory.summary(&alice.pubkey()).unwrap();
+    assert_eq!(summary, sqlite.summary(&alice.pubkey()).unwrap());
+    assert_eq!((summary.payments_sent, summary.amount_sent), (3, 6_000_000));
+    let summary = sqlite.summary(&carol).unwrap();
+    assert_eq!(summary, memory.summary(&carol).unwrap());
+    assert_eq!(
+        (summary.payments_received, summary.amount_received),
+        (2, 3_000_000)
+    );
+    assert_eq!(sqlite.payments_by_payee(&bob).unwrap(), legs[..1].to_vec());
+}
+
+#[test]
+fn test_replay_decodes_instructions_and_events() {
+    let mut replay = Replay::new();
+    replay.initialize();
+    let alice = replay.funded_user(TEN_USDC);
+    let deposit = xusdc_indexer::decode_transaction(replay.records.last().unwrap()).unwrap();
+    assert_eq!(deposit.instructions.len(), 1);
+    assert_eq!(
+        deposit.instructions[0].instruction,
+        XusdcInstruction::Deposit { amount: TEN_USDC }
+    );
+    assert_eq!(deposit.instructions[0].accounts[0], alice.pubkey());
+    let [XusdcEvent::Deposited(event)] = deposit.events.as_slice() else {
+        panic!(
+            "expected a single Deposited event, got {:?}",
+            deposit.events
+        );
+    };
+    assert_eq!(event.user, alice.pubkey());
+    assert_eq!(event.collateral_mint, USDC_MINT_KEY);
+    assert_eq!(event.amount, TEN_USDC);
+
+    let initialize = xusdc_indexer::decode_transaction(&replay.records[0]).unwrap();
+    assert_eq!(
+        initialize.instructions[0].instruction,
+        XusdcInstruction::Initialize {
+            name: "xUSDC".to_string(),
+            symbol: "xUSDC".to_string(),
+            uri: xusdc_harness::METADATA_URI.to_string(),
+        }
+    );
+    assert!(initialize.events.is_empty());
+}
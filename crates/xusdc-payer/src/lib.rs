@@ -0,0 +1,187 @@
+//! Pay x402 paywalls in xUSDC from a reqwest client.
+//!
+//! [`AutoPay`] is a [`reqwest_middleware::Middleware`]. When a server answers 402 and
+//! accepts xUSDC on the payer's network, it signs a `PaymentAuthorization` for the
+//! price, if the [`SpendingPolicy`] allows it, and retries the request with
+//! `X-PAYMENT`. The authorization's `resource_hash` binds it to the request's method,
+//! the server's `resource` and the body, so it can only pay for that request:
+//!
+//! ```ignore
+//! let autopay = AutoPay::new(keypair, Network::Solana)
+//!     .policy(SpendingPolicy::new().max_per_request(50_000).total_budget(5_000_000));
+//! let client = ClientBuilder::new(reqwest::Client::new()).with(autopay.clone()).build();
+//! let weather = client.get("https://example.com/weather").send().await?;
+//! println!("spent {} so far", autopay.spent().total());
+//! ```
+//!
+//! A 402 with no xUSDC option, or whose body isn't an x402 response, is returned as
+//! is. A payment the policy refuses fails the request with [`PayError::Policy`].
+
+pub mod policy;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use http::{Extensions, HeaderValue, StatusCode};
+use reqwest::{Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{Middleware, Next};
+use xusdc_client::auth::DEFAULT_TTL;
+use xusdc_client::{PaymentAuthorization, SignError, Signer};
+use xusdc_x402::{
+    Network, PaymentPayload, PaymentRequiredResponse, SettleResponse, X_PAYMENT, X_PAYMENT_RESPONSE,
+};
+
+pub use policy::{PolicyError, Spend, SpendingPolicy, Spent, DEFAULT_MAX_PER_REQUEST};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PayError {
+    #[error("payment refused by the spending policy: {0}")]
+    Policy(#[from] PolicyError),
+    #[error("could not sign the payment: {0}")]
+    Sign(#[from] SignError),
+    /// The request body is a stream, so it cannot be sent again with the payment
+    #[error("payment required, but the request cannot be retried")]
+    NotRetryable,
+}
+
+/// Pays 402 responses. Clones share the record of what was spent.
+pub struct AutoPay<S> {
+    signer: Arc<S>,
+    network: Network,
+    policy: Arc<SpendingPolicy>,
+    spent: Arc<Mutex<Spent>>,
+}
+
+impl<S> Clone for AutoPay<S> {
+    fn clone(&self) -> Self {
+        Self {
+            signer: self.signer.clone(),
+            network: self.network,
+            policy: self.policy.clone(),
+            spent: self.spent.clone(),
+        }
+    }
+}
+
+impl<S: Signer + Send + Sync + 'static> AutoPay<S> {
+    /// Pay from `signer`'s xUSDC on `network`, with no limits until given a policy
+    pub fn new(signer: S, network: Network) -> Self {
+        Self {
+            signer: Arc::new(signer),
+            network,
+            policy: Arc::default(),
+            spent: Arc::default(),
+        }
+    }
+
+    pub fn policy(mut self, policy: SpendingPolicy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// What has been spent so far, across every clone
+    pub fn spent(&self) -> MutexGuard<'_, Spent> {
+        self.spent.lock().unwrap()
+    }
+
+    /// Sign for the xUSDC option in `required`, recording the spend. `None` if there
+    /// is no such option.
+    fn pay(
+        &self,
+        request: &Request,
+        required: &PaymentRequiredResponse,
+    ) -> Result<Option<(PaymentPayload, [u8; 32])>, PayError> {
+        let Some(requirements) = required.xusdc(self.network) else {
+            return Ok(None);
+        };
+        let url = request.url();
+        let host = url.host_str().unwrap_or_default();
+        let (to, amount) = (requirements.pay_to, requirements.max_amount_required);
+
+        // Check, sign and record under one lock so concurrent requests can't overspend
+        let mut spent = self.spent();
+        self.policy.check(&spent, host, &to, amount)?;
+        let ttl = DEFAULT_TTL.min(Duration::from_secs(requirements.max_timeout_seconds));
+        let body = request.body().and_then(|body| body.as_bytes());
+        let resource_hash =
+            requirements.resource_hash(request.method().as_str(), body.unwrap_or_default());
+        let payload = PaymentAuthorization::builder(to, amount)
+            .resource_hash(resource_hash)
+            .ttl(ttl)
+            .sign(&*self.signer)?;
+        let nonce = payload.payment_auth.nonce;
+        spent.record(Spend {
+            host: host.to_string(),
+            resource: url.to_string(),
+            to,
+            amount,
+            nonce,
+            settlement: None,
+        });
+        Ok(Some((PaymentPayload::new(self.network, &payload), nonce)))
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Signer + Send + Sync + 'static> Middleware for AutoPay<S> {
+    async fn handle(
+        &self,
+        request: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let retry = request.try_clone();
+        let response = next.clone().run(request, extensions).await?;
+        if response.status() != StatusCode::PAYMENT_REQUIRED {
+            return Ok(response);
+        }
+
+        // Keep what's needed to hand the 402 back untouched
+        let (url, version, headers) = (
+            response.url().clone(),
+            response.version(),
+            response.headers().clone(),
+        );
+        let body = response.bytes().await?;
+        let unpaid = || {
+            let mut response = http::Response::builder()
+                .status(StatusCode::PAYMENT_REQUIRED)
+                .version(version)
+                .url(url.clone())
+                .body(body.clone())
+                .expect("the parts came from a response");
+            *response.headers_mut() = headers.clone();
+            Ok(Response::from(response))
+        };
+        let Ok(required) = serde_json::from_slice::<PaymentRequiredResponse>(&body) else {
+            return unpaid();
+        };
+        let Some(mut retry) = retry else {
+            if required.xusdc(self.network).is_none() {
+                return unpaid();
+            }
+            return Err(reqwest_middleware::Error::middleware(
+                PayError::NotRetryable,
+            ));
+        };
+        let Some((payment, nonce)) = self
+            .pay(&retry, &required)
+            .map_err(reqwest_middleware::Error::middleware)?
+        else {
+            return unpaid();
+        };
+
+        let header = HeaderValue::from_str(&payment.to_header()).expect("base64 is a valid header");
+        retry.headers_mut().insert(X_PAYMENT, header);
+        let response = next.run(retry, extensions).await?;
+        let settlement = response
+            .headers()
+            .get(X_PAYMENT_RESPONSE)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| SettleResponse::from_header(header).ok());
+        if let Some(settlement) = settlement {
+            self.spent().settled(&nonce, settlement);
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,283 @@
+//! How much the payer may spend, and on whom
+
+use std::collections::{HashMap, HashSet};
+
+use solana_program::pubkey::Pubkey;
+use xusdc_x402::SettleResponse;
+
+/// The per-request maximum of [`SpendingPolicy::default`]: one xUSDC
+pub const DEFAULT_MAX_PER_REQUEST: u64 = 1_000_000;
+
+/// Limits on what the payer signs for. [`SpendingPolicy::default`] pays any paywall up
+/// to [`DEFAULT_MAX_PER_REQUEST`] a request; the budgets and allowed payees are
+/// optional.
+#[derive(Debug, Clone)]
+pub struct SpendingPolicy {
+    max_per_request: Option<u64>,
+    host_budget: Option<u64>,
+    total_budget: Option<u64>,
+    allowed_to: Option<HashSet<Pubkey>>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PolicyError {
+    #[error("{amount} exceeds the per-request maximum of {max}")]
+    RequestMaximum { amount: u64, max: u64 },
+    #[error(
+        "{amount} more would exceed the budget of {budget} for {host}, of which {spent} is spent"
+    )]
+    HostBudget {
+        host: String,
+        amount: u64,
+        spent: u128,
+        budget: u64,
+    },
+    #[error("{amount} more would exceed the total budget of {budget}, of which {spent} is spent")]
+    TotalBudget {
+        amount: u64,
+        spent: u128,
+        budget: u64,
+    },
+    #[error("{0} is not an allowed payee")]
+    PayeeNotAllowed(Pubkey),
+}
+
+impl Default for SpendingPolicy {
+    fn default() -> Self {
+        Self {
+            max_per_request: Some(DEFAULT_MAX_PER_REQUEST),
+            host_budget: None,
+            total_budget: None,
+            allowed_to: None,
+        }
+    }
+}
+
+impl SpendingPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refuse any single payment above `max`, instead of [`DEFAULT_MAX_PER_REQUEST`]
+    pub fn max_per_request(mut self, max: u64) -> Self {
+        self.max_per_request = Some(max);
+        self
+    }
+
+    /// Spend at most `budget` on each host
+    pub fn host_budget(mut self, budget: u64) -> Self {
+        self.host_budget = Some(budget);
+        self
+    }
+
+    /// Spend at most `budget` altogether
+    pub fn total_budget(mut self, budget: u64) -> Self {
+        self.total_budget = Some(budget);
+        self
+    }
+
+    /// Only pay `to`, and whoever else is allowed. Without any, every payee is.
+    pub fn allow_to(mut self, to: Pubkey) -> Self {
+        self.allowed_to.get_or_insert_with(HashSet::new).insert(to);
+        self
+    }
+
+    /// Whether paying `amount` to `to` for a request to `host` stays within the policy,
+    /// given what `spent` records
+    pub fn check(
+        &self,
+        spent: &Spent,
+        host: &str,
+        to: &Pubkey,
+        amount: u64,
+    ) -> Result<(), PolicyError> {
+        if let Some(allowed_to) = &self.allowed_to {
+            if !allowed_to.contains(to) {
+                return Err(PolicyError::PayeeNotAllowed(*to));
+            }
+        }
+        if let Some(max) = self.max_per_request {
+            if amount > max {
+                return Err(PolicyError::RequestMaximum { amount, max });
+            }
+        }
+        if let Some(budget) = self.host_budget {
+            let spent = spent.on_host(host);
+            if spent + amount as u128 > budget as u128 {
+                return Err(PolicyError::HostBudget {
+                    host: host.to_string(),
+                    amount,
+                    spent,
+                    budget,
+                });
+            }
+        }
+        if let Some(budget) = self.total_budget {
+            let spent = spent.total();
+            if spent + amount as u128 > budget as u128 {
+                return Err(PolicyError::TotalBudget {
+                    amount,
+                    spent,
+                    budget,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A payment the payer signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spend {
+    pub host: String,
+    /// The URL that asked for payment
+    pub resource: String,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub nonce: [u8; 32],
+    /// What the server reported in `X-PAYMENT-RESPONSE`, if it did
+    pub settlement: Option<SettleResponse>,
+}
+
+/// Every payment signed so far. A signed authorization can be settled whether or not
+/// the server answers, so it counts against the budgets from the moment it is signed.
+/// Totals are `u128`, so no number of payments can overflow them.
+#[derive(Debug, Clone, Default)]
+pub struct Spent {
+    spends: Vec<Spend>,
+    by_host: HashMap<String, u128>,
+    total: u128,
+}
+
+impl Spent {
+    pub fn total(&self) -> u128 {
+        self.total
+    }
+
+    pub fn on_host(&self, host: &str) -> u128 {
+        self.by_host.get(host).copied().unwrap_or(0)
+    }
+
+    /// Oldest first
+    pub fn spends(&self) -> &[Spend] {
+        &self.spends
+    }
+
+    pub(crate) fn record(&mut self, spend: Spend) {
+        *self.by_host.entry(spend.host.clone()).or_default() += spend.amount as u128;
+        self.total += spend.amount as u128;
+        self.spends.push(spend);
+    }
+
+    pub(crate) fn settled(&mut self, nonce: &[u8; 32], settlement: SettleResponse) {
+        if let Some(spend) = self.spends.iter_mut().rev().find(|s| &s.nonce == nonce) {
+            spend.settlement = Some(settlement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spend(host: &str, amount: u64) -> Spend {
+        Spend {
+            host: host.to_string(),
+            resource: format!("http://{host}/"),
+            to: Pubkey::new_unique(),
+            amount,
+            nonce: [0; 32],
+            settlement: None,
+        }
+    }
+
+    #[test]
+    fn test_budgets() {
+        let policy = SpendingPolicy::new()
+            .max_per_request(100)
+            .host_budget(150)
+            .total_budget(250);
+        let to = Pubkey::new_unique();
+        let mut spent = Spent::default();
+
+        assert_eq!(
+            policy.check(&spent, "a.com", &to, 101),
+            Err(PolicyError::RequestMaximum {
+                amount: 101,
+                max: 100
+            })
+        );
+        spent.record(spend("a.com", 100));
+        assert_eq!(
+            policy.check(&spent, "a.com", &to, 60),
+            Err(PolicyError::HostBudget {
+                host: "a.com".to_string(),
+                amount: 60,
+                spent: 100,
+                budget: 150
+            })
+        );
+        assert_eq!(policy.check(&spent, "a.com", &to, 50), Ok(()));
+        spent.record(spend("b.com", 100));
+        assert_eq!(
+            policy.check(&spent, "c.com", &to, 60),
+            Err(PolicyError::TotalBudget {
+                amount: 60,
+                spent: 200,
+                budget: 250
+            })
+        );
+        assert_eq!(policy.check(&spent, "c.com", &to, 50), Ok(()));
+        assert_eq!(spent.on_host("a.com"), 100);
+        assert_eq!(spent.total(), 200);
+    }
+
+    #[test]
+    fn test_allowed_payees() {
+        let (merchant, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let spent = Spent::default();
+
+        assert_eq!(
+            SpendingPolicy::new().check(&spent, "a.com", &other, DEFAULT_MAX_PER_REQUEST),
+            Ok(())
+        );
+        let policy = SpendingPolicy::new().allow_to(merchant);
+        assert_eq!(policy.check(&spent, "a.com", &merchant, 1), Ok(()));
+        assert_eq!(
+            policy.check(&spent, "a.com", &other, 1),
+            Err(PolicyError::PayeeNotAllowed(other))
+        );
+    }
+
+    #[test]
+    fn test_default_policy_caps_each_request() {
+        let to = Pubkey::new_unique();
+        let spent = Spent::default();
+        assert_eq!(
+            SpendingPolicy::default().check(&spent, "a.com", &to, DEFAULT_MAX_PER_REQUEST + 1),
+            Err(PolicyError::RequestMaximum {
+                amount: DEFAULT_MAX_PER_REQUEST + 1,
+                max: DEFAULT_MAX_PER_REQUEST
+            })
+        );
+        let unlimited = SpendingPolicy::new().max_per_request(u64::MAX);
+        assert_eq!(unlimited.check(&spent, "a.com", &to, u64::MAX), Ok(()));
+    }
+
+    #[test]
+    fn test_spent_does_not_overflow() {
+        let mut spent = Spent::default();
+        spent.record(spend("a.com", u64::MAX));
+        spent.record(spend("a.com", 1));
+        spent.record(spend("b.com", 1));
+        assert_eq!(spent.on_host("a.com"), u64::MAX as u128 + 1);
+        assert_eq!(spent.on_host("b.com"), 1);
+        assert_eq!(spent.total(), u64::MAX as u128 + 2);
+        assert_eq!(spent.spends().len(), 3);
+        let policy = SpendingPolicy::new().total_budget(u64::MAX);
+        assert!(matches!(
+            policy.check(&spent, "c.com", &Pubkey::new_unique(), 1),
+            Err(PolicyError::TotalBudget { .. })
+        ));
+    }
+}
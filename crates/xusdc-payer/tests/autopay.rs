@@ -0,0 +1,289 @@
+//! Paying a local mock server: paywalled routes settled by a facilitator that accepts
+//! every payment, plus 402s the payer can't or shouldn't pay
+
+use std::sync::{Arc, Mutex};
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use solana_keypair::Keypair;
+use solana_program::pubkey::Pubkey;
+use solana_signer::Signer as _;
+use xusdc_payer::{AutoPay, PayError, PolicyError, SpendingPolicy};
+use xusdc_paywall::{Facilitate, FacilitatorError, Paywall};
+use xusdc_x402::{
+    Network, PaymentPayload, PaymentRequiredResponse, PaymentRequirements, SettleResponse,
+    VerifyResponse,
+};
+
+const PRICE: u64 = 10_000;
+
+/// Accepts every payment, remembering the authorizations it settled
+#[derive(Clone, Default)]
+struct AcceptAll {
+    settled: Arc<Mutex<Vec<PaymentPayload>>>,
+}
+
+impl Facilitate for AcceptAll {
+    async fn verify(
+        &self,
+        _: &PaymentPayload,
+        _: &PaymentRequirements,
+    ) -> Result<VerifyResponse, FacilitatorError> {
+        Ok(VerifyResponse {
+            is_valid: true,
+            invalid_reason: None,
+            payer: None,
+        })
+    }
+
+    async fn settle(
+        &self,
+        payment: &PaymentPayload,
+        _: &PaymentRequirements,
+    ) -> Result<SettleResponse, FacilitatorError> {
+        let mut settled = self.settled.lock().unwrap();
+        settled.push(payment.clone());
+        Ok(SettleResponse {
+            success: true,
+            error_reason: None,
+            transaction: Some(format!("settlement{}", settled.len())),
+            network: payment.network,
+            payer: None,
+        })
+    }
+}
+
+struct Server {
+    /// Reached as `127.0.0.1:port` or `localhost:port`, two hosts as far as budgets go
+    port: u16,
+    merchant: Pubkey,
+    stranger: Pubkey,
+    facilitator: AcceptAll,
+}
+
+impl Server {
+    async fn start() -> Self {
+        let facilitator = AcceptAll::default();
+        let (merchant, stranger) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Payers bind payments to the resource the server names, whatever URL they used
+        let public_url = "https://weather.example";
+        let paywall = Paywall::new(
+            facilitator.clone(),
+            Network::SolanaDevnet,
+            merchant,
+            public_url,
+        );
+        let stranger_paywall = Paywall::new(
+            facilitator.clone(),
+            Network::SolanaDevnet,
+            stranger,
+            public_url,
+        );
+        let mainnet_only = PaymentRequiredResponse::new(
+            vec![PaymentRequirements::new(Network::Solana, merchant, PRICE)],
+            None,
+        );
+        let app = Router::new()
+            .route("/free", get(|| async { "free" }))
+            .route(
+                "/weather",
+                get(|| async { "sunny" }).layer(paywall.price(PRICE)),
+            )
+            .route(
+                "/premium",
+                get(|| async { "premium" }).layer(paywall.price(100 * PRICE)),
+            )
+            .route(
+                "/echo",
+                post(|body: String| async move { body }).layer(paywall.price(PRICE)),
+            )
+            .route(
+                "/stranger",
+                get(|| async { "who?" }).layer(stranger_paywall.price(PRICE)),
+            )
+            .route(
+                "/mainnet",
+                get(move || async move { (StatusCode::PAYMENT_REQUIRED, Json(mainnet_only)) }),
+            )
+            .route(
+                "/legacy",
+                get(|| async {
+                    (StatusCode::PAYMENT_REQUIRED, "pay at the counter").into_response()
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Self {
+            port,
+            merchant,
+            stranger,
+            facilitator,
+        }
+    }
+
+    fn url(&self, host: &str, path: &str) -> String {
+        format!("http://{host}:{}{path}", self.port)
+    }
+
+    fn settled(&self) -> Vec<PaymentPayload> {
+        self.facilitator.settled.lock().unwrap().clone()
+    }
+}
+
+fn client(autopay: &AutoPay<Keypair>) -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(autopay.clone())
+        .build()
+}
+
+fn policy_error(error: reqwest_middleware::Error) -> PolicyError {
+    match error {
+        reqwest_middleware::Error::Middleware(error) => match error.downcast().unwrap() {
+            PayError::Policy(error) => error,
+            error => panic!("{error}"),
+        },
+        error => panic!("{error}"),
+    }
+}
+
+#[tokio::test]
+async fn test_pays_and_records_spend() {
+    let server = Server::start().await;
+    let payer = Keypair::new();
+    let payer_key = payer.pubkey();
+    let autopay = AutoPay::new(payer, Network::SolanaDevnet);
+    let client = client(&autopay);
+
+    let response = client
+        .get(server.url("127.0.0.1", "/free"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "free");
+    assert_eq!(autopay.spent().total(), 0);
+
+    let response = client
+        .get(server.url("127.0.0.1", "/weather"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "sunny");
+
+    // Bodies are sent again with the payment
+    let response = client
+        .post(server.url("127.0.0.1", "/echo"))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.text().await.unwrap(), "hello");
+
+    let settled = server.settled();
+    assert_eq!(settled.len(), 2);
+    let auth = settled[0].settle_payload().unwrap().payment_auth;
+    assert_eq!(
+        (auth.from, auth.to, auth.amount),
+        (payer_key, server.merchant, PRICE)
+    );
+
+    let spent = autopay.spent();
+    assert_eq!(spent.total(), 2 * PRICE as u128);
+    assert_eq!(spent.on_host("127.0.0.1"), 2 * PRICE as u128);
+    let spend = &spent.spends()[0];
+    assert_eq!(spend.resource, server.url("127.0.0.1", "/weather"));
+    assert_eq!(
+        (spend.to, spend.amount, spend.nonce),
+        (server.merchant, PRICE, auth.nonce)
+    );
+    assert_eq!(
+        spend.settlement.as_ref().unwrap().transaction.as_deref(),
+        Some("settlement1")
+    );
+}
+
+#[tokio::test]
+async fn test_spending_policy() {
+    let server = Server::start().await;
+    let autopay = AutoPay::new(Keypair::new(), Network::SolanaDevnet).policy(
+        SpendingPolicy::new()
+            .max_per_request(10 * PRICE)
+            .host_budget(2 * PRICE)
+            .total_budget(3 * PRICE)
+            .allow_to(server.merchant),
+    );
+    let client = client(&autopay);
+    let get = |host: &str, path: &str| client.get(server.url(host, path)).send();
+
+    let error = get("127.0.0.1", "/premium").await.unwrap_err();
+    assert_eq!(
+        policy_error(error),
+        PolicyError::RequestMaximum {
+            amount: 100 * PRICE,
+            max: 10 * PRICE
+        }
+    );
+    let error = get("127.0.0.1", "/stranger").await.unwrap_err();
+    assert_eq!(
+        policy_error(error),
+        PolicyError::PayeeNotAllowed(server.stranger)
+    );
+
+    for _ in 0..2 {
+        assert_eq!(
+            get("127.0.0.1", "/weather").await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+    let error = get("127.0.0.1", "/weather").await.unwrap_err();
+    assert!(
+        matches!(policy_error(error), PolicyError::HostBudget { spent, .. } if spent == 2 * PRICE as u128)
+    );
+
+    // Another host has its own budget, but not its own total
+    assert_eq!(
+        get("localhost", "/weather").await.unwrap().status(),
+        StatusCode::OK
+    );
+    let error = get("localhost", "/weather").await.unwrap_err();
+    assert!(
+        matches!(policy_error(error), PolicyError::TotalBudget { spent, .. } if spent == 3 * PRICE as u128)
+    );
+
+    assert_eq!(server.settled().len(), 3);
+    assert_eq!(autopay.spent().spends().len(), 3);
+}
+
+#[tokio::test]
+async fn test_unpayable_402_is_returned() {
+    let server = Server::start().await;
+    let autopay = AutoPay::new(Keypair::new(), Network::SolanaDevnet);
+    let client = client(&autopay);
+
+    // Only payable on another network
+    let response = client
+        .get(server.url("127.0.0.1", "/mainnet"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    let required: PaymentRequiredResponse =
+        serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(required.accepts[0].network, Network::Solana);
+
+    // Not x402 at all
+    let response = client
+        .get(server.url("127.0.0.1", "/legacy"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(response.text().await.unwrap(), "pay at the counter");
+
+    assert_eq!(autopay.spent().total(), 0);
+    assert!(server.settled().is_empty());
+}
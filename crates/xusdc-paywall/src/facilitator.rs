@@ -0,0 +1,193 @@
+//! Who the paywall asks to verify and settle payments
+
+use std::future::Future;
+
+use xusdc_x402::{PaymentPayload, PaymentRequirements, SettleResponse, VerifyResponse};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FacilitatorError {
+    #[cfg(feature = "remote")]
+    #[error("facilitator request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("facilitator is unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// Verifies and settles payments for the paywall. Turning a payment down is an
+/// answer, not an error.
+pub trait Facilitate: Send + Sync + 'static {
+    fn verify(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> impl Future<Output = Result<VerifyResponse, FacilitatorError>> + Send;
+
+    fn settle(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> impl Future<Output = Result<SettleResponse, FacilitatorError>> + Send;
+}
+
+/// Verifies and settles in-process, sending transactions through its own backend
+#[cfg(feature = "local")]
+pub struct Local<B> {
+    facilitator: std::sync::Arc<xusdc_facilitator::Facilitator<B>>,
+}
+
+#[cfg(feature = "local")]
+impl<B> Local<B> {
+    pub fn new(facilitator: std::sync::Arc<xusdc_facilitator::Facilitator<B>>) -> Self {
+        Self { facilitator }
+    }
+}
+
+#[cfg(feature = "local")]
+impl<B: xusdc_facilitator::Backend + 'static> Local<B> {
+    /// Run `f` off the async runtime, since backends block
+    async fn run<T: Send + 'static>(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+        f: impl FnOnce(&xusdc_facilitator::Facilitator<B>, &PaymentPayload, &PaymentRequirements) -> T
+            + Send
+            + 'static,
+    ) -> Result<T, FacilitatorError> {
+        let facilitator = self.facilitator.clone();
+        let (payment, requirements) = (payment.clone(), requirements.clone());
+        tokio::task::spawn_blocking(move || f(&facilitator, &payment, &requirements))
+            .await
+            .map_err(|e| FacilitatorError::Unavailable(e.to_string()))
+    }
+}
+
+#[cfg(feature = "local")]
+impl<B: xusdc_facilitator::Backend + 'static> Facilitate for Local<B> {
+    async fn verify(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<VerifyResponse, FacilitatorError> {
+        let result = self
+            .run(
+                payment,
+                requirements,
+                |facilitator, payment, requirements| facilitator.verify(payment, requirements),
+            )
+            .await?;
+        match result {
+            Ok(payload) => Ok(VerifyResponse {
+                is_valid: true,
+                invalid_reason: None,
+                payer: Some(payload.payment_auth.from.to_string()),
+            }),
+            Err(xusdc_facilitator::Rejection::Backend(e)) => {
+                Err(FacilitatorError::Unavailable(e.to_string()))
+            }
+            Err(rejection) => Ok(VerifyResponse {
+                is_valid: false,
+                invalid_reason: Some(rejection.reason().to_string()),
+                payer: None,
+            }),
+        }
+    }
+
+    async fn settle(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<SettleResponse, FacilitatorError> {
+        let network = self.facilitator.network();
+        let result = self
+            .run(
+                payment,
+                requirements,
+                |facilitator, payment, requirements| facilitator.settle(payment, requirements),
+            )
+            .await?;
+        Ok(match result {
+            Ok((payload, signature)) => SettleResponse {
+                success: true,
+                error_reason: None,
+                transaction: Some(signature.to_string()),
+                network,
+                payer: Some(payload.payment_auth.from.to_string()),
+            },
+            Err(rejection) => SettleResponse {
+                success: false,
+                error_reason: Some(rejection.reason().to_string()),
+                transaction: None,
+                network,
+                payer: None,
+            },
+        })
+    }
+}
+
+/// A facilitator reached over HTTP, e.g. `xusdc-facilitator`
+#[cfg(feature = "remote")]
+pub struct Remote {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[cfg(feature = "remote")]
+impl Remote {
+    /// `url` is the facilitator's base URL, without `/verify` or `/settle`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), url)
+    }
+
+    pub fn with_client(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<T, FacilitatorError> {
+        let request = xusdc_x402::FacilitatorRequest {
+            x402_version: xusdc_x402::X402_VERSION,
+            payment_payload: Some(payment.clone()),
+            payment_header: None,
+            payment_requirements: requirements.clone(),
+        };
+        let response = self
+            .client
+            .post(format!("{}{path}", self.url))
+            .json(&request)
+            .send()
+            .await?;
+        if response.status().is_server_error() {
+            return Err(FacilitatorError::Unavailable(format!(
+                "{path} answered {}",
+                response.status()
+            )));
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Facilitate for Remote {
+    async fn verify(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<VerifyResponse, FacilitatorError> {
+        self.post("/verify", payment, requirements).await
+    }
+
+    async fn settle(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<SettleResponse, FacilitatorError> {
+        self.post("/settle", payment, requirements).await
+    }
+}
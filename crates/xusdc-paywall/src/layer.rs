@@ -0,0 +1,318 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use axum::body::Body;
+use axum::extract::OriginalUri;
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use solana_program::pubkey::Pubkey;
+use tower::{Layer, Service};
+use xusdc_x402::{
+    Network, PaymentPayload, PaymentRequiredResponse, PaymentRequirements, X_PAYMENT,
+    X_PAYMENT_RESPONSE,
+};
+
+use crate::facilitator::Facilitate;
+
+/// Largest request body a paid request may have. The body is buffered to check the
+/// payment was signed for it.
+pub const MAX_PAID_BODY_BYTES: usize = 1 << 20;
+
+/// What a request costs, in xUSDC base units. `None` lets it through for free.
+pub trait Pricing: Send + Sync + 'static {
+    fn price(&self, request: &Parts) -> Option<u64>;
+}
+
+impl Pricing for u64 {
+    fn price(&self, _: &Parts) -> Option<u64> {
+        Some(*self)
+    }
+}
+
+impl<F> Pricing for F
+where
+    F: Fn(&Parts) -> Option<u64> + Send + Sync + 'static,
+{
+    fn price(&self, request: &Parts) -> Option<u64> {
+        self(request)
+    }
+}
+
+/// Charges for routes in xUSDC, paid to one account through one facilitator.
+/// [`Paywall::price`] makes the layer for each route or router.
+pub struct Paywall<F> {
+    facilitator: Arc<F>,
+    network: Network,
+    pay_to: Pubkey,
+    /// Prefixed to each request's path to name the resource paid for
+    public_url: String,
+    /// Nonces of the payments whose requests are being served, shared by every layer
+    in_flight: Arc<Mutex<HashSet<[u8; 32]>>>,
+    description: String,
+    mime_type: String,
+    max_timeout_seconds: u64,
+}
+
+impl<F> Clone for Paywall<F> {
+    fn clone(&self) -> Self {
+        Self {
+            facilitator: self.facilitator.clone(),
+            network: self.network,
+            pay_to: self.pay_to,
+            public_url: self.public_url.clone(),
+            in_flight: self.in_flight.clone(),
+            description: self.description.clone(),
+            mime_type: self.mime_type.clone(),
+            max_timeout_seconds: self.max_timeout_seconds,
+        }
+    }
+}
+
+impl<F: Facilitate> Paywall<F> {
+    /// Payments go to `pay_to`'s xUSDC account. `public_url` is where clients reach the
+    /// server, e.g. `https://api.example.com`: each resource is named by it and the
+    /// request's full path, so payments for one deployment or route never match
+    /// another.
+    pub fn new(
+        facilitator: F,
+        network: Network,
+        pay_to: Pubkey,
+        public_url: impl Into<String>,
+    ) -> Self {
+        let public_url: String = public_url.into();
+        Self {
+            facilitator: Arc::new(facilitator),
+            network,
+            pay_to,
+            public_url: public_url.trim_end_matches('/').to_string(),
+            in_flight: Arc::default(),
+            description: String::new(),
+            mime_type: String::new(),
+            max_timeout_seconds: xusdc_x402::requirements::DEFAULT_MAX_TIMEOUT_SECONDS,
+        }
+    }
+
+    /// Shown to payers in the payment requirements
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = mime_type.into();
+        self
+    }
+
+    /// The longest an authorization may stay valid
+    pub fn max_timeout_seconds(mut self, seconds: u64) -> Self {
+        self.max_timeout_seconds = seconds;
+        self
+    }
+
+    /// A layer charging what `pricing` says: a fixed amount, or a closure of the
+    /// request
+    pub fn price<P: Pricing>(&self, pricing: P) -> PaywallLayer<F, P> {
+        PaywallLayer {
+            paywall: self.clone(),
+            pricing: Arc::new(pricing),
+        }
+    }
+
+    fn requirements(&self, request: &Parts, amount: u64) -> PaymentRequirements {
+        // A nested router only sees the rest of the path
+        let uri = request
+            .extensions
+            .get::<OriginalUri>()
+            .map_or(&request.uri, |original| &original.0);
+        let path = uri.path_and_query().map_or("/", |path| path.as_str());
+        PaymentRequirements {
+            resource: format!("{}{path}", self.public_url),
+            description: self.description.clone(),
+            mime_type: self.mime_type.clone(),
+            max_timeout_seconds: self.max_timeout_seconds,
+            ..PaymentRequirements::new(self.network, self.pay_to, amount)
+        }
+    }
+}
+
+pub struct PaywallLayer<F, P> {
+    paywall: Paywall<F>,
+    pricing: Arc<P>,
+}
+
+impl<F, P> Clone for PaywallLayer<F, P> {
+    fn clone(&self) -> Self {
+        Self {
+            paywall: self.paywall.clone(),
+            pricing: self.pricing.clone(),
+        }
+    }
+}
+
+impl<S, F, P> Layer<S> for PaywallLayer<F, P> {
+    type Service = PaywallService<S, F, P>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PaywallService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+pub struct PaywallService<S, F, P> {
+    inner: S,
+    layer: PaywallLayer<F, P>,
+}
+
+impl<S: Clone, F, P> Clone for PaywallService<S, F, P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+/// Holds a payment's nonce in the paywall's in-flight set until dropped, so the same
+/// payment can't pay for two requests served at once
+struct Reservation {
+    in_flight: Arc<Mutex<HashSet<[u8; 32]>>>,
+    nonce: [u8; 32],
+}
+
+impl Reservation {
+    /// `None` if a request paid with `nonce` is already being served
+    fn new(in_flight: &Arc<Mutex<HashSet<[u8; 32]>>>, nonce: [u8; 32]) -> Option<Self> {
+        in_flight.lock().unwrap().insert(nonce).then(|| Self {
+            in_flight: in_flight.clone(),
+            nonce,
+        })
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.nonce);
+    }
+}
+
+fn payment_required(requirements: PaymentRequirements, error: impl Into<String>) -> Response {
+    (
+        StatusCode::PAYMENT_REQUIRED,
+        Json(PaymentRequiredResponse::new(
+            vec![requirements],
+            Some(error.into()),
+        )),
+    )
+        .into_response()
+}
+
+impl<S, F, P> Service<Request<Body>> for PaywallService<S, F, P>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send,
+    F: Facilitate,
+    P: Pricing,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // The clone may not be ready; keep the one that is
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let Some(amount) = layer.pricing.price(&parts) else {
+                return inner.call(Request::from_parts(parts, body)).await;
+            };
+            let paywall = &layer.paywall;
+            let requirements = paywall.requirements(&parts, amount);
+
+            let Some(header) = parts.headers.get(X_PAYMENT) else {
+                return Ok(payment_required(
+                    requirements,
+                    format!("{X_PAYMENT} header is required"),
+                ));
+            };
+            let payment = match header
+                .to_str()
+                .map_err(|e| e.to_string())
+                .and_then(|header| PaymentPayload::from_header(header).map_err(|e| e.to_string()))
+            {
+                Ok(payment) => payment,
+                Err(e) => {
+                    return Ok(payment_required(
+                        requirements,
+                        format!("invalid {X_PAYMENT} header: {e}"),
+                    ))
+                }
+            };
+            // Whoever the facilitator is, the payment must be to us and for the price
+            let payload = match requirements.check(&payment) {
+                Ok(payload) => payload,
+                Err(mismatch) => return Ok(payment_required(requirements, mismatch.reason())),
+            };
+            // ...and for this very request
+            let body = match axum::body::to_bytes(body, MAX_PAID_BODY_BYTES).await {
+                Ok(body) => body,
+                Err(e) => return Ok((StatusCode::PAYLOAD_TOO_LARGE, e.to_string()).into_response()),
+            };
+            if let Err(mismatch) =
+                requirements.check_resource(&payload, parts.method.as_str(), &body)
+            {
+                return Ok(payment_required(requirements, mismatch.reason()));
+            }
+            let body = Body::from(body);
+
+            // Verification passes until the payment settles, so a payment sent with
+            // several requests at once would otherwise be served for each. Once it has
+            // settled, verification refuses the spent nonce.
+            let Some(_reservation) =
+                Reservation::new(&paywall.in_flight, payload.payment_auth.nonce)
+            else {
+                return Ok(payment_required(requirements, "nonce_already_used"));
+            };
+            match paywall.facilitator.verify(&payment, &requirements).await {
+                Ok(verified) if verified.is_valid => {}
+                Ok(verified) => {
+                    let reason = verified.invalid_reason.unwrap_or_default();
+                    return Ok(payment_required(requirements, reason));
+                }
+                Err(e) => return Ok((StatusCode::BAD_GATEWAY, e.to_string()).into_response()),
+            }
+
+            let mut response = inner.call(Request::from_parts(parts, body)).await?;
+            // Only charge for what was served
+            if !response.status().is_success() {
+                return Ok(response);
+            }
+            match paywall.facilitator.settle(&payment, &requirements).await {
+                Ok(settled) if settled.success => {
+                    let header = HeaderValue::from_str(&settled.to_header())
+                        .expect("base64 is a valid header value");
+                    response.headers_mut().insert(X_PAYMENT_RESPONSE, header);
+                    Ok(response)
+                }
+                Ok(settled) => Ok(payment_required(
+                    requirements,
+                    settled.error_reason.unwrap_or_default(),
+                )),
+                Err(e) => Ok((StatusCode::BAD_GATEWAY, e.to_string()).into_response()),
+            }
+        })
+    }
+}
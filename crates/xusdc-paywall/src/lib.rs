@@ -0,0 +1,32 @@
+//! Charge for HTTP routes in xUSDC over x402.
+//!
+//! A [`Paywall`] knows where payments go and which facilitator settles them; each
+//! route or router gets a layer with its own price:
+//!
+//! ```ignore
+//! let facilitator = Remote::new("http://localhost:8402");
+//! let paywall = Paywall::new(facilitator, Network::Solana, merchant, "https://api.example.com");
+//! let app = Router::new()
+//!     .route("/weather", get(weather).layer(paywall.price(10_000)))
+//!     .route("/forecast/{days}", get(forecast).layer(paywall.price(|request: &Parts| {
+//!         Some(10_000 * days(request.uri.path())?)
+//!     })));
+//! ```
+//!
+//! Requests without a valid `X-PAYMENT` get a 402 listing the requirements, whose
+//! `resource` is the public URL followed by the request's path and query. A payment
+//! must carry the `resource_hash` of the request it comes with, so it can't be replayed
+//! against another route or body, and it pays for one request at a time. Paid requests
+//! are verified, served, then settled;
+//! the response carries the settlement in `X-PAYMENT-RESPONSE`. Responses that are not
+//! a success are not charged for.
+
+pub mod facilitator;
+pub mod layer;
+
+#[cfg(feature = "local")]
+pub use facilitator::Local;
+#[cfg(feature = "remote")]
+pub use facilitator::Remote;
+pub use facilitator::{Facilitate, FacilitatorError};
+pub use layer::{Paywall, PaywallLayer, PaywallService, Pricing, MAX_PAID_BODY_BYTES};
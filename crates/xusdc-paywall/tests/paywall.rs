@@ -0,0 +1,358 @@
+//! The paywall layer against a scripted facilitator
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use axum::body::Body;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use http_body_util::BodyExt;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use tower::ServiceExt;
+use xusdc_client::{resource_hash, PaymentAuthorization};
+use xusdc_paywall::{Facilitate, FacilitatorError, Paywall};
+use xusdc_x402::{
+    Network, PaymentPayload, PaymentRequiredResponse, PaymentRequirements, SettleResponse,
+    VerifyResponse, X_PAYMENT, X_PAYMENT_RESPONSE,
+};
+
+const PRICE: u64 = 10_000;
+const PUBLIC_URL: &str = "https://api.example.com";
+
+/// Accepts everything unless told otherwise, remembering what it was asked
+#[derive(Clone, Default)]
+struct Scripted {
+    invalid_reason: Option<&'static str>,
+    calls: Arc<Mutex<Vec<(&'static str, u64)>>>,
+}
+
+impl Facilitate for Scripted {
+    async fn verify(
+        &self,
+        _: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<VerifyResponse, FacilitatorError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(("verify", requirements.max_amount_required));
+        Ok(VerifyResponse {
+            is_valid: self.invalid_reason.is_none(),
+            invalid_reason: self.invalid_reason.map(str::to_string),
+            payer: None,
+        })
+    }
+
+    async fn settle(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<SettleResponse, FacilitatorError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(("settle", requirements.max_amount_required));
+        Ok(SettleResponse {
+            success: true,
+            error_reason: None,
+            transaction: Some("5ettLement".to_string()),
+            network: payment.network,
+            payer: Some(
+                payment
+                    .settle_payload()
+                    .unwrap()
+                    .payment_auth
+                    .from
+                    .to_string(),
+            ),
+        })
+    }
+}
+
+struct App {
+    router: Router,
+    facilitator: Scripted,
+    merchant: Pubkey,
+}
+
+impl App {
+    fn new(facilitator: Scripted) -> Self {
+        let merchant = Pubkey::new_unique();
+        let paywall = Paywall::new(
+            facilitator.clone(),
+            Network::SolanaDevnet,
+            merchant,
+            format!("{PUBLIC_URL}/"),
+        )
+        .description("Today's weather");
+        // A day's forecast costs the same as the weather; the first one is free
+        let per_day = |request: &Parts| {
+            let days: u64 = request.uri.path().rsplit('/').next()?.parse().ok()?;
+            (days > 1).then(|| PRICE * days)
+        };
+        // Sends the request it serves again, payment and all, while still serving it
+        let app = Arc::new(OnceLock::<Router>::new());
+        let replay = {
+            let app = app.clone();
+            move |headers: HeaderMap| async move {
+                let mut request = Request::builder().uri("/replay");
+                request = request.header(X_PAYMENT, headers[X_PAYMENT].clone());
+                let router = app.get().unwrap().clone();
+                let response = router
+                    .oneshot(request.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                body(response).await
+            }
+        };
+        let router = Router::new()
+            .route(
+                "/weather",
+                get(|| async { "sunny" }).layer(paywall.price(PRICE)),
+            )
+            .nest(
+                "/v1",
+                Router::new().route(
+                    "/weather",
+                    get(|| async { "sunny" }).layer(paywall.price(PRICE)),
+                ),
+            )
+            .route("/replay", get(replay).layer(paywall.price(PRICE)))
+            .route(
+                "/forecast/{days}",
+                get(|| async { "cloudy" }).layer(paywall.price(per_day)),
+            )
+            .route(
+                "/broken",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }).layer(paywall.price(PRICE)),
+            );
+        app.set(router.clone()).unwrap();
+        Self {
+            router,
+            facilitator,
+            merchant,
+        }
+    }
+
+    async fn get(&self, path: &str, payment: Option<&PaymentPayload>) -> Response {
+        let mut request = Request::builder().uri(path);
+        if let Some(payment) = payment {
+            request = request.header(X_PAYMENT, payment.to_header());
+        }
+        self.router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Pay for a GET of `path`
+    fn pay(&self, path: &str, to: Pubkey, amount: u64) -> PaymentPayload {
+        let payload = PaymentAuthorization::builder(to, amount)
+            .resource_hash(resource_hash("GET", &format!("{PUBLIC_URL}{path}"), b""))
+            .now(1_700_000_000)
+            .sign(&Keypair::new())
+            .unwrap();
+        PaymentPayload::new(Network::SolanaDevnet, &payload)
+    }
+
+    fn calls(&self) -> Vec<(&'static str, u64)> {
+        self.facilitator.calls.lock().unwrap().clone()
+    }
+}
+
+async fn body(response: Response) -> Vec<u8> {
+    response
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes()
+        .to_vec()
+}
+
+async fn payment_required(response: Response) -> PaymentRequiredResponse {
+    assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    assert!(response.headers().get(X_PAYMENT_RESPONSE).is_none());
+    serde_json::from_slice(&body(response).await).unwrap()
+}
+
+#[tokio::test]
+async fn test_unpaid_request_gets_requirements() {
+    let app = App::new(Scripted::default());
+
+    let required = payment_required(app.get("/weather?city=lisbon", None).await).await;
+    assert_eq!(
+        required.error.as_deref(),
+        Some("X-PAYMENT header is required")
+    );
+    let requirements = required.xusdc(Network::SolanaDevnet).unwrap();
+    assert_eq!(requirements.pay_to, app.merchant);
+    assert_eq!(requirements.max_amount_required, PRICE);
+    assert_eq!(
+        requirements.resource,
+        "https://api.example.com/weather?city=lisbon"
+    );
+    assert_eq!(requirements.description, "Today's weather");
+    assert!(app.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_paid_request_is_served_and_settled() {
+    let app = App::new(Scripted::default());
+    let payment = app.pay("/weather", app.merchant, PRICE);
+
+    let response = app.get("/weather", Some(&payment)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settled = response.headers()[X_PAYMENT_RESPONSE].to_str().unwrap();
+    let settled = SettleResponse::from_header(settled).unwrap();
+    assert!(settled.success);
+    assert_eq!(settled.transaction.as_deref(), Some("5ettLement"));
+    assert_eq!(body(response).await, b"sunny");
+    assert_eq!(app.calls(), [("verify", PRICE), ("settle", PRICE)]);
+}
+
+#[tokio::test]
+async fn test_payment_must_match_the_route() {
+    let app = App::new(Scripted::default());
+
+    let short = app.pay("/weather", app.merchant, PRICE - 1);
+    let required = payment_required(app.get("/weather", Some(&short)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_amount"));
+
+    let elsewhere = app.pay("/weather", Pubkey::new_unique(), PRICE);
+    let required = payment_required(app.get("/weather", Some(&elsewhere)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_pay_to"));
+
+    let mainnet = PaymentPayload {
+        network: Network::Solana,
+        ..app.pay("/weather", app.merchant, PRICE)
+    };
+    let required = payment_required(app.get("/weather", Some(&mainnet)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_network"));
+
+    let response = app
+        .router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/weather")
+                .header(X_PAYMENT, "not base64!")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let required = payment_required(response).await;
+    assert!(required
+        .error
+        .unwrap()
+        .starts_with("invalid X-PAYMENT header"));
+
+    // None of it reached the facilitator
+    assert!(app.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_payment_is_bound_to_its_request() {
+    let app = App::new(Scripted::default());
+
+    let weather = app.pay("/weather", app.merchant, PRICE);
+    let required = payment_required(app.get("/weather?city=porto", Some(&weather)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_resource"));
+
+    let unbound = PaymentAuthorization::builder(app.merchant, PRICE)
+        .now(1_700_000_000)
+        .sign(&Keypair::new())
+        .unwrap();
+    let unbound = PaymentPayload::new(Network::SolanaDevnet, &unbound);
+    let required = payment_required(app.get("/weather", Some(&unbound)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_resource"));
+    assert!(app.calls().is_empty());
+
+    let response = app.get("/weather", Some(&weather)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rejected_payment_is_not_served() {
+    let app = App::new(Scripted {
+        invalid_reason: Some("insufficient_funds"),
+        ..Default::default()
+    });
+    let payment = app.pay("/weather", app.merchant, PRICE);
+
+    let required = payment_required(app.get("/weather", Some(&payment)).await).await;
+    assert_eq!(required.error.as_deref(), Some("insufficient_funds"));
+    assert_eq!(app.calls(), [("verify", PRICE)]);
+}
+
+#[tokio::test]
+async fn test_failed_response_is_not_charged() {
+    let app = App::new(Scripted::default());
+    let payment = app.pay("/broken", app.merchant, PRICE);
+
+    let response = app.get("/broken", Some(&payment)).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.headers().get(X_PAYMENT_RESPONSE).is_none());
+    assert_eq!(app.calls(), [("verify", PRICE)]);
+}
+
+#[tokio::test]
+async fn test_dynamic_pricing() {
+    let app = App::new(Scripted::default());
+
+    // Free: no payment asked for, nothing settled
+    let response = app.get("/forecast/1", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(X_PAYMENT_RESPONSE).is_none());
+
+    let required = payment_required(app.get("/forecast/3", None).await).await;
+    assert_eq!(required.accepts[0].max_amount_required, 3 * PRICE);
+
+    // Paying for one day doesn't buy three
+    let one_day = app.pay("/forecast/3", app.merchant, PRICE);
+    let required = payment_required(app.get("/forecast/3", Some(&one_day)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_amount"));
+
+    let three_days = app.pay("/forecast/3", app.merchant, 3 * PRICE);
+    let response = app.get("/forecast/3", Some(&three_days)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key(X_PAYMENT_RESPONSE));
+    assert_eq!(app.calls(), [("verify", 3 * PRICE), ("settle", 3 * PRICE)]);
+}
+
+#[tokio::test]
+async fn test_nested_routes_are_named_by_their_full_path() {
+    let app = App::new(Scripted::default());
+
+    let required = payment_required(app.get("/v1/weather", None).await).await;
+    assert_eq!(
+        required.accepts[0].resource,
+        "https://api.example.com/v1/weather"
+    );
+    // The nested route's own path names another resource
+    let unnested = app.pay("/weather", app.merchant, PRICE);
+    let required = payment_required(app.get("/v1/weather", Some(&unnested)).await).await;
+    assert_eq!(required.error.as_deref(), Some("invalid_resource"));
+
+    let payment = app.pay("/v1/weather", app.merchant, PRICE);
+    let response = app.get("/v1/weather", Some(&payment)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_payment_pays_for_one_request_at_a_time() {
+    let app = App::new(Scripted::default());
+    let payment = app.pay("/replay", app.merchant, PRICE);
+
+    // The replay arrives while the first request is still being served
+    let response = app.get("/replay", Some(&payment)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let replayed: PaymentRequiredResponse = serde_json::from_slice(&body(response).await).unwrap();
+    assert_eq!(replayed.error.as_deref(), Some("nonce_already_used"));
+    assert_eq!(app.calls(), [("verify", PRICE), ("settle", PRICE)]);
+}
@@ -0,0 +1,93 @@
+//! The paywall settling real payments against an in-process litesvm bank, both
+//! through an in-process facilitator and one reached over HTTP.
+//!
+//! The bank comes from `xusdc-harness`, which doesn't verify transaction signatures;
+//! payment authorizations are still checked.
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use http_body_util::BodyExt;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use tower::ServiceExt;
+use xusdc_client::{resource_hash, PaymentAuthorization};
+use xusdc_facilitator::{router, Backend, Facilitator, LiteSvmBackend};
+use xusdc_harness::{xusdc_balance, Env};
+use xusdc_paywall::{Facilitate, Local, Paywall, Remote};
+use xusdc_x402::{Network, PaymentPayload, SettleResponse, X_PAYMENT, X_PAYMENT_RESPONSE};
+
+const TEN_USDC: u64 = 10_000_000;
+const PRICE: u64 = 250_000;
+const PUBLIC_URL: &str = "https://api.example.com";
+
+/// Buy `/weather` from behind a paywall settling through `facilitator`
+async fn buy<F: Facilitate>(
+    facilitator: F,
+    merchant: Pubkey,
+    payer: &Keypair,
+    now: i64,
+) -> SettleResponse {
+    let paywall = Paywall::new(facilitator, Network::Solana, merchant, PUBLIC_URL);
+    let app = Router::new().route(
+        "/weather",
+        get(|| async { "sunny" }).layer(paywall.price(PRICE)),
+    );
+
+    let payload = PaymentAuthorization::builder(merchant, PRICE)
+        .resource_hash(resource_hash("GET", &format!("{PUBLIC_URL}/weather"), b""))
+        .now(now)
+        .sign(payer)
+        .unwrap();
+    let request = Request::builder()
+        .uri("/weather")
+        .header(
+            X_PAYMENT,
+            PaymentPayload::new(Network::Solana, &payload).to_header(),
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let settled = response.headers()[X_PAYMENT_RESPONSE].to_str().unwrap();
+    let settled = SettleResponse::from_header(settled).unwrap();
+    assert_eq!(
+        response.into_body().collect().await.unwrap().to_bytes(),
+        "sunny"
+    );
+    settled
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_paywall_settles_locally_and_remotely() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let merchant = env.user().pubkey();
+    let now = env.now();
+    let facilitator = Arc::new(Facilitator::new(
+        LiteSvmBackend::new(env.svm),
+        env.facilitator,
+        Network::Solana,
+    ));
+
+    let settled = buy(Local::new(facilitator.clone()), merchant, &alice, now).await;
+    assert!(settled.success, "{settled:?}");
+    assert_eq!(settled.payer, Some(alice.pubkey().to_string()));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let app = router(facilitator.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let settled = buy(Remote::new(url), merchant, &alice, now).await;
+    assert!(settled.success, "{settled:?}");
+    assert!(settled.transaction.is_some());
+
+    facilitator.backend().with_svm(|svm| {
+        assert_eq!(xusdc_balance(svm, &alice.pubkey()), TEN_USDC - 2 * PRICE);
+        assert_eq!(xusdc_balance(svm, &merchant), 2 * PRICE);
+    });
+    assert!(facilitator.backend().clock().is_ok());
+}
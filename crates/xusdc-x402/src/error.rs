@@ -0,0 +1,47 @@
+use solana_program::pubkey::Pubkey;
+
+use crate::network::Network;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid settle payload: {0}")]
+    Borsh(#[from] std::io::Error),
+}
+
+/// Why a payment does not answer a set of requirements
+#[derive(Debug, thiserror::Error)]
+pub enum Mismatch {
+    #[error("unsupported scheme {0:?}")]
+    UnsupportedScheme(String),
+    #[error("the payment is on {actual}, not {expected}")]
+    NetworkMismatch { expected: Network, actual: Network },
+    #[error("the requirements ask for {0}, not xUSDC")]
+    AssetMismatch(Pubkey),
+    #[error(transparent)]
+    InvalidPayload(#[from] DecodeError),
+    #[error("the payment is to {actual}, not {expected}")]
+    PayToMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("the payment is for {actual}, not {expected}")]
+    AmountMismatch { expected: u64, actual: u64 },
+    #[error("the payment was signed for another request")]
+    ResourceMismatch,
+}
+
+impl Mismatch {
+    /// The x402 `invalidReason`
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::UnsupportedScheme(_) => "unsupported_scheme",
+            Self::NetworkMismatch { .. } => "invalid_network",
+            Self::AssetMismatch(_) => "invalid_asset",
+            Self::InvalidPayload(_) => "invalid_payload",
+            Self::PayToMismatch { .. } => "invalid_pay_to",
+            Self::AmountMismatch { .. } => "invalid_amount",
+            Self::ResourceMismatch => "invalid_resource",
+        }
+    }
+}
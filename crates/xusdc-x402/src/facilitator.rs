@@ -0,0 +1,83 @@
+//! What resource servers and facilitators exchange on `/verify`, `/settle` and
+//! `/supported`
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::DecodeError;
+use crate::network::Network;
+use crate::payload::PaymentPayload;
+use crate::requirements::PaymentRequirements;
+use crate::{decode_header, encode_header};
+
+/// The body of `/verify` and `/settle`. The payment is given either decoded, as
+/// `paymentPayload`, or as the raw `X-PAYMENT` header value, as `paymentHeader`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FacilitatorRequest {
+    pub x402_version: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_payload: Option<PaymentPayload>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_header: Option<String>,
+    pub payment_requirements: PaymentRequirements,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyResponse {
+    pub is_valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SettleResponse {
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
+    /// The settlement transaction signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<String>,
+    pub network: Network,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportedKind {
+    pub x402_version: u8,
+    pub scheme: String,
+    pub network: Network,
+}
+
+/// The body of `/supported`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Supported {
+    pub kinds: Vec<SupportedKind>,
+}
+
+impl FacilitatorRequest {
+    /// The payment, whichever way it was given
+    pub fn payment(&self) -> Option<Result<PaymentPayload, DecodeError>> {
+        match (&self.payment_payload, &self.payment_header) {
+            (Some(payload), _) => Some(Ok(payload.clone())),
+            (None, Some(header)) => Some(PaymentPayload::from_header(header)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl SettleResponse {
+    /// Decode an `X-PAYMENT-RESPONSE` header
+    pub fn from_header(header: &str) -> Result<Self, DecodeError> {
+        decode_header(header)
+    }
+
+    pub fn to_header(&self) -> String {
+        encode_header(self)
+    }
+}
@@ -0,0 +1,44 @@
+//! The x402 messages for paying in xUSDC with the `exact` scheme: what a resource
+//! server asks for in a 402 response, what a client sends back in `X-PAYMENT`, and
+//! what facilitators answer on `/verify` and `/settle`.
+//!
+//! The scheme-specific payload is the Borsh `SettlePayload` that `settle_payment`
+//! takes, base64 encoded. Amounts are strings of xUSDC base units and keys are
+//! base58, as in every other x402 network.
+
+pub mod error;
+pub mod facilitator;
+pub mod network;
+pub mod payload;
+pub mod requirements;
+mod serde_str;
+
+pub use error::{DecodeError, Mismatch};
+pub use facilitator::{
+    FacilitatorRequest, SettleResponse, Supported, SupportedKind, VerifyResponse,
+};
+pub use network::Network;
+pub use payload::{ExactPayload, PaymentPayload};
+pub use requirements::{PaymentRequiredResponse, PaymentRequirements};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub const X402_VERSION: u8 = 1;
+/// The payer authorizes exactly `maxAmountRequired`
+pub const SCHEME: &str = "exact";
+
+/// The request header carrying a [`PaymentPayload`]
+pub const X_PAYMENT: &str = "X-PAYMENT";
+/// The response header carrying a [`SettleResponse`]
+pub const X_PAYMENT_RESPONSE: &str = "X-PAYMENT-RESPONSE";
+
+/// Encode a message for an x402 header: base64 of its JSON
+pub fn encode_header<T: Serialize>(message: &T) -> String {
+    STANDARD.encode(serde_json::to_vec(message).expect("x402 messages serialize"))
+}
+
+pub fn decode_header<T: DeserializeOwned>(header: &str) -> Result<T, DecodeError> {
+    Ok(serde_json::from_slice(&STANDARD.decode(header.trim())?)?)
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    #[serde(rename = "solana")]
+    Solana,
+    #[serde(rename = "solana-devnet")]
+    SolanaDevnet,
+}
+
+impl Network {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Solana => "solana",
+            Self::SolanaDevnet => "solana-devnet",
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "solana" => Ok(Self::Solana),
+            "solana-devnet" => Ok(Self::SolanaDevnet),
+            _ => Err(format!("unknown network {s:?}")),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use xusdc_client::SettlePayload;
+
+use crate::error::DecodeError;
+use crate::network::Network;
+use crate::{decode_header, encode_header, SCHEME, X402_VERSION};
+
+/// The decoded `X-PAYMENT` header
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentPayload {
+    pub x402_version: u8,
+    pub scheme: String,
+    pub network: Network,
+    pub payload: ExactPayload,
+}
+
+/// The `exact` scheme's payload on xUSDC
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExactPayload {
+    /// Base64 of the Borsh `SettlePayload`
+    pub settle_payload: String,
+}
+
+impl PaymentPayload {
+    pub fn new(network: Network, settle_payload: &SettlePayload) -> Self {
+        Self {
+            x402_version: X402_VERSION,
+            scheme: SCHEME.to_string(),
+            network,
+            payload: ExactPayload {
+                settle_payload: STANDARD.encode(borsh::to_vec(settle_payload).unwrap()),
+            },
+        }
+    }
+
+    pub fn from_header(header: &str) -> Result<Self, DecodeError> {
+        decode_header(header)
+    }
+
+    pub fn to_header(&self) -> String {
+        encode_header(self)
+    }
+
+    pub fn settle_payload(&self) -> Result<SettlePayload, DecodeError> {
+        let bytes = STANDARD.decode(&self.payload.settle_payload)?;
+        Ok(borsh::from_slice(&bytes)?)
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use xusdc_client::{resource_hash, SettlePayload, XUSDC_MINT_KEY};
+
+use crate::error::Mismatch;
+use crate::network::Network;
+use crate::payload::PaymentPayload;
+use crate::{serde_str, SCHEME, X402_VERSION};
+
+/// How long an authorization may stay valid unless the resource server says otherwise
+pub const DEFAULT_MAX_TIMEOUT_SECONDS: u64 = 60;
+
+/// One way a resource server accepts payment
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequirements {
+    pub scheme: String,
+    pub network: Network,
+    /// In xUSDC base units. Under `exact` it is the amount, not a ceiling.
+    #[serde(with = "serde_str")]
+    pub max_amount_required: u64,
+    /// URL of the resource being paid for, which the authorization's `resource_hash`
+    /// commits to
+    #[serde(default)]
+    pub resource: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    /// The owner of the xUSDC account paid, not the token account
+    #[serde(with = "serde_str")]
+    pub pay_to: Pubkey,
+    /// The longest `valid_until` may be past the time of settlement
+    pub max_timeout_seconds: u64,
+    #[serde(with = "serde_str")]
+    pub asset: Pubkey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<serde_json::Value>,
+}
+
+impl PaymentRequirements {
+    /// Ask for exactly `amount` xUSDC base units, paid to `pay_to`
+    pub fn new(network: Network, pay_to: Pubkey, amount: u64) -> Self {
+        Self {
+            scheme: SCHEME.to_string(),
+            network,
+            max_amount_required: amount,
+            resource: String::new(),
+            description: String::new(),
+            mime_type: String::new(),
+            output_schema: None,
+            pay_to,
+            max_timeout_seconds: DEFAULT_MAX_TIMEOUT_SECONDS,
+            asset: XUSDC_MINT_KEY,
+            extra: None,
+        }
+    }
+
+    /// Check that `payment` answers these requirements, returning the authorization
+    /// it carries. Whether that authorization would settle is for a facilitator to say.
+    pub fn check(&self, payment: &PaymentPayload) -> Result<SettlePayload, Mismatch> {
+        for scheme in [&payment.scheme, &self.scheme] {
+            if scheme != SCHEME {
+                return Err(Mismatch::UnsupportedScheme(scheme.clone()));
+            }
+        }
+        if payment.network != self.network {
+            return Err(Mismatch::NetworkMismatch {
+                expected: self.network,
+                actual: payment.network,
+            });
+        }
+        if self.asset != XUSDC_MINT_KEY {
+            return Err(Mismatch::AssetMismatch(self.asset));
+        }
+
+        let payload = payment.settle_payload()?;
+        let payment_auth = &payload.payment_auth;
+        if payment_auth.to != self.pay_to {
+            return Err(Mismatch::PayToMismatch {
+                expected: self.pay_to,
+                actual: payment_auth.to,
+            });
+        }
+        if payment_auth.amount != self.max_amount_required {
+            return Err(Mismatch::AmountMismatch {
+                expected: self.max_amount_required,
+                actual: payment_auth.amount,
+            });
+        }
+        Ok(payload)
+    }
+
+    /// The `resource_hash` a payment for a `method` request to [`Self::resource`]
+    /// with this `body` must carry
+    pub fn resource_hash(&self, method: &str, body: &[u8]) -> [u8; 32] {
+        resource_hash(method, &self.resource, body)
+    }
+
+    /// Check that `payload` was signed for this request, as well as [`Self::check`]
+    /// does for the payment itself
+    pub fn check_resource(
+        &self,
+        payload: &SettlePayload,
+        method: &str,
+        body: &[u8],
+    ) -> Result<(), Mismatch> {
+        if payload.payment_auth.resource_hash != self.resource_hash(method, body) {
+            return Err(Mismatch::ResourceMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// The body of a 402 response
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequiredResponse {
+    pub x402_version: u8,
+    pub accepts: Vec<PaymentRequirements>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PaymentRequiredResponse {
+    pub fn new(accepts: Vec<PaymentRequirements>, error: Option<String>) -> Self {
+        Self {
+            x402_version: X402_VERSION,
+            accepts,
+            error,
+        }
+    }
+
+    /// The first option payable in xUSDC on `network`
+    pub fn xusdc(&self, network: Network) -> Option<&PaymentRequirements> {
+        self.accepts.iter().find(|requirements| {
+            requirements.scheme == SCHEME
+                && requirements.network == network
+                && requirements.asset == XUSDC_MINT_KEY
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use xusdc_client::PaymentAuthorization;
+
+    fn payment(to: Pubkey, amount: u64) -> PaymentPayload {
+        let payload = xusdc_client::SettlePayload {
+            payment_auth: PaymentAuthorization::builder(to, amount)
+                .now(0)
+                .build(Pubkey::new_unique())
+                .unwrap(),
+            signature: [3; 64],
+            signer_pubkey: [4; 32],
+        };
+        PaymentPayload::new(Network::Solana, &payload)
+    }
+
+    #[test]
+    fn test_requirements_round_trip() {
+        let requirements = PaymentRequirements {
+            output_schema: Some(json!({ "type": "object" })),
+            extra: Some(json!({ "feePayer": Pubkey::new_unique().to_string() })),
+            ..PaymentRequirements::new(Network::Solana, Pubkey::new_unique(), u64::MAX)
+        };
+        let json = serde_json::to_value(&requirements).unwrap();
+        assert_eq!(json["maxAmountRequired"], u64::MAX.to_string());
+        assert_eq!(json["payTo"], requirements.pay_to.to_string());
+        assert_eq!(json["asset"], XUSDC_MINT_KEY.to_string());
+        assert_eq!(
+            serde_json::from_value::<PaymentRequirements>(json).unwrap(),
+            requirements
+        );
+
+        // Optional fields may be left out
+        let mut json = serde_json::to_value(PaymentRequirements::new(
+            Network::SolanaDevnet,
+            Pubkey::new_unique(),
+            1,
+        ))
+        .unwrap();
+        for field in ["resource", "description", "mimeType"] {
+            json.as_object_mut().unwrap().remove(field);
+        }
+        assert!(serde_json::from_value::<PaymentRequirements>(json).is_ok());
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let payment = payment(Pubkey::new_unique(), 42);
+        let decoded = PaymentPayload::from_header(&payment.to_header()).unwrap();
+        assert_eq!(decoded, payment);
+        assert_eq!(decoded.settle_payload().unwrap().payment_auth.amount, 42);
+    }
+
+    #[test]
+    fn test_check_matches_payment_to_requirements() {
+        let requirements = PaymentRequirements::new(Network::Solana, Pubkey::new_unique(), 500);
+        let payload = requirements
+            .check(&payment(requirements.pay_to, 500))
+            .unwrap();
+        assert_eq!(payload.payment_auth.amount, 500);
+
+        let reason = |payment: &PaymentPayload, requirements: &PaymentRequirements| {
+            requirements.check(payment).unwrap_err().reason()
+        };
+        assert_eq!(
+            reason(&payment(Pubkey::new_unique(), 500), &requirements),
+            "invalid_pay_to"
+        );
+        // Exact means exact: paying more is refused too
+        assert_eq!(
+            reason(&payment(requirements.pay_to, 501), &requirements),
+            "invalid_amount"
+        );
+        let mut devnet = payment(requirements.pay_to, 500);
+        devnet.network = Network::SolanaDevnet;
+        assert_eq!(reason(&devnet, &requirements), "invalid_network");
+        let usdc = PaymentRequirements {
+            asset: xusdc_client::USDC_MINT_KEY,
+            ..requirements.clone()
+        };
+        assert_eq!(
+            reason(&payment(requirements.pay_to, 500), &usdc),
+            "invalid_asset"
+        );
+    }
+
+    #[test]
+    fn test_check_resource_matches_the_request() {
+        let requirements = PaymentRequirements {
+            resource: "https://example.com/weather".to_string(),
+            ..PaymentRequirements::new(Network::Solana, Pubkey::new_unique(), 500)
+        };
+        let mut payload = requirements
+            .check(&payment(requirements.pay_to, 500))
+            .unwrap();
+        payload.payment_auth.resource_hash = requirements.resource_hash("POST", b"{}");
+        assert!(requirements.check_resource(&payload, "POST", b"{}").is_ok());
+
+        for (method, body) in [("GET", &b"{}"[..]), ("POST", b"{ }")] {
+            let mismatch = requirements
+                .check_resource(&payload, method, body)
+                .unwrap_err();
+            assert_eq!(mismatch.reason(), "invalid_resource");
+        }
+        let elsewhere = PaymentRequirements {
+            resource: "https://example.com/forecast".to_string(),
+            ..requirements
+        };
+        assert!(elsewhere.check_resource(&payload, "POST", b"{}").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Conformance fixtures: the exact JSON bodies and headers that resource servers,
+//! clients and facilitators exchange for an xUSDC payment. Other implementations can
+//! test against `fixtures/` directly.
+//!
+//! Run with `XUSDC_X402_BLESS=1` to rewrite the fixtures after a deliberate change to
+//! the encoding.
+
+use std::path::PathBuf;
+
+use solana_keypair::keypair_from_seed;
+use solana_program::pubkey::Pubkey;
+use solana_signer::Signer;
+use xusdc_client::{resource_hash, PaymentAuthorization, SettlePayload};
+use xusdc_x402::{
+    FacilitatorRequest, Network, PaymentPayload, PaymentRequiredResponse, PaymentRequirements,
+    SettleResponse, VerifyResponse, X402_VERSION,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+/// Compare `actual` with the fixture, or rewrite the fixture when blessing
+fn check_fixture(name: &str, actual: &str) {
+    let path = fixture(name);
+    if std::env::var_os("XUSDC_X402_BLESS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("reading {}: {e}", path.display()));
+    assert_eq!(actual, expected, "{name} no longer matches its fixture");
+}
+
+fn check_json<T>(name: &str, value: &T)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    check_fixture(name, &(serde_json::to_string_pretty(value).unwrap() + "\n"));
+    let parsed: T = serde_json::from_str(&std::fs::read_to_string(fixture(name)).unwrap()).unwrap();
+    assert_eq!(&parsed, value);
+}
+
+fn merchant() -> Pubkey {
+    "MerchantxUSDCPayee1111111111111111111111111"
+        .parse()
+        .unwrap()
+}
+
+fn requirements() -> PaymentRequirements {
+    PaymentRequirements {
+        resource: "https://api.example.com/weather".to_string(),
+        description: "Current weather".to_string(),
+        mime_type: "application/json".to_string(),
+        ..PaymentRequirements::new(Network::SolanaDevnet, merchant(), 10_000)
+    }
+}
+
+/// A payment answering `requirements()`. Ed25519 signatures are deterministic, so
+/// this is the same every run.
+fn settle_payload() -> SettlePayload {
+    let payer = keypair_from_seed(&[7; 32]).unwrap();
+    let payment_auth = PaymentAuthorization::builder(merchant(), 10_000)
+        .resource_hash(requirements().resource_hash("GET", b""))
+        .nonce([1; 32])
+        .valid_until(1_700_000_060)
+        .build(payer.pubkey())
+        .unwrap();
+    SettlePayload::sign(payment_auth, &payer).unwrap()
+}
+
+#[test]
+fn test_payment_required_response() {
+    let response = PaymentRequiredResponse::new(
+        vec![requirements()],
+        Some("X-PAYMENT header is required".to_string()),
+    );
+    check_json("payment_required_response.json", &response);
+    assert_eq!(response.xusdc(Network::SolanaDevnet), Some(&requirements()));
+    assert_eq!(response.xusdc(Network::Solana), None);
+}
+
+#[test]
+fn test_x_payment_header() {
+    let payment = PaymentPayload::new(Network::SolanaDevnet, &settle_payload());
+    check_json("payment_payload.json", &payment);
+    check_fixture("x_payment.txt", &(payment.to_header() + "\n"));
+
+    let header = std::fs::read_to_string(fixture("x_payment.txt")).unwrap();
+    let decoded = PaymentPayload::from_header(&header).unwrap();
+    assert_eq!(decoded, payment);
+    let payload = requirements().check(&decoded).unwrap();
+    assert_eq!(payload, settle_payload());
+    requirements().check_resource(&payload, "GET", b"").unwrap();
+    // Signed exactly as settle_payment verifies it
+    brine_ed25519::sig_verify(
+        &payload.signer_pubkey,
+        &payload.signature,
+        &payload.payment_auth.message(),
+    )
+    .unwrap();
+}
+
+/// The canonical `resource_hash`, for other implementations to check theirs against
+#[test]
+fn test_resource_hash() {
+    let hex = |hash: [u8; 32]| hash.map(|byte| format!("{byte:02x}")).concat();
+    let hashes = [
+        ("GET", "https://api.example.com/weather", &b""[..]),
+        (
+            "post",
+            "https://api.example.com/orders?id=7",
+            br#"{"qty":2}"#,
+        ),
+    ]
+    .map(|(method, url, body)| {
+        format!(
+            "{method} {url} {:?} {}",
+            String::from_utf8_lossy(body),
+            hex(resource_hash(method, url, body))
+        )
+    });
+    check_fixture("resource_hash.txt", &(hashes.join("\n") + "\n"));
+}
+
+#[test]
+fn test_facilitator_messages() {
+    let request = FacilitatorRequest {
+        x402_version: X402_VERSION,
+        payment_payload: Some(PaymentPayload::new(
+            Network::SolanaDevnet,
+            &settle_payload(),
+        )),
+        payment_header: None,
+        payment_requirements: requirements(),
+    };
+    check_json("facilitator_request.json", &request);
+
+    let payer = settle_payload().payment_auth.from.to_string();
+    check_json(
+        "verify_response.json",
+        &VerifyResponse {
+            is_valid: true,
+            invalid_reason: None,
+            payer: Some(payer.clone()),
+        },
+    );
+    let settled = SettleResponse {
+        success: true,
+        error_reason: None,
+        transaction: Some(
+            "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW"
+                .to_string(),
+        ),
+        network: Network::SolanaDevnet,
+        payer: Some(payer),
+    };
+    check_json("settle_response.json", &settled);
+    check_fixture("x_payment_response.txt", &(settled.to_header() + "\n"));
+    let header = std::fs::read_to_string(fixture("x_payment_response.txt")).unwrap();
+    assert_eq!(SettleResponse::from_header(&header).unwrap(), settled);
+}
+
+#[test]
+fn test_invalid_fixtures_are_rejected() {
+    let invalid = fixture("invalid");
+    let mut checked = 0;
+    for entry in std::fs::read_dir(&invalid).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        let json = std::fs::read_to_string(&path).unwrap();
+        let rejected = if name.starts_with("requirements_") {
+            serde_json::from_str::<PaymentRequirements>(&json).is_err()
+        } else if name.starts_with("payload_") {
+            match serde_json::from_str::<PaymentPayload>(&json) {
+                Err(_) => true,
+                Ok(payment) => requirements().check(&payment).is_err(),
+            }
+        } else {
+            panic!("unexpected fixture {name}");
+        };
+        assert!(rejected, "{name} was accepted");
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
@@ -0,0 +1,34 @@
+//! `SettlePayload` decoding, run by facilitators on bytes from anyone. The program's
+//! Anchor decoder and the client's Borsh decoder must agree on every input and
+//! re-encode exactly what they consumed, and decoding an x402 header must not panic.
+
+#![no_main]
+
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use borsh::BorshDeserialize;
+use libfuzzer_sys::fuzz_target;
+use xusdc_x402::PaymentPayload;
+
+fuzz_target!(|data: &[u8]| {
+    let mut program_rest = data;
+    let program = <xusdc::ixs::SettlePayload as AnchorDeserialize>::deserialize(&mut program_rest);
+    let mut client_rest = data;
+    let client = <xusdc_client::SettlePayload as BorshDeserialize>::deserialize(&mut client_rest);
+
+    match (program, client) {
+        (Ok(program), Ok(client)) => {
+            assert_eq!(program_rest.len(), client_rest.len());
+            let consumed = &data[..data.len() - client_rest.len()];
+            assert_eq!(borsh::to_vec(&client).unwrap(), consumed);
+            assert_eq!(program.try_to_vec().unwrap(), consumed);
+        }
+        (Err(_), Err(_)) => {}
+        (program, client) => panic!("the program decoded {program:?}, the client {client:?}"),
+    }
+
+    if let Ok(header) = std::str::from_utf8(data) {
+        if let Ok(payment) = PaymentPayload::from_header(header) {
+            let _ = payment.settle_payload();
+        }
+    }
+});
@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The hook was invoked outside a transfer")]
+    NotTransferring,
+    #[msg("Wallet is blocked")]
+    WalletBlocked,
+    #[msg("Transfer would exceed the wallet's spend limit")]
+    SpendLimitExceeded,
+    #[msg("Invalid wallet policy")]
+    InvalidPolicy,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+}
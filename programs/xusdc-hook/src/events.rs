@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+// Like the xUSDC program, admin changes are emitted with `emit_cpi!`. `execute` emits
+// nothing, since Token-2022 only passes it the accounts its meta list names.
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyUpdated {
+    pub wallet: Pubkey,
+    pub spend_limit: u64,
+    pub window_seconds: i64,
+}
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions},
+    state::Account as TokenAccountState,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::state::{
+    WalletPolicy, BLOCKED_ACCOUNT_SEED, EXTRA_ACCOUNT_METAS_SEED, POLICY_SEED, XUSDC_MINT_KEY,
+};
+
+/// The accounts Token-2022 passes a transfer hook, followed by the ones our meta list adds
+#[derive(Accounts)]
+pub struct Execute<'info> {
+    #[account(token::mint = mint)]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = XUSDC_MINT_KEY)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: The source's owner or delegate, already checked by Token-2022
+    pub authority: UncheckedAccount<'info>,
+    /// CHECK: Our meta list, which Token-2022 resolved the accounts below from
+    #[account(seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    /// CHECK: The source owner's policy, empty if it has none. The only account a
+    /// transfer writes, so transfers from different wallets still run in parallel.
+    #[account(mut, seeds = [POLICY_SEED, source.owner.as_ref()], bump)]
+    pub source_policy: UncheckedAccount<'info>,
+    /// CHECK: The xUSDC program, which owns the blocklist
+    #[account(address = xusdc::ID)]
+    pub xusdc_program: UncheckedAccount<'info>,
+    /// CHECK: The source owner's xUSDC blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, source.owner.as_ref()],
+        bump,
+        seeds::program = xusdc_program,
+        constraint = source_blocked.data_is_empty() @ ErrorCode::WalletBlocked,
+    )]
+    pub source_blocked: UncheckedAccount<'info>,
+    /// CHECK: The destination owner's xUSDC blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, destination.owner.as_ref()],
+        bump,
+        seeds::program = xusdc_program,
+        constraint = destination_blocked.data_is_empty() @ ErrorCode::WalletBlocked,
+    )]
+    pub destination_blocked: UncheckedAccount<'info>,
+}
+
+/// The policy stored in `account`, if one was ever set
+fn read_policy(account: &AccountInfo) -> Result<Option<WalletPolicy>> {
+    if account.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(WalletPolicy::try_deserialize(
+        &mut &account.try_borrow_data()?[..],
+    )?))
+}
+
+pub fn handler(ctx: Context<Execute>, amount: u64) -> Result<()> {
+    // Only count transfers Token-2022 is making, not calls from anyone else
+    {
+        let source = ctx.accounts.source.to_account_info();
+        let data = source.try_borrow_data()?;
+        let account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+        let transferring = account.get_extension::<TransferHookAccount>()?.transferring;
+        require!(bool::from(transferring), ErrorCode::NotTransferring);
+    }
+
+    if let Some(mut policy) = read_policy(&ctx.accounts.source_policy)? {
+        let spent = policy
+            .spend(amount, Clock::get()?.unix_timestamp)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(spent <= policy.spend_limit, ErrorCode::SpendLimitExceeded);
+        policy.try_serialize(&mut &mut ctx.accounts.source_policy.try_borrow_mut_data()?[..])?;
+    }
+    Ok(())
+}
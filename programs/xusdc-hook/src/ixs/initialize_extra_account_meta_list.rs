@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+use crate::state::{
+    ADMIN_KEY, BLOCKED_ACCOUNT_SEED, EXTRA_ACCOUNT_METAS_SEED, POLICY_SEED, XUSDC_MINT_KEY,
+};
+
+/// Where the owner of a token account is stored
+const TOKEN_ACCOUNT_OWNER: (u8, u8) = (32, 32);
+
+/// Where the xUSDC program lands among `execute`'s accounts
+const XUSDC_PROGRAM_INDEX: u8 = 6;
+
+/// `seed` followed by the owner of the token account at `account_index`
+fn owner_seeds(seed: &[u8], account_index: u8) -> [Seed; 2] {
+    [
+        Seed::Literal {
+            bytes: seed.to_vec(),
+        },
+        Seed::AccountData {
+            account_index,
+            data_index: TOKEN_ACCOUNT_OWNER.0,
+            length: TOKEN_ACCOUNT_OWNER.1,
+        },
+    ]
+}
+
+/// Accounts Token-2022 adds to `execute` after the meta list: the source owner's policy,
+/// the xUSDC program, and the source and destination owners' xUSDC blocklist entries
+pub fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    let blocked_of = |account_index| {
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            XUSDC_PROGRAM_INDEX,
+            &owner_seeds(BLOCKED_ACCOUNT_SEED, account_index),
+            false,
+            false,
+        )
+    };
+    Ok(vec![
+        // The source's policy is writable to count its spending
+        ExtraAccountMeta::new_with_seeds(&owner_seeds(POLICY_SEED, 0), false, true)?,
+        ExtraAccountMeta::new_with_pubkey(&xusdc::ID, false, false)?,
+        blocked_of(0)?,
+        blocked_of(2)?,
+    ])
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    /// CHECK: Written below as a TLV meta list
+    #[account(
+        init,
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()],
+        bump,
+        payer = authority,
+        space = ExtraAccountMetaList::size_of(extra_account_metas()?.len())?,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    /// CHECK: Only used as a seed
+    #[account(address = XUSDC_MINT_KEY)]
+    pub mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
+    let mut data = ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?;
+    ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &extra_account_metas()?)?;
+    Ok(())
+}
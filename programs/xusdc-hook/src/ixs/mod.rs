@@ -0,0 +1,10 @@
+// Every module defines its own `handler`; the globs are only used for the account structs.
+#![allow(ambiguous_glob_reexports)]
+
+pub mod execute;
+pub mod initialize_extra_account_meta_list;
+pub mod set_policy;
+
+pub use execute::*;
+pub use initialize_extra_account_meta_list::*;
+pub use set_policy::*;
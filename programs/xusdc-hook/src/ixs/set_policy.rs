@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::PolicyUpdated;
+use crate::state::{WalletPolicy, ADMIN_KEY, POLICY_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct SetPolicy<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [POLICY_SEED, wallet.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + WalletPolicy::INIT_SPACE,
+    )]
+    pub policy: Account<'info, WalletPolicy>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<SetPolicy>,
+    wallet: Pubkey,
+    spend_limit: u64,
+    window_seconds: i64,
+) -> Result<()> {
+    require!(window_seconds > 0, ErrorCode::InvalidPolicy);
+
+    // A changed limit applies to what was already spent in the current window
+    let policy = &mut ctx.accounts.policy;
+    if policy.wallet == Pubkey::default() {
+        policy.wallet = wallet;
+        policy.window_start = Clock::get()?.unix_timestamp;
+    }
+    policy.spend_limit = spend_limit;
+    policy.window_seconds = window_seconds;
+
+    emit_cpi!(PolicyUpdated {
+        wallet,
+        spend_limit,
+        window_seconds,
+    });
+    Ok(())
+}
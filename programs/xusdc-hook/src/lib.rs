@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+use spl_discriminator::SplDiscriminate;
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+pub mod error;
+pub mod events;
+pub mod ixs;
+pub mod state;
+
+use ixs::*;
+
+declare_id!("9wWX5aM2whfNuu46sYaxcWuyU6gJF6nb2V7qvsMBn2Wr");
+
+/// Token-2022 transfer hook for xUSDC, run on every transfer once the xUSDC program's
+/// `set_transfer_hook` points the mint at it. It refuses transfers from or to wallets
+/// on xUSDC's blocklist, holds wallets to their spend limits and counts what each of
+/// them sends.
+#[program]
+pub mod xusdc_hook {
+    use super::*;
+
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        initialize_extra_account_meta_list::handler(ctx)
+    }
+
+    pub fn set_policy(
+        ctx: Context<SetPolicy>,
+        wallet: Pubkey,
+        spend_limit: u64,
+        window_seconds: i64,
+    ) -> Result<()> {
+        set_policy::handler(ctx, wallet, spend_limit, window_seconds)
+    }
+
+    #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn execute(ctx: Context<Execute>, amount: u64) -> Result<()> {
+        execute::handler(ctx, amount)
+    }
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+pub use xusdc::state::{ADMIN_KEY, BLOCKED_ACCOUNT_SEED, XUSDC_MINT_KEY};
+
+/// Fixed by the transfer hook interface, with the mint as the second seed
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+pub const POLICY_SEED: &[u8] = b"policy";
+
+/// Set by the admin for one wallet. Wallets without a policy have no spend limit.
+#[account]
+#[derive(InitSpace, Debug, PartialEq, Eq)]
+pub struct WalletPolicy {
+    pub wallet: Pubkey,
+    /// Most xUSDC the wallet can send per window
+    pub spend_limit: u64,
+    pub window_seconds: i64,
+    pub window_start: i64,
+    /// Sent since `window_start`
+    pub spent: u64,
+    /// Transfers the wallet has sent since its policy was set, and their total
+    pub transfers: u64,
+    pub volume: u64,
+}
+
+impl WalletPolicy {
+    /// Count `amount` against the limit and the wallet's totals, starting a new window if the last one is over
+    pub fn spend(&mut self, amount: u64, now: i64) -> Option<u64> {
+        if now >= self.window_start.saturating_add(self.window_seconds) {
+            self.window_start = now;
+            self.spent = 0;
+        }
+        self.spent = self.spent.checked_add(amount)?;
+        self.transfers = self.transfers.checked_add(1)?;
+        self.volume = self.volume.checked_add(amount)?;
+        Some(self.spent)
+    }
+}
@@ -0,0 +1,199 @@
+//! The transfer hook, loaded next to xUSDC with the mint pointing at it
+
+use anchor_lang::AccountDeserialize;
+use litesvm::types::TransactionResult;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::TransactionError;
+use xusdc_client::{hook, instruction, pda, ADMIN_KEY, TOKEN_2022_PROGRAM_ID, XUSDC_MINT_KEY};
+use xusdc_harness::Env;
+use xusdc_hook::error::ErrorCode;
+use xusdc_hook::state::WalletPolicy;
+
+const TEN_USDC: u64 = 10_000_000;
+const DAY: i64 = 24 * 60 * 60;
+
+fn hook_env() -> Env {
+    let mut env = Env::new();
+    env.enable_transfer_hook();
+    env
+}
+
+fn assert_hook_error(result: TransactionResult, code: ErrorCode) {
+    let code = u32::from(code);
+    match result {
+        Ok(_) => panic!("Expected Custom({code}), but the transaction succeeded"),
+        Err(e) => match e.err {
+            TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+                assert_eq!(actual, code, "{}", e.meta.logs.join("\n"))
+            }
+            err => panic!("Expected Custom({code}), got {err:?}"),
+        },
+    }
+}
+
+fn must(result: TransactionResult) {
+    if let Err(failed) = result {
+        panic!("{:?}\n{}", failed.err, failed.meta.logs.join("\n"));
+    }
+}
+
+fn set_policy(env: &mut Env, wallet: &Pubkey, spend_limit: u64) {
+    must(env.send(
+        &ADMIN_KEY,
+        &[hook::set_policy(&ADMIN_KEY, wallet, spend_limit, DAY)],
+    ));
+}
+
+fn policy(env: &Env, wallet: &Pubkey) -> WalletPolicy {
+    let account = env.svm.get_account(&hook::policy(wallet).0).unwrap();
+    WalletPolicy::try_deserialize(&mut &account.data[..]).unwrap()
+}
+
+#[test]
+fn test_transfers_are_counted_per_wallet() {
+    let mut env = hook_env();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.funded_user(TEN_USDC);
+    set_policy(&mut env, &alice.pubkey(), u64::MAX);
+    set_policy(&mut env, &bob.pubkey(), u64::MAX);
+
+    must(env.transfer(&alice.pubkey(), &bob.pubkey(), 1_000_000));
+    env.sign_and_settle(&alice, &bob.pubkey(), 2_000_000);
+    must(env.transfer(&bob.pubkey(), &alice.pubkey(), 500_000));
+
+    assert_eq!(env.xusdc_balance(&bob.pubkey()), TEN_USDC + 2_500_000);
+    let alice_policy = policy(&env, &alice.pubkey());
+    assert_eq!(
+        (alice_policy.transfers, alice_policy.volume),
+        (2, 3_000_000)
+    );
+    let bob_policy = policy(&env, &bob.pubkey());
+    assert_eq!((bob_policy.transfers, bob_policy.volume), (1, 500_000));
+
+    // Only the sender's policy is writable, so transfers from different wallets don't
+    // lock a shared account
+    let accounts = hook::transfer_accounts(&alice.pubkey(), &bob.pubkey());
+    let writable: Vec<_> = accounts.iter().filter(|meta| meta.is_writable).collect();
+    assert_eq!(writable.len(), 1);
+    assert_eq!(writable[0].pubkey, hook::policy(&alice.pubkey()).0);
+}
+
+#[test]
+fn test_transfer_without_hook_accounts_fails() {
+    let mut env = hook_env();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.user();
+
+    let facilitator = env.facilitator.pubkey();
+    let payload = env.sign(&alice, &bob.pubkey(), 1_000_000);
+    let settle = instruction::settle_payment(&facilitator, &payload);
+    assert!(env.send(&facilitator, &[settle]).is_err());
+    assert_eq!(env.xusdc_balance(&bob.pubkey()), 0);
+}
+
+#[test]
+fn test_blocked_wallets_cannot_send_or_receive() {
+    let mut env = hook_env();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.funded_user(TEN_USDC);
+    let alice_key = alice.pubkey();
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::block_account(&ADMIN_KEY, &alice_key)],
+    ));
+
+    // Plain Token-2022 transfers, outside the xUSDC program, are refused by the hook
+    let result = env.transfer(&alice_key, &bob.pubkey(), 1);
+    assert_hook_error(result, ErrorCode::WalletBlocked);
+    let result = env.transfer(&bob.pubkey(), &alice_key, 1);
+    assert_hook_error(result, ErrorCode::WalletBlocked);
+    assert_eq!(env.xusdc_balance(&alice_key), TEN_USDC);
+    assert_eq!(env.xusdc_balance(&bob.pubkey()), TEN_USDC);
+
+    // Unblocked, the same transfers go through
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::unblock_account(&ADMIN_KEY, &alice_key)],
+    ));
+    must(env.transfer(&alice_key, &bob.pubkey(), 1));
+    must(env.transfer(&bob.pubkey(), &alice_key, 1));
+    assert_eq!(env.xusdc_balance(&alice_key), TEN_USDC);
+}
+
+#[test]
+fn test_spend_limit_resets_each_window() {
+    let mut env = hook_env();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.user();
+    set_policy(&mut env, &alice.pubkey(), 3_000_000);
+
+    env.sign_and_settle(&alice, &bob.pubkey(), 2_000_000);
+    must(env.transfer(&alice.pubkey(), &bob.pubkey(), 1_000_000));
+    let result = env.transfer(&alice.pubkey(), &bob.pubkey(), 1);
+    assert_hook_error(result, ErrorCode::SpendLimitExceeded);
+    assert_eq!(policy(&env, &alice.pubkey()).spent, 3_000_000);
+
+    // Receiving doesn't count against bob, who has no policy at all
+    must(env.transfer(&bob.pubkey(), &alice.pubkey(), 3_000_000));
+
+    env.warp(DAY);
+    must(env.transfer(&alice.pubkey(), &bob.pubkey(), 3_000_000));
+    assert_eq!(policy(&env, &alice.pubkey()).spent, 3_000_000);
+}
+
+#[test]
+fn test_execute_outside_a_transfer_fails() {
+    let mut env = hook_env();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.user();
+    set_policy(&mut env, &alice.pubkey(), u64::MAX);
+
+    let (alice_key, bob_key) = (alice.pubkey(), bob.pubkey());
+    let mut accounts = vec![
+        AccountMeta::new_readonly(pda::xusdc_token_address(&alice_key), false),
+        AccountMeta::new_readonly(XUSDC_MINT_KEY, false),
+        AccountMeta::new_readonly(pda::xusdc_token_address(&bob_key), false),
+        AccountMeta::new_readonly(alice_key, false),
+        AccountMeta::new_readonly(hook::extra_account_metas().0, false),
+    ];
+    accounts.extend(hook::transfer_accounts(&alice_key, &bob_key));
+    accounts.truncate(accounts.len() - 2);
+    let execute = Instruction {
+        program_id: hook::HOOK_PROGRAM_ID,
+        accounts,
+        data: spl_transfer_hook_interface::instruction::TransferHookInstruction::Execute {
+            amount: 1,
+        }
+        .pack(),
+    };
+    assert_hook_error(env.send(&alice_key, &[execute]), ErrorCode::NotTransferring);
+    assert_eq!(policy(&env, &alice_key).transfers, 0);
+}
+
+#[test]
+fn test_disabling_the_hook() {
+    let mut env = hook_env();
+    let alice = env.funded_user(TEN_USDC);
+    let bob = env.user();
+    set_policy(&mut env, &alice.pubkey(), 0);
+
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::set_transfer_hook(&ADMIN_KEY, None)],
+    ));
+    let transfer = spl_token_2022::instruction::transfer_checked(
+        &TOKEN_2022_PROGRAM_ID,
+        &pda::xusdc_token_address(&alice.pubkey()),
+        &XUSDC_MINT_KEY,
+        &pda::xusdc_token_address(&bob.pubkey()),
+        &alice.pubkey(),
+        &[],
+        1_000_000,
+        6,
+    )
+    .unwrap();
+    must(env.send(&alice.pubkey(), &[transfer]));
+    assert_eq!(env.xusdc_balance(&bob.pubkey()), 1_000_000);
+}
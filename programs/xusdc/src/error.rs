@@ -20,4 +20,42 @@ pub enum ErrorCode {
     InvalidPublicKey,
     #[msg("Unauthorized signer")]
     UnauthorizedSigner,
+    #[msg("Collateral mint is not registered")]
+    UnsupportedCollateral,
+    #[msg("Collateral mint is already registered")]
+    CollateralAlreadyRegistered,
+    #[msg("Collateral registry is full")]
+    CollateralRegistryFull,
+    #[msg("Collateral must use the same decimals as xUSDC")]
+    InvalidCollateralDecimals,
+    #[msg("Collateral vault does not match the registry")]
+    InvalidCollateralVault,
+    #[msg("Deposit would exceed the collateral cap")]
+    DepositCapExceeded,
+    #[msg("Withdraw fee is too high")]
+    InvalidWithdrawFee,
+    #[msg("Not enough xUSDC is backed by this collateral")]
+    InsufficientCollateral,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Collateral does not cover the xUSDC supply")]
+    InsufficientBacking,
+    #[msg("Invalid withdraw limits")]
+    InvalidWithdrawLimits,
+    #[msg("A withdraw request is already pending")]
+    WithdrawRequestPending,
+    #[msg("Withdraw request is not claimable yet")]
+    WithdrawRequestNotReady,
+    #[msg("Wallet is blocked")]
+    AccountBlocked,
+    #[msg("Refund is zero or more than is left of the payment")]
+    InvalidRefundAmount,
+    #[msg("Nonce is already in the current layout")]
+    NonceIsCurrent,
+    #[msg("Nonce must be migrated to the current layout first")]
+    NonceNotMigrated,
+    #[msg("xUSDC mint lacks a Token-2022 extension that only a new mint can have")]
+    MintMissingExtension,
+    #[msg("xUSDC mint has no freeze authority and can't get one without a new mint")]
+    MintNotFreezable,
 }
@@ -0,0 +1,199 @@
+use anchor_lang::prelude::*;
+
+use crate::ixs::Split;
+use crate::state::MetadataField;
+
+// Every event is emitted with `emit_cpi!`, so it is recorded as an inner instruction
+// and cannot be lost to log truncation.
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentSettled {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub nonce: [u8; 32],
+    pub facilitator: Pubkey,
+    pub valid_until: i64,
+    /// The request the payer signed for, so the payee can prove what was paid for
+    pub resource_hash: [u8; 32],
+    pub memo: Option<String>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPaymentSettled {
+    pub from: Pubkey,
+    /// Each recipient and what it was paid, revealed even when the payer signed a
+    /// commitment
+    pub splits: Vec<Split>,
+    pub amount: u64,
+    pub nonce: [u8; 32],
+    pub facilitator: Pubkey,
+    pub valid_until: i64,
+    /// The request the payer signed for, as in `PaymentSettled`
+    pub resource_hash: [u8; 32],
+    pub memo: Option<String>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRefunded {
+    pub nonce: [u8; 32],
+    /// The original payer, who receives the refund
+    pub from: Pubkey,
+    /// The original payee, who signed the refund
+    pub to: Pubkey,
+    pub amount: u64,
+    /// Refunded so far, including this refund
+    pub refunded: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deposited {
+    pub user: Pubkey,
+    pub collateral_mint: Pubkey,
+    /// Collateral the vault received, which is also the amount of xUSDC minted
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Withdrawn {
+    pub user: Pubkey,
+    pub collateral_mint: Pubkey,
+    /// xUSDC burned
+    pub amount: u64,
+    /// Part of `amount` kept in the vault
+    pub fee: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawRequested {
+    pub user: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub claimable_at: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawRequestCancelled {
+    pub user: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RentContributed {
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RentWithdrawn {
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceCollected {
+    pub nonce_account: Pubkey,
+    pub expires_at: i64,
+    /// Rent returned to the global rent pool
+    pub lamports: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceMigrated {
+    pub nonce_account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    /// Rent the global rent pool added for the larger account
+    pub lamports: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollateralUpdated {
+    pub mint: Pubkey,
+    pub deposit_cap: u64,
+    pub withdraw_fee_bps: u16,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawLimitsUpdated {
+    pub window_seconds: i64,
+    pub global_limit: u64,
+    pub wallet_limit: u64,
+    pub queue_delay_seconds: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CollateralReserve {
+    pub mint: Pubkey,
+    pub vault_balance: u64,
+    pub outstanding: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReservesAttested {
+    pub xusdc_supply: u64,
+    pub total_reserves: u64,
+    pub collaterals: Vec<CollateralReserve>,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataUpdated {
+    pub field: MetadataField,
+    pub value: String,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferHookUpdated {
+    /// `None` when the hook was turned off
+    pub program_id: Option<Pubkey>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountBlocked {
+    pub wallet: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountUnblocked {
+    pub wallet: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreezeUpdated {
+    pub wallet: Pubkey,
+    pub token_account: Pubkey,
+    pub frozen: bool,
+}
+
+/// xUSDC taken from a blocked wallet by the admin
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seized {
+    pub wallet: Pubkey,
+    pub from_token_account: Pubkey,
+    pub to_token_account: Pubkey,
+    pub amount: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
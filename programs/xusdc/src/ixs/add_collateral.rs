@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{self, AssociatedToken, Create},
+    token_interface::{Mint, TokenInterface},
+};
+
+use crate::error::ErrorCode;
+use crate::events::CollateralUpdated;
+use crate::state::{
+    CollateralInfo, CollateralRegistry, ADMIN_KEY, COLLATERAL_REGISTRY_SEED, MAX_COLLATERALS,
+    MAX_WITHDRAW_FEE_BPS, TRANSFER_AUTHORITY_SEED, XUSDC_MINT_KEY,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AddCollateral<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+    #[account(address = XUSDC_MINT_KEY)]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = collateral_token_program.key())]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: The collateral vault, created here as the ATA of the transfer authority
+    #[account(mut)]
+    pub collateral_vault: UncheckedAccount<'info>,
+    /// CHECK: This is the transfer authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: UncheckedAccount<'info>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddCollateral>, deposit_cap: u64, withdraw_fee_bps: u16) -> Result<()> {
+    let registry = &mut ctx.accounts.collateral_registry;
+    let collateral_mint = ctx.accounts.collateral_mint.key();
+
+    require!(
+        registry.get(&collateral_mint).is_none(),
+        ErrorCode::CollateralAlreadyRegistered
+    );
+    require!(
+        registry.collaterals.len() < MAX_COLLATERALS,
+        ErrorCode::CollateralRegistryFull
+    );
+    // xUSDC is minted 1:1, which only holds for collateral with the same decimals
+    require_eq!(
+        ctx.accounts.collateral_mint.decimals,
+        ctx.accounts.xusdc_mint.decimals,
+        ErrorCode::InvalidCollateralDecimals
+    );
+    require!(
+        withdraw_fee_bps <= MAX_WITHDRAW_FEE_BPS,
+        ErrorCode::InvalidWithdrawFee
+    );
+
+    let expected_vault = anchor_spl::associated_token::get_associated_token_address_with_program_id(
+        &ctx.accounts.transfer_authority.key(),
+        &collateral_mint,
+        &ctx.accounts.collateral_token_program.key(),
+    );
+    require_keys_eq!(
+        ctx.accounts.collateral_vault.key(),
+        expected_vault,
+        ErrorCode::InvalidCollateralVault
+    );
+
+    associated_token::create_idempotent(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: ctx.accounts.authority.to_account_info(),
+            associated_token: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.transfer_authority.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.collateral_token_program.to_account_info(),
+        },
+    ))?;
+
+    registry.collaterals.push(CollateralInfo {
+        mint: collateral_mint,
+        vault: expected_vault,
+        token_program: ctx.accounts.collateral_token_program.key(),
+        deposit_cap,
+        withdraw_fee_bps,
+        outstanding: 0,
+        fees_accrued: 0,
+    });
+
+    emit_cpi!(CollateralUpdated {
+        mint: collateral_mint,
+        deposit_cap,
+        withdraw_fee_bps,
+    });
+    Ok(())
+}
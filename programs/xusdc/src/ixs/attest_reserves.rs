@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::events::{CollateralReserve, ReservesAttested};
+use crate::state::{CollateralRegistry, COLLATERAL_REGISTRY_SEED, XUSDC_MINT_KEY};
+
+/// Permissionless proof of reserves.
+///
+/// The vault of every registered collateral must be passed as a remaining account,
+/// in registry order. A shortfall fails the attestation, so nothing is emitted for it;
+/// `ReservesAttested` only ever records full backing.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AttestReserves<'info> {
+    #[account(
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, AttestReserves<'info>>) -> Result<()> {
+    let registry = &ctx.accounts.collateral_registry;
+    require_eq!(
+        ctx.remaining_accounts.len(),
+        registry.collaterals.len(),
+        ErrorCode::InvalidCollateralVault
+    );
+
+    let mut total_reserves = 0u64;
+    let mut collaterals = Vec::with_capacity(registry.collaterals.len());
+    for (collateral, vault_info) in registry.collaterals.iter().zip(ctx.remaining_accounts) {
+        require_keys_eq!(
+            collateral.vault,
+            vault_info.key(),
+            ErrorCode::InvalidCollateralVault
+        );
+        let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        collateral.check_backing(vault.amount)?;
+
+        total_reserves = total_reserves
+            .checked_add(vault.amount)
+            .ok_or(ErrorCode::Overflow)?;
+        collaterals.push(CollateralReserve {
+            mint: collateral.mint,
+            vault_balance: vault.amount,
+            outstanding: collateral.outstanding,
+        });
+    }
+
+    let xusdc_supply = ctx.accounts.xusdc_mint.supply;
+    require!(
+        total_reserves >= xusdc_supply,
+        ErrorCode::InsufficientBacking
+    );
+    registry.check_supply(xusdc_supply)?;
+
+    emit_cpi!(ReservesAttested {
+        xusdc_supply,
+        total_reserves,
+        collaterals,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
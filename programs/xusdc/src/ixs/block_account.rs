@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::events::AccountBlocked;
+use crate::state::{BlockedAccount, ADMIN_KEY, BLOCKED_ACCOUNT_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct BlockAccount<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        seeds = [BLOCKED_ACCOUNT_SEED, wallet.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + BlockedAccount::INIT_SPACE,
+    )]
+    pub blocked_account: Account<'info, BlockedAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<BlockAccount>, wallet: Pubkey) -> Result<()> {
+    let blocked_account = &mut ctx.accounts.blocked_account;
+    blocked_account.wallet = wallet;
+    blocked_account.blocked_at = Clock::get()?.unix_timestamp;
+
+    emit_cpi!(AccountBlocked { wallet });
+    Ok(())
+}
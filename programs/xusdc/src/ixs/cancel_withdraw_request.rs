@@ -0,0 +1,83 @@
+use anchor_lang::accounts::interface_account::InterfaceAccount;
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::{self, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::events::WithdrawRequestCancelled;
+use crate::state::{
+    WithdrawRequest, BLOCKED_ACCOUNT_SEED, TRANSFER_AUTHORITY_SEED, WITHDRAW_REQUEST_SEED,
+    XUSDC_MINT_KEY,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelWithdrawRequest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        close = user,
+        has_one = user,
+        seeds = [WITHDRAW_REQUEST_SEED, &user.key().to_bytes()],
+        bump
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(
+        mut,
+        token::mint=xusdc_mint,
+        token::authority=user,
+        token::token_program=token_program.key()
+    )]
+    pub user_xusdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: PDA used as transfer authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+
+    /// CHECK: The user's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, user.key().as_ref()],
+        bump,
+        constraint = user_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+}
+
+/// Give back the xUSDC burned by a pending withdraw request.
+///
+/// The collateral never left the vault, so re-minting keeps the supply backed.
+pub fn handler(ctx: Context<CancelWithdrawRequest>) -> Result<()> {
+    token_2022::mint_to_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022::MintToChecked {
+                mint: ctx.accounts.xusdc_mint.to_account_info(),
+                to: ctx.accounts.user_xusdc_ata.to_account_info(),
+                authority: ctx.accounts.transfer_authority.to_account_info(),
+            },
+            &[&[TRANSFER_AUTHORITY_SEED, &[ctx.bumps.transfer_authority]]],
+        ),
+        ctx.accounts.withdraw_request.amount,
+        ctx.accounts.xusdc_mint.decimals,
+    )?;
+
+    let request = &ctx.accounts.withdraw_request;
+    emit_cpi!(WithdrawRequestCancelled {
+        user: request.user,
+        collateral_mint: request.collateral_mint,
+        amount: request.amount,
+    });
+    Ok(())
+}
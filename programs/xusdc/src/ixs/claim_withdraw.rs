@@ -0,0 +1,129 @@
+use anchor_lang::accounts::interface_account::InterfaceAccount;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::error::ErrorCode;
+use crate::events::Withdrawn;
+use crate::ixs::withdraw::release_collateral;
+use crate::state::{
+    CollateralRegistry, WalletOutflow, WithdrawLimits, WithdrawRequest, BLOCKED_ACCOUNT_SEED,
+    COLLATERAL_REGISTRY_SEED, TRANSFER_AUTHORITY_SEED, WALLET_OUTFLOW_SEED, WITHDRAW_LIMITS_SEED,
+    WITHDRAW_REQUEST_SEED,
+};
+
+// Token accounts are boxed to keep `try_accounts` within the SBF stack frame
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        close = user,
+        has_one = user,
+        has_one = collateral_mint,
+        seeds = [WITHDRAW_REQUEST_SEED, &user.key().to_bytes()],
+        bump
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(
+        mut,
+        token::mint=collateral_mint,
+        token::authority=user,
+        token::token_program=collateral_token_program.key()
+    )]
+    pub user_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The collateral vault: the ATA of `transfer_authority`
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = transfer_authority,
+        associated_token::token_program = collateral_token_program,
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mint::token_program = collateral_token_program.key())]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: PDA used as transfer authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: AccountInfo<'info>,
+
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+
+    #[account(mut, seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    #[account(mut, seeds = [WITHDRAW_LIMITS_SEED], bump)]
+    pub withdraw_limits: Account<'info, WithdrawLimits>,
+
+    #[account(
+        init_if_needed,
+        seeds = [WALLET_OUTFLOW_SEED, &user.key().to_bytes()],
+        bump,
+        payer = user,
+        space = 8 + std::mem::size_of::<WalletOutflow>(),
+    )]
+    pub wallet_outflow: Account<'info, WalletOutflow>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The user's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, user.key().as_ref()],
+        bump,
+        constraint = user_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+}
+
+/// Release the collateral of a queued withdrawal once its delay has passed.
+///
+/// The claim is never refused for being over the limits, but it counts towards the
+/// current window's outflow, so queueing can't be used to withdraw more than the
+/// limits allow per window without waiting.
+pub fn handler(ctx: Context<ClaimWithdraw>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        now >= ctx.accounts.withdraw_request.claimable_at,
+        ErrorCode::WithdrawRequestNotReady
+    );
+
+    let amount = ctx.accounts.withdraw_request.amount;
+    let limits = &mut ctx.accounts.withdraw_limits;
+    let wallet_outflow = &mut ctx.accounts.wallet_outflow;
+    wallet_outflow.user = ctx.accounts.user.key();
+    limits.roll_window(now);
+    wallet_outflow.roll_window(now, limits.window_seconds);
+    limits.window_outflow = limits.window_outflow.saturating_add(amount);
+    wallet_outflow.outflow = wallet_outflow.outflow.saturating_add(amount);
+
+    let fee = release_collateral(
+        &mut ctx.accounts.collateral_registry,
+        &ctx.accounts.collateral_mint,
+        &ctx.accounts.collateral_vault,
+        &ctx.accounts.user_collateral_ata,
+        &ctx.accounts.collateral_token_program,
+        &ctx.accounts.transfer_authority,
+        ctx.bumps.transfer_authority,
+        amount,
+    )?;
+
+    ctx.accounts.collateral_vault.reload()?;
+    ctx.accounts
+        .collateral_registry
+        .get(&ctx.accounts.collateral_mint.key())
+        .ok_or(ErrorCode::UnsupportedCollateral)?
+        .check_backing(ctx.accounts.collateral_vault.amount)?;
+
+    emit_cpi!(Withdrawn {
+        user: ctx.accounts.user.key(),
+        collateral_mint: ctx.accounts.collateral_mint.key(),
+        amount,
+        fee,
+    });
+    Ok(())
+}
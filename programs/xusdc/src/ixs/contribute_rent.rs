@@ -1,11 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
 
+use crate::events::RentContributed;
 use crate::state::ContributorRentInfo;
 use crate::state::{RENT_CONTRIBUTOR_SEED, RENT_POOL_SEED};
 
+#[event_cpi]
 #[derive(Accounts)]
-
 pub struct ContributeRent<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
@@ -44,5 +45,9 @@ pub fn handler(ctx: Context<ContributeRent>, amount: u64) -> Result<()> {
         amount,
     )?;
 
+    emit_cpi!(RentContributed {
+        user: ctx.accounts.user.key(),
+        amount,
+    });
     Ok(())
 }
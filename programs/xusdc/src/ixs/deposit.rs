@@ -1,16 +1,21 @@
 use anchor_lang::accounts::interface_account::InterfaceAccount;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token};
 use anchor_spl::token_2022::{self, Token2022};
-use anchor_spl::token_interface::{Mint, TokenAccount};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
 
-use crate::state::{TRANSFER_AUTHORITY_SEED, USDC_MINT_KEY, XUSDC_MINT_KEY};
+use crate::error::ErrorCode;
+use crate::events::Deposited;
+use crate::state::{
+    CollateralRegistry, BLOCKED_ACCOUNT_SEED, COLLATERAL_REGISTRY_SEED, TRANSFER_AUTHORITY_SEED,
+    XUSDC_MINT_KEY,
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub tokenkeg: Program<'info, Token>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
     pub token_program: Program<'info, Token2022>,
     #[account(
         mut,
@@ -19,42 +24,105 @@ pub struct Deposit<'info> {
     )]
     pub xusdc_mint: InterfaceAccount<'info, Mint>,
 
+    #[account(mint::token_program = collateral_token_program.key())]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
     #[account(
-        mint::token_program = tokenkeg.key(),
-        address = USDC_MINT_KEY
+        mut,
+        token::mint=collateral_mint,
+        token::authority=user,
+        token::token_program=collateral_token_program.key()
     )]
-    pub usdc_mint: InterfaceAccount<'info, Mint>,
-
-    #[account(mut, token::mint=usdc_mint, token::authority=user, token::token_program=tokenkeg.key())]
-    pub user_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+    pub user_collateral_ata: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut, token::mint=xusdc_mint, token::authority=user, token::token_program=token_program.key())]
     pub user_xusdc_ata: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut, token::mint=usdc_mint, token::token_program=tokenkeg.key())]
-    pub usdc_global_ata: InterfaceAccount<'info, TokenAccount>,
+    /// The collateral vault: the ATA of `transfer_authority`
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = transfer_authority,
+        associated_token::token_program = collateral_token_program,
+    )]
+    pub collateral_vault: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: PDA used as transfer authority
     #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
     pub transfer_authority: AccountInfo<'info>,
+
+    #[account(mut, seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// CHECK: The user's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, user.key().as_ref()],
+        bump,
+        constraint = user_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
 }
 
 pub fn handler(ctx: Context<Deposit>, amount: u64) -> Result<()> {
     let bump = ctx.bumps.transfer_authority;
-    token::transfer_checked(
+    let collateral_mint = ctx.accounts.collateral_mint.key();
+    let collateral = ctx
+        .accounts
+        .collateral_registry
+        .get(&collateral_mint)
+        .ok_or(ErrorCode::UnsupportedCollateral)?;
+    require_keys_eq!(
+        collateral.vault,
+        ctx.accounts.collateral_vault.key(),
+        ErrorCode::InvalidCollateralVault
+    );
+    require_keys_eq!(
+        collateral.token_program,
+        ctx.accounts.collateral_token_program.key(),
+        ErrorCode::InvalidCollateralVault
+    );
+    let vault_before = ctx.accounts.collateral_vault.amount;
+    require!(
+        vault_before
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?
+            <= collateral.deposit_cap,
+        ErrorCode::DepositCapExceeded
+    );
+
+    token_interface::transfer_checked(
         CpiContext::new(
-            ctx.accounts.tokenkeg.to_account_info(),
-            token::TransferChecked {
-                from: ctx.accounts.user_usdc_ata.to_account_info(),
-                to: ctx.accounts.usdc_global_ata.to_account_info(),
-                mint: ctx.accounts.usdc_mint.to_account_info(),
+            ctx.accounts.collateral_token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: ctx.accounts.user_collateral_ata.to_account_info(),
+                to: ctx.accounts.collateral_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
                 authority: ctx.accounts.user.to_account_info(),
             },
         ),
         amount,
-        ctx.accounts.usdc_mint.decimals,
+        ctx.accounts.collateral_mint.decimals,
     )?;
 
+    // Token-2022 collateral may charge a transfer fee, so only mint what the vault received
+    ctx.accounts.collateral_vault.reload()?;
+    let received = ctx
+        .accounts
+        .collateral_vault
+        .amount
+        .checked_sub(vault_before)
+        .ok_or(ErrorCode::Overflow)?;
+
+    let collateral = ctx
+        .accounts
+        .collateral_registry
+        .get_mut(&collateral_mint)
+        .ok_or(ErrorCode::UnsupportedCollateral)?;
+    collateral.outstanding = collateral
+        .outstanding
+        .checked_add(received)
+        .ok_or(ErrorCode::Overflow)?;
+
     token_2022::mint_to_checked(
         CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
@@ -65,8 +133,22 @@ pub fn handler(ctx: Context<Deposit>, amount: u64) -> Result<()> {
             },
             &[&[TRANSFER_AUTHORITY_SEED, &[bump]]],
         ),
-        amount,
+        received,
         ctx.accounts.xusdc_mint.decimals,
     )?;
+
+    ctx.accounts.xusdc_mint.reload()?;
+    let registry = &ctx.accounts.collateral_registry;
+    registry
+        .get(&collateral_mint)
+        .ok_or(ErrorCode::UnsupportedCollateral)?
+        .check_backing(ctx.accounts.collateral_vault.amount)?;
+    registry.check_supply(ctx.accounts.xusdc_mint.supply)?;
+
+    emit_cpi!(Deposited {
+        user: ctx.accounts.user.key(),
+        collateral_mint,
+        amount: received,
+    });
     Ok(())
 }
@@ -1,12 +1,15 @@
-use anchor_lang::{prelude::*};
+use anchor_lang::prelude::*;
 
 use crate::error::ErrorCode;
+use crate::events::NonceCollected;
 use crate::state::{NonceAccount, RENT_POOL_SEED};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct GarbageCollect<'info> {
+    /// A nonce of either version, see `Nonce::read`
     #[account(
-        mut, 
+        mut,
         close = global_rent_pool
     )]
     pub nonce_account: Account<'info, NonceAccount>,
@@ -16,9 +19,18 @@ pub struct GarbageCollect<'info> {
 }
 
 pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, GarbageCollect<'info>>) -> Result<()> {
+    // `settle_payment` still accepts the authorization at `valid_until` itself, so
+    // collecting its nonce then would let it settle twice
     require!(
-        ctx.accounts.nonce_account.expires_at <= Clock::get()?.unix_timestamp,
+        ctx.accounts.nonce_account.expires_at < Clock::get()?.unix_timestamp,
         ErrorCode::NonceIsNotExpired
     );
+
+    let nonce_account = &ctx.accounts.nonce_account;
+    emit_cpi!(NonceCollected {
+        nonce_account: nonce_account.key(),
+        expires_at: nonce_account.expires_at,
+        lamports: nonce_account.to_account_info().lamports(),
+    });
     Ok(())
 }
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{NonceAccount, NONCE_SEED};
+
+#[derive(Accounts)]
+#[instruction(nonce: [u8; 32])]
+pub struct GetPayment<'info> {
+    /// CHECK: The nonce's PDA, empty until it is used and after it is collected
+    #[account(seeds = [NONCE_SEED, nonce.as_ref()], bump)]
+    pub nonce_account: UncheckedAccount<'info>,
+}
+
+/// The payment settled with `nonce`, as return data, or `None` if there is no nonce
+/// account. Changes nothing, so clients call it by simulating a transaction.
+pub fn handler(ctx: Context<GetPayment>, _nonce: [u8; 32]) -> Result<Option<NonceAccount>> {
+    let nonce_account = &ctx.accounts.nonce_account;
+    if nonce_account.owner != &crate::ID {
+        return Ok(None);
+    }
+    NonceAccount::read(&nonce_account.try_borrow_data()?).map(Some)
+}
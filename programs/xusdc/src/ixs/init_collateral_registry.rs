@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::events::CollateralUpdated;
+use crate::state::{
+    CollateralInfo, CollateralRegistry, ADMIN_KEY, COLLATERAL_REGISTRY_SEED,
+    TRANSFER_AUTHORITY_SEED, USDC_MINT_KEY, XUSDC_MINT_KEY,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitCollateralRegistry<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        seeds = [COLLATERAL_REGISTRY_SEED],
+        bump,
+        payer = authority,
+        space = 8 + CollateralRegistry::INIT_SPACE,
+    )]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+    #[account(
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = tokenkeg.key(),
+        address = USDC_MINT_KEY
+    )]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+    /// The USDC vault `initialize` created
+    #[account(
+        associated_token::mint = usdc_mint,
+        associated_token::authority = transfer_authority,
+        associated_token::token_program = tokenkeg,
+    )]
+    pub usdc_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA owning the vault
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub tokenkeg: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the collateral registry of a deployment initialized before it existed.
+///
+/// USDC was the only collateral then, so every xUSDC in circulation was minted against
+/// it and is recorded as outstanding, as `deposit` would have.
+pub fn handler(ctx: Context<InitCollateralRegistry>) -> Result<()> {
+    let usdc = CollateralInfo::usdc(
+        ctx.accounts.usdc_vault.key(),
+        ctx.accounts.xusdc_mint.supply,
+    );
+    emit_cpi!(CollateralUpdated {
+        mint: usdc.mint,
+        deposit_cap: usdc.deposit_cap,
+        withdraw_fee_bps: usdc.withdraw_fee_bps,
+    });
+    ctx.accounts.collateral_registry.collaterals = vec![usdc];
+    Ok(())
+}
@@ -2,12 +2,23 @@ use anchor_lang::{prelude::*, system_program};
 use anchor_spl::{
     associated_token::{self, AssociatedToken, Create},
     token::Token,
-    token_2022::{self, Token2022},
-    token_2022_extensions,
+    token_2022::{
+        self,
+        spl_token_2022::{extension::ExtensionType, state::Mint as MintState},
+        Token2022,
+    },
+    token_2022_extensions::{
+        self, spl_pod::optional_keys::OptionalNonZeroPubkey,
+        spl_token_metadata_interface::state::TokenMetadata,
+    },
     token_interface::Mint,
 };
 
-use crate::state::{ADMIN_KEY, TRANSFER_AUTHORITY_SEED, USDC_MINT_KEY, XUSDC_MINT_KEY};
+use crate::state::{
+    CollateralInfo, CollateralRegistry, WithdrawLimits, ADMIN_KEY, COLLATERAL_REGISTRY_SEED,
+    DEFAULT_WITHDRAW_QUEUE_DELAY_SECONDS, DEFAULT_WITHDRAW_WINDOW_SECONDS, TRANSFER_AUTHORITY_SEED,
+    USDC_MINT_KEY, WITHDRAW_LIMITS_SEED, XUSDC_MINT_KEY,
+};
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -30,12 +41,43 @@ pub struct Initialize<'info> {
     pub transfer_authority: UncheckedAccount<'info>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub tokenkeg: Program<'info, Token>,
+    #[account(
+        init,
+        seeds = [COLLATERAL_REGISTRY_SEED],
+        bump,
+        payer = authority,
+        space = 8 + CollateralRegistry::INIT_SPACE,
+    )]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+    #[account(
+        init,
+        seeds = [WITHDRAW_LIMITS_SEED],
+        bump,
+        payer = authority,
+        space = 8 + std::mem::size_of::<WithdrawLimits>(),
+    )]
+    pub withdraw_limits: Account<'info, WithdrawLimits>,
 }
 
-pub fn handler(ctx: Context<Initialize>) -> Result<()> {
-    // Found this out by running the spl-token cli, creating a perma delegate mint
-    // and checking the size of the mint account
-    let space = 0xca;
+pub fn handler(ctx: Context<Initialize>, name: String, symbol: String, uri: String) -> Result<()> {
+    let transfer_authority = ctx.accounts.transfer_authority.key();
+    let metadata = TokenMetadata {
+        update_authority: OptionalNonZeroPubkey::try_from(Some(transfer_authority))?,
+        mint: ctx.accounts.xusdc_mint.key(),
+        name,
+        symbol,
+        uri,
+        additional_metadata: vec![],
+    };
+
+    // Token-2022 wants the mint sized for its fixed-length extensions only, and grows it
+    // when the metadata is written, so the rent has to cover the metadata up front
+    let space = ExtensionType::try_calculate_account_len::<MintState>(&[
+        ExtensionType::PermanentDelegate,
+        ExtensionType::MetadataPointer,
+        ExtensionType::TransferHook,
+    ])?;
+    let lamports = Rent::get()?.minimum_balance(space + metadata.tlv_size_of()?);
     system_program::create_account(
         CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
@@ -44,8 +86,8 @@ pub fn handler(ctx: Context<Initialize>) -> Result<()> {
                 to: ctx.accounts.xusdc_mint.to_account_info(),
             },
         ),
-        Rent::get()?.minimum_balance(space as usize),
-        space,
+        lamports,
+        space as u64,
         &Token2022::id(),
     )?;
 
@@ -57,11 +99,33 @@ pub fn handler(ctx: Context<Initialize>) -> Result<()> {
             token_program_id: ctx.accounts.token_program.to_account_info(),
         },
     );
-    token_2022_extensions::permanent_delegate_initialize(
+    token_2022_extensions::permanent_delegate_initialize(cpi_context, &transfer_authority)?;
+
+    // Wallets find the metadata through the pointer, which points at the mint itself
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        token_2022_extensions::MetadataPointerInitialize {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            mint: ctx.accounts.xusdc_mint.to_account_info(),
+        },
+    );
+    token_2022_extensions::metadata_pointer_initialize(
         cpi_context,
-        &ctx.accounts.transfer_authority.key(),
+        Some(transfer_authority),
+        Some(ctx.accounts.xusdc_mint.key()),
     )?;
 
+    // The hook starts out disabled; `set_transfer_hook` can point it at a program later,
+    // which is only possible if the extension exists from the start
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        token_2022_extensions::TransferHookInitialize {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            mint: ctx.accounts.xusdc_mint.to_account_info(),
+        },
+    );
+    token_2022_extensions::transfer_hook_initialize(cpi_context, Some(transfer_authority), None)?;
+
     // Need to initialize the token 2022 mi nt
     let cpi_context = CpiContext::new(
         ctx.accounts.token_program.to_account_info(),
@@ -69,10 +133,36 @@ pub fn handler(ctx: Context<Initialize>) -> Result<()> {
             mint: ctx.accounts.xusdc_mint.to_account_info(),
         },
     );
-    token_2022::initialize_mint2(cpi_context, 6, &ctx.accounts.transfer_authority.key(), None)?;
+    // The transfer authority is also the freeze authority, used by `set_frozen` and `seize`
+    token_2022::initialize_mint2(
+        cpi_context,
+        6,
+        &transfer_authority,
+        Some(&transfer_authority),
+    )?;
+
+    // The metadata can only be written by the mint authority, and is updated through
+    // `update_metadata` with the transfer authority as its update authority
+    let bump = ctx.bumps.transfer_authority;
+    token_2022_extensions::token_metadata_initialize(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022_extensions::TokenMetadataInitialize {
+                program_id: ctx.accounts.token_program.to_account_info(),
+                metadata: ctx.accounts.xusdc_mint.to_account_info(),
+                update_authority: ctx.accounts.transfer_authority.to_account_info(),
+                mint_authority: ctx.accounts.transfer_authority.to_account_info(),
+                mint: ctx.accounts.xusdc_mint.to_account_info(),
+            },
+            &[&[TRANSFER_AUTHORITY_SEED, &[bump]]],
+        ),
+        metadata.name,
+        metadata.symbol,
+        metadata.uri,
+    )?;
 
     let expected_ata = anchor_spl::associated_token::get_associated_token_address_with_program_id(
-        &ctx.accounts.transfer_authority.key(),
+        &transfer_authority,
         &ctx.accounts.usdc_mint.key(),
         &Token::id(),
     );
@@ -90,5 +180,16 @@ pub fn handler(ctx: Context<Initialize>) -> Result<()> {
         },
     ))?;
 
+    // USDC is the first accepted collateral
+    ctx.accounts.collateral_registry.collaterals = vec![CollateralInfo::usdc(expected_ata, 0)];
+
+    // Withdrawals start out unlimited until the admin sets limits
+    let withdraw_limits = &mut ctx.accounts.withdraw_limits;
+    withdraw_limits.window_seconds = DEFAULT_WITHDRAW_WINDOW_SECONDS;
+    withdraw_limits.global_limit = u64::MAX;
+    withdraw_limits.wallet_limit = u64::MAX;
+    withdraw_limits.queue_delay_seconds = DEFAULT_WITHDRAW_QUEUE_DELAY_SECONDS;
+    withdraw_limits.window_start = Clock::get()?.unix_timestamp;
+
     Ok(())
 }
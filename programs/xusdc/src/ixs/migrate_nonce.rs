@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::NonceMigrated;
+use crate::state::{NonceAccount, NONCE_VERSION, RENT_POOL_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateNonce<'info> {
+    /// CHECK: A nonce account of either version, see `Nonce::read`
+    #[account(mut, owner = crate::ID)]
+    pub nonce_account: UncheckedAccount<'info>,
+    /// CHECK: Global rent pool that pays for the larger account
+    #[account(mut, seeds = [RENT_POOL_SEED], bump)]
+    pub rent_pool: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Rewrite a nonce in the current layout, keeping what its version recorded. Anyone
+/// can run it, once per nonce; the rent pool pays for the extra space, as it did for
+/// the account.
+pub fn handler(ctx: Context<MigrateNonce>) -> Result<()> {
+    let nonce_account = ctx.accounts.nonce_account.to_account_info();
+    let nonce = NonceAccount::read(&nonce_account.try_borrow_data()?)?;
+    require!(nonce.version < NONCE_VERSION, ErrorCode::NonceIsCurrent);
+
+    let space = 8 + NonceAccount::INIT_SPACE;
+    let top_up = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(nonce_account.lamports());
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.rent_pool.to_account_info(),
+                    to: nonce_account.clone(),
+                },
+                &[&[RENT_POOL_SEED, &[ctx.bumps.rent_pool]]],
+            ),
+            top_up,
+        )?;
+    }
+    nonce_account.realloc(space, false)?;
+
+    let from_version = nonce.version;
+    let nonce = NonceAccount {
+        version: NONCE_VERSION,
+        ..nonce
+    };
+    nonce.try_serialize(&mut &mut nonce_account.try_borrow_mut_data()?[..])?;
+
+    emit_cpi!(NonceMigrated {
+        nonce_account: nonce_account.key(),
+        from_version,
+        to_version: NONCE_VERSION,
+        lamports: top_up,
+    });
+    Ok(())
+}
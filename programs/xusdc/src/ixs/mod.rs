@@ -1,15 +1,54 @@
+// Every module defines its own `handler`; the globs are only used for the account structs.
+#![allow(ambiguous_glob_reexports)]
+
+pub mod add_collateral;
+pub mod attest_reserves;
+pub mod block_account;
+pub mod cancel_withdraw_request;
+pub mod claim_withdraw;
 pub mod contribute_rent;
 pub mod deposit;
 pub mod garbage_collect;
+pub mod get_payment;
+pub mod init_collateral_registry;
 pub mod initialize;
+pub mod migrate_nonce;
+pub mod refund_payment;
+pub mod request_withdraw;
+pub mod seize;
+pub mod set_frozen;
+pub mod set_transfer_hook;
+pub mod set_withdraw_limits;
 pub mod settle_payment;
+pub mod settle_split_payment;
+pub mod unblock_account;
+pub mod update_collateral;
+pub mod update_metadata;
 pub mod withdraw;
 pub mod withdraw_rent;
 
+pub use add_collateral::*;
+pub use attest_reserves::*;
+pub use block_account::*;
+pub use cancel_withdraw_request::*;
+pub use claim_withdraw::*;
 pub use contribute_rent::*;
 pub use deposit::*;
 pub use garbage_collect::*;
+pub use get_payment::*;
+pub use init_collateral_registry::*;
 pub use initialize::*;
+pub use migrate_nonce::*;
+pub use refund_payment::*;
+pub use request_withdraw::*;
+pub use seize::*;
+pub use set_frozen::*;
+pub use set_transfer_hook::*;
+pub use set_withdraw_limits::*;
 pub use settle_payment::*;
+pub use settle_split_payment::*;
+pub use unblock_account::*;
+pub use update_collateral::*;
+pub use update_metadata::*;
 pub use withdraw::*;
 pub use withdraw_rent::*;
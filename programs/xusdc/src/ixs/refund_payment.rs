@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::{spl_token_2022, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::events::PaymentRefunded;
+use crate::state::{NonceAccount, BLOCKED_ACCOUNT_SEED, NONCE_SEED, NONCE_VERSION, XUSDC_MINT_KEY};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(nonce: [u8; 32])]
+pub struct RefundPayment<'info> {
+    /// The payment's `to`, who gives the xUSDC back
+    pub payee: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    #[account(
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [NONCE_SEED, nonce.as_ref()],
+        bump,
+        constraint = nonce_account.version == NONCE_VERSION @ ErrorCode::NonceNotMigrated,
+        constraint = nonce_account.to == payee.key() @ ErrorCode::UnauthorizedSigner,
+    )]
+    pub nonce_account: Account<'info, NonceAccount>,
+    #[account(
+        mut,
+        token::mint = xusdc_mint,
+        token::authority = payee,
+        token::token_program = token_program.key()
+    )]
+    pub payee_xusdc_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = xusdc_mint,
+        token::authority = nonce_account.from,
+        token::token_program = token_program.key()
+    )]
+    pub payer_xusdc_ata: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: The payer's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, nonce_account.from.as_ref()],
+        bump,
+        constraint = from_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub from_blocked: UncheckedAccount<'info>,
+    /// CHECK: The payee's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, payee.key().as_ref()],
+        bump,
+        constraint = to_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub to_blocked: UncheckedAccount<'info>,
+}
+
+/// Return `amount` of a settled payment to its payer. Refunds can be partial, up to
+/// the payment's amount in total, for as long as the nonce hasn't been collected.
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, RefundPayment<'info>>,
+    nonce: [u8; 32],
+    amount: u64,
+) -> Result<()> {
+    let nonce_account = &ctx.accounts.nonce_account;
+    let refunded = nonce_account
+        .refunded
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    require!(
+        amount > 0 && refunded <= nonce_account.amount,
+        ErrorCode::InvalidRefundAmount
+    );
+
+    // With a transfer hook enabled, the remaining accounts carry its extra accounts
+    spl_token_2022::onchain::invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.payee_xusdc_ata.to_account_info(),
+        ctx.accounts.xusdc_mint.to_account_info(),
+        ctx.accounts.payer_xusdc_ata.to_account_info(),
+        ctx.accounts.payee.to_account_info(),
+        ctx.remaining_accounts,
+        amount,
+        ctx.accounts.xusdc_mint.decimals,
+        &[],
+    )?;
+
+    let nonce_account = &mut ctx.accounts.nonce_account;
+    nonce_account.refunded = refunded;
+    emit_cpi!(PaymentRefunded {
+        nonce,
+        from: nonce_account.from,
+        to: nonce_account.to,
+        amount,
+        refunded,
+    });
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use anchor_lang::accounts::interface_account::InterfaceAccount;
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::{self, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::error::ErrorCode;
+use crate::events::WithdrawRequested;
+use crate::ixs::withdraw::{open_withdraw_request, registered_collateral};
+use crate::state::{
+    CollateralRegistry, WithdrawLimits, WithdrawRequest, BLOCKED_ACCOUNT_SEED,
+    COLLATERAL_REGISTRY_SEED, TRANSFER_AUTHORITY_SEED, WITHDRAW_LIMITS_SEED, WITHDRAW_REQUEST_SEED,
+    XUSDC_MINT_KEY,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint=xusdc_mint,
+        token::authority=user,
+        token::token_program=token_program.key()
+    )]
+    pub user_xusdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The collateral vault: the ATA of `transfer_authority`
+    #[account(
+        associated_token::mint = collateral_mint,
+        associated_token::authority = transfer_authority,
+        associated_token::token_program = collateral_token_program,
+    )]
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mint::token_program = collateral_token_program.key())]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: PDA used as transfer authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: AccountInfo<'info>,
+
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+    pub token_program: Program<'info, Token2022>,
+
+    #[account(seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    #[account(seeds = [WITHDRAW_LIMITS_SEED], bump)]
+    pub withdraw_limits: Account<'info, WithdrawLimits>,
+
+    /// CHECK: PDA created here, checked to be empty
+    #[account(
+        mut,
+        seeds = [WITHDRAW_REQUEST_SEED, &user.key().to_bytes()],
+        bump
+    )]
+    pub withdraw_request: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The user's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, user.key().as_ref()],
+        bump,
+        constraint = user_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+}
+
+/// Burn xUSDC now and redeem it for collateral with `claim_withdraw` after the queue delay.
+///
+/// Unlike `withdraw`, this isn't held to the rate limits. The claim counts towards them
+/// instead, once the delay has passed.
+pub fn handler(ctx: Context<RequestWithdraw>, amount: u64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let claimable_at = now.saturating_add(ctx.accounts.withdraw_limits.queue_delay_seconds);
+
+    registered_collateral(
+        &ctx.accounts.collateral_registry,
+        &ctx.accounts.collateral_mint,
+        &ctx.accounts.collateral_vault,
+        &ctx.accounts.collateral_token_program,
+        amount,
+    )?;
+    open_withdraw_request(
+        &ctx.accounts.withdraw_request,
+        &ctx.accounts.user,
+        &ctx.accounts.system_program,
+        ctx.bumps.withdraw_request,
+        WithdrawRequest {
+            user: ctx.accounts.user.key(),
+            collateral_mint: ctx.accounts.collateral_mint.key(),
+            amount,
+            requested_at: now,
+            claimable_at,
+        },
+    )?;
+
+    token_2022::burn_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022::BurnChecked {
+                mint: ctx.accounts.xusdc_mint.to_account_info(),
+                authority: ctx.accounts.transfer_authority.to_account_info(),
+                from: ctx.accounts.user_xusdc_ata.to_account_info(),
+            },
+            &[&[TRANSFER_AUTHORITY_SEED, &[ctx.bumps.transfer_authority]]],
+        ),
+        amount,
+        ctx.accounts.xusdc_mint.decimals,
+    )?;
+
+    emit_cpi!(WithdrawRequested {
+        user: ctx.accounts.user.key(),
+        collateral_mint: ctx.accounts.collateral_mint.key(),
+        amount,
+        claimable_at,
+    });
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::{self, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::events::Seized;
+use crate::ixs::set_frozen::set_frozen;
+use crate::state::{
+    BlockedAccount, ADMIN_KEY, BLOCKED_ACCOUNT_SEED, TRANSFER_AUTHORITY_SEED, XUSDC_MINT_KEY,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct Seize<'info> {
+    #[account(address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    /// Only blocked wallets can have their xUSDC seized
+    #[account(seeds = [BLOCKED_ACCOUNT_SEED, wallet.as_ref()], bump)]
+    pub blocked_account: Account<'info, BlockedAccount>,
+    #[account(
+        mut,
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = xusdc_mint,
+        token::authority = wallet,
+        token::token_program = token_program.key()
+    )]
+    pub from_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = xusdc_mint,
+        token::token_program = token_program.key()
+    )]
+    pub to_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that is the mint's permanent delegate, mint and freeze authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: AccountInfo<'info>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+/// Move `amount` of a blocked wallet's xUSDC to `to_token_account`.
+///
+/// The permanent delegate burns it and the mint authority mints it again, rather than
+/// transferring it, so neither the supply nor the backing changes and a transfer hook
+/// blocking the wallet can't refuse it. A frozen account is thawed for the burn and
+/// frozen again.
+pub fn handler(ctx: Context<Seize>, wallet: Pubkey, amount: u64) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let bump = ctx.bumps.transfer_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[TRANSFER_AUTHORITY_SEED, &[bump]]];
+    let frozen = accounts.from_token_account.is_frozen();
+
+    if frozen {
+        set_frozen(
+            &accounts.token_program,
+            &accounts.from_token_account,
+            &accounts.xusdc_mint,
+            &accounts.transfer_authority,
+            bump,
+            false,
+        )?;
+    }
+    token_2022::burn_checked(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            token_2022::BurnChecked {
+                mint: accounts.xusdc_mint.to_account_info(),
+                from: accounts.from_token_account.to_account_info(),
+                authority: accounts.transfer_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        accounts.xusdc_mint.decimals,
+    )?;
+    if frozen {
+        set_frozen(
+            &accounts.token_program,
+            &accounts.from_token_account,
+            &accounts.xusdc_mint,
+            &accounts.transfer_authority,
+            bump,
+            true,
+        )?;
+    }
+    token_2022::mint_to_checked(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            token_2022::MintToChecked {
+                mint: accounts.xusdc_mint.to_account_info(),
+                to: accounts.to_token_account.to_account_info(),
+                authority: accounts.transfer_authority.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        accounts.xusdc_mint.decimals,
+    )?;
+
+    emit_cpi!(Seized {
+        wallet,
+        from_token_account: accounts.from_token_account.key(),
+        to_token_account: accounts.to_token_account.key(),
+        amount,
+        authority: accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
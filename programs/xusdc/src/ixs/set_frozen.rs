@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::token_2022::{self, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::events::FreezeUpdated;
+use crate::state::{ADMIN_KEY, TRANSFER_AUTHORITY_SEED, XUSDC_MINT_KEY};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct SetFrozen<'info> {
+    #[account(address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(
+        mint::token_program = token_program.key(),
+        address = XUSDC_MINT_KEY
+    )]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = xusdc_mint,
+        token::authority = wallet,
+        token::token_program = token_program.key()
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA used as the mint's freeze authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: AccountInfo<'info>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+/// Freeze or thaw one of `wallet`'s xUSDC accounts. A frozen account can't send or
+/// receive xUSDC, through the program or directly.
+pub fn handler(ctx: Context<SetFrozen>, wallet: Pubkey, frozen: bool) -> Result<()> {
+    set_frozen(
+        &ctx.accounts.token_program,
+        &ctx.accounts.token_account,
+        &ctx.accounts.xusdc_mint,
+        &ctx.accounts.transfer_authority,
+        ctx.bumps.transfer_authority,
+        frozen,
+    )?;
+
+    emit_cpi!(FreezeUpdated {
+        wallet,
+        token_account: ctx.accounts.token_account.key(),
+        frozen,
+    });
+    Ok(())
+}
+
+/// Freeze or thaw `token_account` with the transfer authority as freeze authority
+pub(crate) fn set_frozen<'info>(
+    token_program: &Program<'info, Token2022>,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    xusdc_mint: &InterfaceAccount<'info, Mint>,
+    transfer_authority: &AccountInfo<'info>,
+    bump: u8,
+    frozen: bool,
+) -> Result<()> {
+    // Mints created before `set_frozen` existed have no freeze authority, and Token-2022
+    // can't add one afterwards
+    require!(
+        xusdc_mint.freeze_authority == COption::Some(transfer_authority.key()),
+        ErrorCode::MintNotFreezable
+    );
+    let signer_seeds: &[&[&[u8]]] = &[&[TRANSFER_AUTHORITY_SEED, &[bump]]];
+    if frozen {
+        token_2022::freeze_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_2022::FreezeAccount {
+                account: token_account.to_account_info(),
+                mint: xusdc_mint.to_account_info(),
+                authority: transfer_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    } else {
+        token_2022::thaw_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_2022::ThawAccount {
+                account: token_account.to_account_info(),
+                mint: xusdc_mint.to_account_info(),
+                authority: transfer_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+}
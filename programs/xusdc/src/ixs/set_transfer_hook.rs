@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::{
+        spl_token_2022::{
+            extension::{
+                transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions,
+            },
+            state::Mint as MintState,
+        },
+        Token2022,
+    },
+    token_2022_extensions,
+};
+
+use crate::error::ErrorCode;
+use crate::events::TransferHookUpdated;
+use crate::state::{ADMIN_KEY, TRANSFER_AUTHORITY_SEED, XUSDC_MINT_KEY};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetTransferHook<'info> {
+    #[account(address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    /// CHECK: The xUSDC mint, whose transfer hook extension is updated
+    #[account(mut, address = XUSDC_MINT_KEY)]
+    pub xusdc_mint: UncheckedAccount<'info>,
+    /// CHECK: The transfer hook's authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+pub fn handler(ctx: Context<SetTransferHook>, program_id: Option<Pubkey>) -> Result<()> {
+    // Mints created before `set_transfer_hook` existed don't have the extension, and
+    // Token-2022 can't add it to an existing mint
+    {
+        let data = ctx.accounts.xusdc_mint.try_borrow_data()?;
+        let state = StateWithExtensions::<MintState>::unpack(&data)?;
+        require!(
+            state.get_extension::<TransferHook>().is_ok(),
+            ErrorCode::MintMissingExtension
+        );
+    }
+
+    // Once set, every transfer needs the hook's extra accounts, `settle_payment` included
+    let bump = ctx.bumps.transfer_authority;
+    token_2022_extensions::transfer_hook_update(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022_extensions::TransferHookUpdate {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                mint: ctx.accounts.xusdc_mint.to_account_info(),
+                authority: ctx.accounts.transfer_authority.to_account_info(),
+            },
+            &[&[TRANSFER_AUTHORITY_SEED, &[bump]]],
+        ),
+        program_id,
+    )?;
+
+    emit_cpi!(TransferHookUpdated { program_id });
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::WithdrawLimitsUpdated;
+use crate::state::{WithdrawLimits, ADMIN_KEY, WITHDRAW_LIMITS_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetWithdrawLimits<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    /// Created here for deployments initialized before withdrawals were limited
+    #[account(
+        init_if_needed,
+        seeds = [WITHDRAW_LIMITS_SEED],
+        bump,
+        payer = authority,
+        space = 8 + std::mem::size_of::<WithdrawLimits>(),
+    )]
+    pub withdraw_limits: Account<'info, WithdrawLimits>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<SetWithdrawLimits>,
+    window_seconds: i64,
+    global_limit: u64,
+    wallet_limit: u64,
+    queue_delay_seconds: i64,
+) -> Result<()> {
+    require!(
+        window_seconds > 0 && queue_delay_seconds >= 0,
+        ErrorCode::InvalidWithdrawLimits
+    );
+    // Without a delay, `request_withdraw` would pay out straight away past the limits
+    require!(
+        queue_delay_seconds > 0 || (global_limit == u64::MAX && wallet_limit == u64::MAX),
+        ErrorCode::InvalidWithdrawLimits
+    );
+
+    // The current window keeps its outflow, so tightening a limit applies immediately
+    let limits = &mut ctx.accounts.withdraw_limits;
+    if limits.window_seconds == 0 {
+        // Just created, so the first window starts now
+        limits.window_start = Clock::get()?.unix_timestamp;
+    }
+    limits.window_seconds = window_seconds;
+    limits.global_limit = global_limit;
+    limits.wallet_limit = wallet_limit;
+    limits.queue_delay_seconds = queue_delay_seconds;
+
+    emit_cpi!(WithdrawLimitsUpdated {
+        window_seconds,
+        global_limit,
+        wallet_limit,
+        queue_delay_seconds,
+    });
+    Ok(())
+}
@@ -1,11 +1,16 @@
 use anchor_lang::prelude::*;
 use brine_ed25519::sig_verify;
 
-use anchor_spl::token_2022::Token2022;
-use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked};
+use anchor_spl::token_2022::{spl_token_2022, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
 
-use crate::state::{NonceAccount, NONCE_SEED, RENT_POOL_SEED, TRANSFER_AUTHORITY_SEED};
+use crate::events::PaymentSettled;
+use crate::state::{
+    NonceAccount, BLOCKED_ACCOUNT_SEED, NONCE_SEED, NONCE_VERSION, RENT_POOL_SEED,
+    TRANSFER_AUTHORITY_SEED,
+};
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(payload: SettlePayload)]
 pub struct SettlePayment<'info> {
@@ -43,25 +48,44 @@ pub struct SettlePayment<'info> {
     pub rent_pool: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: The payer's blocklist entry, which must not exist
+    #[account(seeds = [BLOCKED_ACCOUNT_SEED, payload.payment_auth.from.as_ref()], bump)]
+    pub from_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: The payee's blocklist entry, which must not exist
+    #[account(seeds = [BLOCKED_ACCOUNT_SEED, payload.payment_auth.to.as_ref()], bump)]
+    pub to_blocked: UncheckedAccount<'info>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+/// Longest `PaymentAuthorization::memo` in bytes
+pub const MAX_MEMO_LEN: usize = 128;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
 pub struct PaymentAuthorization {
     pub from: Pubkey,
     pub to: Pubkey,
     pub amount: u64,
     pub nonce: [u8; 32],
     pub valid_until: i64,
+    /// Hash of the HTTP request paid for: its method, URL and body digest. All zeros
+    /// when the payment isn't for a request.
+    pub resource_hash: [u8; 32],
+    /// Free text from the payer, e.g. an order number
+    pub memo: Option<String>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
 pub struct SettlePayload {
     pub payment_auth: PaymentAuthorization,
     pub signature: [u8; 64],
     pub signer_pubkey: [u8; 32],
 }
 
-pub fn settle_payment(ctx: Context<SettlePayment>, payload: SettlePayload) -> Result<()> {
+pub fn settle_payment<'info>(
+    ctx: Context<'_, '_, '_, 'info, SettlePayment<'info>>,
+    payload: SettlePayload,
+) -> Result<()> {
     let payment_auth = &payload.payment_auth;
 
     require!(
@@ -80,6 +104,11 @@ pub fn settle_payment(ctx: Context<SettlePayment>, payload: SettlePayload) -> Re
         ErrorCode::InvalidPaymentAuthorization
     );
 
+    require!(
+        ctx.accounts.from_blocked.data_is_empty() && ctx.accounts.to_blocked.data_is_empty(),
+        ErrorCode::AccountBlocked
+    );
+
     // Check payment hasn't expired
     let current_timestamp = Clock::get()?.unix_timestamp;
     require!(
@@ -89,19 +118,21 @@ pub fn settle_payment(ctx: Context<SettlePayment>, payload: SettlePayload) -> Re
 
     // Serialize the payment authorization for signature verification
     let message = payment_auth.try_to_vec()?;
+    verify_signature(
+        &payload.signer_pubkey,
+        &payload.signature,
+        &message,
+        &payment_auth.from,
+    )?;
 
-    msg!("Verifying signature");
-
-    // Verify the ed25519 signature
-    sig_verify(&payload.signer_pubkey, &payload.signature, &message)
-        .map_err(|_| error!(ErrorCode::InvalidSignature))?;
-    msg!("Verified signature");
-
-    // Verify the signer is the from account
-    let signer_pubkey = Pubkey::from(payload.signer_pubkey);
+    require!(payment_auth.amount > 0, ErrorCode::InvalidAmount);
     require!(
-        signer_pubkey == payment_auth.from,
-        ErrorCode::UnauthorizedSigner
+        payment_auth.memo.as_ref().map_or(0, String::len) <= MAX_MEMO_LEN,
+        ErrorCode::MemoTooLong
+    );
+    require!(
+        ctx.accounts.from_user_xusdc_ata.amount >= payment_auth.amount,
+        ErrorCode::InsufficientFunds
     );
 
     // Transfer xUSDC using permanent delegate authority
@@ -109,55 +140,102 @@ pub fn settle_payment(ctx: Context<SettlePayment>, payload: SettlePayload) -> Re
     let transfer_authority_seeds = [TRANSFER_AUTHORITY_SEED, &[transfer_authority_bump]];
     let signer_seeds = &[&transfer_authority_seeds[..]];
 
-    transfer_checked(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            TransferChecked {
-                from: ctx.accounts.from_user_xusdc_ata.to_account_info(),
-                to: ctx.accounts.to_user_xusdc_ata.to_account_info(),
-                mint: ctx.accounts.xusdc_mint.to_account_info(),
-                authority: ctx.accounts.transfer_authority.to_account_info(),
-            },
-            signer_seeds,
-        ),
+    // With a transfer hook enabled, the remaining accounts carry its extra accounts
+    spl_token_2022::onchain::invoke_transfer_checked(
+        &ctx.accounts.token_program.key(),
+        ctx.accounts.from_user_xusdc_ata.to_account_info(),
+        ctx.accounts.xusdc_mint.to_account_info(),
+        ctx.accounts.to_user_xusdc_ata.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.remaining_accounts,
         payment_auth.amount,
         ctx.accounts.xusdc_mint.decimals,
+        signer_seeds,
+    )?;
+
+    // Record the payment against its nonce, for `refund_payment` and `get_payment`
+    create_nonce_account(
+        &ctx.accounts.system_program,
+        &ctx.accounts.rent_pool,
+        ctx.bumps.rent_pool,
+        &ctx.accounts.nonce_account,
+        &payment_auth.nonce,
+        ctx.bumps.nonce_account,
+        &NonceAccount {
+            expires_at: payment_auth.valid_until,
+            version: NONCE_VERSION,
+            from: payment_auth.from,
+            to: payment_auth.to,
+            amount: payment_auth.amount,
+            refunded: 0,
+            facilitator: ctx.accounts.facilitator.key(),
+            settled_at: current_timestamp,
+        },
     )?;
 
-    // The nonce account was created and funded by the global rent pool
-    let rent_bump = ctx.bumps.rent_pool;
-    let nonce_bump = ctx.bumps.nonce_account;
-    let space = 8 + std::mem::size_of::<NonceAccount>();
+    emit_cpi!(PaymentSettled {
+        from: payment_auth.from,
+        to: payment_auth.to,
+        amount: payment_auth.amount,
+        nonce: payment_auth.nonce,
+        facilitator: ctx.accounts.facilitator.key(),
+        valid_until: payment_auth.valid_until,
+        resource_hash: payment_auth.resource_hash,
+        memo: payment_auth.memo.clone(),
+    });
+    Ok(())
+}
+
+/// Check `signature` is `from`'s Ed25519 signature of `message`
+pub(crate) fn verify_signature(
+    signer_pubkey: &[u8; 32],
+    signature: &[u8; 64],
+    message: &[u8],
+    from: &Pubkey,
+) -> Result<()> {
+    msg!("Verifying signature");
+
+    // Verify the ed25519 signature
+    sig_verify(signer_pubkey, signature, message)
+        .map_err(|_| error!(ErrorCode::InvalidSignature))?;
+    msg!("Verified signature");
+
+    // Verify the signer is the from account
+    require!(
+        Pubkey::from(*signer_pubkey) == *from,
+        ErrorCode::UnauthorizedSigner
+    );
+    Ok(())
+}
+
+/// Create a payment's nonce account, funded by the global rent pool, holding `record`
+pub(crate) fn create_nonce_account<'info>(
+    system_program: &Program<'info, System>,
+    rent_pool: &AccountInfo<'info>,
+    rent_bump: u8,
+    nonce_account: &AccountInfo<'info>,
+    nonce: &[u8; 32],
+    nonce_bump: u8,
+    record: &NonceAccount,
+) -> Result<()> {
+    let space = 8 + NonceAccount::INIT_SPACE;
     anchor_lang::system_program::create_account(
         CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
+            system_program.to_account_info(),
             anchor_lang::system_program::CreateAccount {
-                from: ctx.accounts.rent_pool.to_account_info(),
-                to: ctx.accounts.nonce_account.to_account_info(),
+                from: rent_pool.clone(),
+                to: nonce_account.clone(),
             },
             &[
                 &[RENT_POOL_SEED, &[rent_bump]],
-                &[NONCE_SEED, &payload.payment_auth.nonce, &[nonce_bump]],
+                &[NONCE_SEED, nonce, &[nonce_bump]],
             ],
         ),
         Rent::get()?.minimum_balance(space),
         space as u64,
         &crate::ID,
     )?;
-
-    // Update nonce account with expiry time
-    let mut nonce_data = ctx.accounts.nonce_account.try_borrow_mut_data()?;
-    let mut nonce_account = NonceAccount::DISCRIMINATOR.to_vec();
-    nonce_account.extend_from_slice(
-        &NonceAccount {
-            expires_at: payment_auth.valid_until,
-        }
-        .try_to_vec()?,
-    );
-
-    nonce_data[0..nonce_account.len()].copy_from_slice(&nonce_account);
-
-    Ok(())
+    record.try_serialize(&mut &mut nonce_account.try_borrow_mut_data()?[..])
 }
 
 #[error_code]
@@ -176,4 +254,14 @@ pub enum ErrorCode {
     NonceAlreadyUsed,
     #[msg("Arithmetic overflow")]
     Overflow,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Payment amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Wallet is blocked")]
+    AccountBlocked,
+    #[msg("Splits are empty, too many, or don't match the authorization")]
+    InvalidSplits,
+    #[msg("Memo is too long")]
+    MemoTooLong,
 }
@@ -0,0 +1,249 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use anchor_spl::token_2022::{spl_token_2022, Token2022};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::events::SplitPaymentSettled;
+use crate::ixs::settle_payment::{create_nonce_account, verify_signature, ErrorCode, MAX_MEMO_LEN};
+use crate::state::{
+    NonceAccount, BLOCKED_ACCOUNT_SEED, NONCE_SEED, NONCE_VERSION, RENT_POOL_SEED,
+    TRANSFER_AUTHORITY_SEED,
+};
+
+/// Most recipients one split payment can pay
+pub const MAX_SPLIT_RECIPIENTS: usize = 5;
+
+/// Prefixed to a split authorization's Borsh encoding to form the signed message, so
+/// it can never be mistaken for a `PaymentAuthorization`
+pub const SPLIT_PAYMENT_DOMAIN: &[u8] = b"xusdc:split-payment";
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(payload: SplitSettlePayload)]
+pub struct SettleSplitPayment<'info> {
+    pub facilitator: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+
+    #[account(mint::token_program = token_program.key())]
+    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Its owner is checked against `from` in the payload
+    #[account(mut, token::mint = xusdc_mint, token::token_program = token_program.key())]
+    pub from_user_xusdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA used as transfer authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: AccountInfo<'info>,
+
+    /// CHECK: PDA used as nonce account
+    #[account(
+        mut,
+        seeds = [NONCE_SEED, payload.payment_auth.nonce.as_ref()],
+        bump
+    )]
+    pub nonce_account: AccountInfo<'info>,
+
+    /// CHECK: Global rent pool that funds nonce account creation
+    #[account(mut, seeds = [RENT_POOL_SEED], bump)]
+    pub rent_pool: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The payer's blocklist entry, which must not exist
+    #[account(seeds = [BLOCKED_ACCOUNT_SEED, payload.payment_auth.from.as_ref()], bump)]
+    pub from_blocked: UncheckedAccount<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Split {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// Who a split payment pays
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SplitRecipients {
+    /// The splits themselves
+    List(Vec<Split>),
+    /// `splits_hash` of the splits, which the facilitator reveals when settling
+    Commitment([u8; 32]),
+}
+
+/// What the payer signs for a split payment: `SPLIT_PAYMENT_DOMAIN` followed by this
+/// struct's Borsh encoding
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitPaymentAuthorization {
+    pub from: Pubkey,
+    pub to: SplitRecipients,
+    /// Sum of the split amounts
+    pub amount: u64,
+    pub nonce: [u8; 32],
+    pub valid_until: i64,
+    /// Hash of the HTTP request paid for: its method, URL and body digest. All zeros
+    /// when the payment isn't for a request.
+    pub resource_hash: [u8; 32],
+    /// Free text from the payer, e.g. an order number
+    pub memo: Option<String>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SplitSettlePayload {
+    pub payment_auth: SplitPaymentAuthorization,
+    /// The splits a `Commitment` stands for; empty for a `List`
+    pub revealed: Vec<Split>,
+    pub signature: [u8; 64],
+    pub signer_pubkey: [u8; 32],
+}
+
+/// SHA-256 of the splits' Borsh encoding, as a `Commitment` holds it
+pub fn splits_hash(splits: &[Split]) -> Result<[u8; 32]> {
+    Ok(hash(&splits.try_to_vec()?).to_bytes())
+}
+
+/// Pay each split from one authorization, through the permanent delegate.
+///
+/// The remaining accounts hold, for each split in order, the recipient's xUSDC
+/// account and its blocklist PDA, followed by any transfer hook accounts. The nonce
+/// records the total, and `splits_hash` of the splits paid as its `to`. Nobody can
+/// sign for that, so `refund_payment` doesn't apply.
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, SettleSplitPayment<'info>>,
+    payload: SplitSettlePayload,
+) -> Result<()> {
+    let payment_auth = &payload.payment_auth;
+
+    require!(
+        ctx.accounts.nonce_account.data_is_empty(),
+        ErrorCode::NonceAlreadyUsed
+    );
+    require!(
+        payment_auth.from == ctx.accounts.from_user_xusdc_ata.owner,
+        ErrorCode::InvalidPaymentAuthorization
+    );
+    require!(
+        ctx.accounts.from_blocked.data_is_empty(),
+        ErrorCode::AccountBlocked
+    );
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    require!(
+        current_timestamp <= payment_auth.valid_until,
+        ErrorCode::PaymentExpired
+    );
+
+    let message = [SPLIT_PAYMENT_DOMAIN, &payment_auth.try_to_vec()?].concat();
+    verify_signature(
+        &payload.signer_pubkey,
+        &payload.signature,
+        &message,
+        &payment_auth.from,
+    )?;
+
+    let splits = match &payment_auth.to {
+        SplitRecipients::List(splits) => {
+            require!(payload.revealed.is_empty(), ErrorCode::InvalidSplits);
+            splits
+        }
+        SplitRecipients::Commitment(commitment) => {
+            require!(
+                splits_hash(&payload.revealed)? == *commitment,
+                ErrorCode::InvalidSplits
+            );
+            &payload.revealed
+        }
+    };
+    require!(
+        (1..=MAX_SPLIT_RECIPIENTS).contains(&splits.len()),
+        ErrorCode::InvalidSplits
+    );
+    let mut total: u64 = 0;
+    for split in splits {
+        require!(split.amount > 0, ErrorCode::InvalidAmount);
+        total = total.checked_add(split.amount).ok_or(ErrorCode::Overflow)?;
+    }
+    require!(total == payment_auth.amount, ErrorCode::InvalidSplits);
+    require!(
+        payment_auth.memo.as_ref().map_or(0, String::len) <= MAX_MEMO_LEN,
+        ErrorCode::MemoTooLong
+    );
+    require!(
+        ctx.accounts.from_user_xusdc_ata.amount >= total,
+        ErrorCode::InsufficientFunds
+    );
+
+    require!(
+        ctx.remaining_accounts.len() >= 2 * splits.len(),
+        ErrorCode::InvalidPaymentAuthorization
+    );
+    let (recipient_accounts, hook_accounts) = ctx.remaining_accounts.split_at(2 * splits.len());
+    let transfer_authority_seeds = [TRANSFER_AUTHORITY_SEED, &[ctx.bumps.transfer_authority]];
+    for (split, accounts) in splits.iter().zip(recipient_accounts.chunks(2)) {
+        let (to_ata, to_blocked) = (&accounts[0], &accounts[1]);
+        require_keys_eq!(
+            *to_ata.owner,
+            ctx.accounts.token_program.key(),
+            ErrorCode::InvalidPaymentAuthorization
+        );
+        let token_account = TokenAccount::try_deserialize(&mut &to_ata.try_borrow_data()?[..])?;
+        require!(
+            token_account.owner == split.recipient
+                && token_account.mint == ctx.accounts.xusdc_mint.key(),
+            ErrorCode::InvalidPaymentAuthorization
+        );
+        let blocked_key = Pubkey::find_program_address(
+            &[BLOCKED_ACCOUNT_SEED, split.recipient.as_ref()],
+            &crate::ID,
+        )
+        .0;
+        require!(
+            to_blocked.key() == blocked_key,
+            ErrorCode::InvalidPaymentAuthorization
+        );
+        require!(to_blocked.data_is_empty(), ErrorCode::AccountBlocked);
+
+        spl_token_2022::onchain::invoke_transfer_checked(
+            &ctx.accounts.token_program.key(),
+            ctx.accounts.from_user_xusdc_ata.to_account_info(),
+            ctx.accounts.xusdc_mint.to_account_info(),
+            to_ata.clone(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            hook_accounts,
+            split.amount,
+            ctx.accounts.xusdc_mint.decimals,
+            &[&transfer_authority_seeds[..]],
+        )?;
+    }
+
+    create_nonce_account(
+        &ctx.accounts.system_program,
+        &ctx.accounts.rent_pool,
+        ctx.bumps.rent_pool,
+        &ctx.accounts.nonce_account,
+        &payment_auth.nonce,
+        ctx.bumps.nonce_account,
+        &NonceAccount {
+            expires_at: payment_auth.valid_until,
+            version: NONCE_VERSION,
+            from: payment_auth.from,
+            to: Pubkey::new_from_array(splits_hash(splits)?),
+            amount: total,
+            refunded: 0,
+            facilitator: ctx.accounts.facilitator.key(),
+            settled_at: current_timestamp,
+        },
+    )?;
+
+    emit_cpi!(SplitPaymentSettled {
+        from: payment_auth.from,
+        splits: splits.clone(),
+        amount: total,
+        nonce: payment_auth.nonce,
+        facilitator: ctx.accounts.facilitator.key(),
+        valid_until: payment_auth.valid_until,
+        resource_hash: payment_auth.resource_hash,
+        memo: payment_auth.memo.clone(),
+    });
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::events::AccountUnblocked;
+use crate::state::{BlockedAccount, ADMIN_KEY, BLOCKED_ACCOUNT_SEED};
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct UnblockAccount<'info> {
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [BLOCKED_ACCOUNT_SEED, wallet.as_ref()],
+        bump,
+    )]
+    pub blocked_account: Account<'info, BlockedAccount>,
+}
+
+/// Lift the block. A wallet frozen with `set_frozen` stays frozen until it is thawed.
+pub fn handler(ctx: Context<UnblockAccount>, wallet: Pubkey) -> Result<()> {
+    emit_cpi!(AccountUnblocked { wallet });
+    Ok(())
+}
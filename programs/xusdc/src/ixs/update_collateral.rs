@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::CollateralUpdated;
+use crate::state::{CollateralRegistry, ADMIN_KEY, COLLATERAL_REGISTRY_SEED, MAX_WITHDRAW_FEE_BPS};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateCollateral<'info> {
+    #[account(address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+    /// CHECK: Only used to look up the registry entry
+    pub collateral_mint: UncheckedAccount<'info>,
+}
+
+pub fn handler(
+    ctx: Context<UpdateCollateral>,
+    deposit_cap: u64,
+    withdraw_fee_bps: u16,
+) -> Result<()> {
+    require!(
+        withdraw_fee_bps <= MAX_WITHDRAW_FEE_BPS,
+        ErrorCode::InvalidWithdrawFee
+    );
+    let collateral = ctx
+        .accounts
+        .collateral_registry
+        .get_mut(&ctx.accounts.collateral_mint.key())
+        .ok_or(ErrorCode::UnsupportedCollateral)?;
+
+    // Lowering the cap below the vault balance only blocks new deposits
+    collateral.deposit_cap = deposit_cap;
+    collateral.withdraw_fee_bps = withdraw_fee_bps;
+
+    emit_cpi!(CollateralUpdated {
+        mint: collateral.mint,
+        deposit_cap,
+        withdraw_fee_bps,
+    });
+    Ok(())
+}
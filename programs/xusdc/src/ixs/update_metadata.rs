@@ -0,0 +1,81 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::{
+    token_2022::{
+        spl_token_2022::{
+            extension::{BaseStateWithExtensions, StateWithExtensions},
+            state::Mint as MintState,
+        },
+        Token2022,
+    },
+    token_2022_extensions::{self, spl_token_metadata_interface::state::TokenMetadata},
+};
+
+use crate::error::ErrorCode;
+use crate::events::MetadataUpdated;
+use crate::state::{MetadataField, ADMIN_KEY, TRANSFER_AUTHORITY_SEED, XUSDC_MINT_KEY};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    /// Pays for the mint to grow with a longer value
+    #[account(mut, address = ADMIN_KEY)]
+    pub authority: Signer<'info>,
+    /// CHECK: The xUSDC mint, which holds its own metadata
+    #[account(mut, address = XUSDC_MINT_KEY)]
+    pub xusdc_mint: UncheckedAccount<'info>,
+    /// CHECK: The metadata's update authority
+    #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
+    pub transfer_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<UpdateMetadata>, field: MetadataField, value: String) -> Result<()> {
+    // Token-2022 resizes the mint to fit the new value but leaves its rent to us
+    let mint = ctx.accounts.xusdc_mint.to_account_info();
+    let new_len = {
+        let data = mint.try_borrow_data()?;
+        let state = StateWithExtensions::<MintState>::unpack(&data)?;
+        // Mints created before `update_metadata` existed carry no metadata, and Token-2022
+        // can't add the metadata pointer it needs to an existing mint
+        let mut metadata = state
+            .get_variable_len_extension::<TokenMetadata>()
+            .map_err(|_| ErrorCode::MintMissingExtension)?;
+        let old_size = metadata.tlv_size_of()?;
+        metadata.update(field.into(), value.clone());
+        data.len() - old_size + metadata.tlv_size_of()?
+    };
+    let shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(mint.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: mint.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+
+    let bump = ctx.bumps.transfer_authority;
+    token_2022_extensions::token_metadata_update_field(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022_extensions::TokenMetadataUpdateField {
+                program_id: ctx.accounts.token_program.to_account_info(),
+                metadata: mint,
+                update_authority: ctx.accounts.transfer_authority.to_account_info(),
+            },
+            &[&[TRANSFER_AUTHORITY_SEED, &[bump]]],
+        ),
+        field.into(),
+        value.clone(),
+    )?;
+
+    emit_cpi!(MetadataUpdated { field, value });
+    Ok(())
+}
@@ -1,11 +1,18 @@
 use anchor_lang::accounts::interface_account::InterfaceAccount;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token};
 use anchor_spl::token_2022::{self, Token2022};
-use anchor_spl::token_interface::{Mint, TokenAccount};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
 
-use crate::state::{TRANSFER_AUTHORITY_SEED, USDC_MINT_KEY, XUSDC_MINT_KEY};
+use crate::error::ErrorCode;
+use crate::events::{WithdrawRequested, Withdrawn};
+use crate::state::{
+    CollateralInfo, CollateralRegistry, WalletOutflow, WithdrawLimits, WithdrawRequest,
+    BLOCKED_ACCOUNT_SEED, COLLATERAL_REGISTRY_SEED, TRANSFER_AUTHORITY_SEED, WALLET_OUTFLOW_SEED,
+    WITHDRAW_LIMITS_SEED, WITHDRAW_REQUEST_SEED, XUSDC_MINT_KEY,
+};
 
+// Token accounts are boxed to keep `try_accounts` within the SBF stack frame
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
@@ -17,62 +24,129 @@ pub struct Withdraw<'info> {
         token::authority=user,
         token::token_program=token_program.key()
     )]
-    pub user_xusdc_ata: InterfaceAccount<'info, TokenAccount>,
+    pub user_xusdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
-        token::mint=usdc_mint,
+        token::mint=collateral_mint,
         token::authority=user,
-        token::token_program=tokenkeg.key()
+        token::token_program=collateral_token_program.key()
     )]
-    pub user_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+    pub user_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// The collateral vault: the ATA of `transfer_authority`
     #[account(
         mut,
-        token::mint=usdc_mint,
-        token::token_program=tokenkeg.key()
+        associated_token::mint = collateral_mint,
+        associated_token::authority = transfer_authority,
+        associated_token::token_program = collateral_token_program,
     )]
-    pub global_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+    pub collateral_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(
-        mint::token_program = tokenkeg.key(),
-        address = USDC_MINT_KEY
-    )]
-    pub usdc_mint: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = collateral_token_program.key())]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
         mint::token_program = token_program.key(),
         address = XUSDC_MINT_KEY
     )]
-    pub xusdc_mint: InterfaceAccount<'info, Mint>,
+    pub xusdc_mint: Box<InterfaceAccount<'info, Mint>>,
 
     /// CHECK: PDA used as transfer authority
     #[account(seeds = [TRANSFER_AUTHORITY_SEED], bump)]
     pub transfer_authority: AccountInfo<'info>,
 
-    pub tokenkeg: Program<'info, Token>,
+    pub collateral_token_program: Interface<'info, TokenInterface>,
     pub token_program: Program<'info, Token2022>,
+
+    #[account(mut, seeds = [COLLATERAL_REGISTRY_SEED], bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    #[account(mut, seeds = [WITHDRAW_LIMITS_SEED], bump)]
+    pub withdraw_limits: Account<'info, WithdrawLimits>,
+
+    #[account(
+        init_if_needed,
+        seeds = [WALLET_OUTFLOW_SEED, &user.key().to_bytes()],
+        bump,
+        payer = user,
+        space = 8 + std::mem::size_of::<WalletOutflow>(),
+    )]
+    pub wallet_outflow: Account<'info, WalletOutflow>,
+
+    /// CHECK: PDA only created when the withdrawal is over the limits
+    #[account(
+        mut,
+        seeds = [WITHDRAW_REQUEST_SEED, &user.key().to_bytes()],
+        bump
+    )]
+    pub withdraw_request: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The user's blocklist entry, which must not exist
+    #[account(
+        seeds = [BLOCKED_ACCOUNT_SEED, user.key().as_ref()],
+        bump,
+        constraint = user_blocked.data_is_empty() @ ErrorCode::AccountBlocked,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
 }
 
-/// Exchange xUSDC for USDC
+/// Exchange xUSDC for one of the registered collaterals, minus its withdraw fee.
+///
+/// Withdrawals over the global or per-wallet limits still burn the xUSDC, but the
+/// collateral is only released by `claim_withdraw` after the queue delay.
 pub fn handler(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
     let bump = ctx.bumps.transfer_authority;
+    let now = Clock::get()?.unix_timestamp;
+    let user = ctx.accounts.user.key();
 
-    token::transfer_checked(
-        CpiContext::new_with_signer(
-            ctx.accounts.tokenkeg.to_account_info(),
-            token::TransferChecked {
-                from: ctx.accounts.global_usdc_ata.to_account_info(),
-                to: ctx.accounts.user_usdc_ata.to_account_info(),
-                mint: ctx.accounts.usdc_mint.to_account_info(),
-                authority: ctx.accounts.transfer_authority.to_account_info(),
+    let limits = &mut ctx.accounts.withdraw_limits;
+    let wallet_outflow = &mut ctx.accounts.wallet_outflow;
+    wallet_outflow.user = user;
+    limits.roll_window(now);
+    wallet_outflow.roll_window(now, limits.window_seconds);
+    let claimable_at = now.saturating_add(limits.queue_delay_seconds);
+
+    // `None` when the withdrawal was queued instead of paid out
+    let fee = if limits.allows(wallet_outflow, amount) {
+        limits.window_outflow += amount;
+        wallet_outflow.outflow += amount;
+        Some(release_collateral(
+            &mut ctx.accounts.collateral_registry,
+            &ctx.accounts.collateral_mint,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.user_collateral_ata,
+            &ctx.accounts.collateral_token_program,
+            &ctx.accounts.transfer_authority,
+            bump,
+            amount,
+        )?)
+    } else {
+        registered_collateral(
+            &ctx.accounts.collateral_registry,
+            &ctx.accounts.collateral_mint,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.collateral_token_program,
+            amount,
+        )?;
+        open_withdraw_request(
+            &ctx.accounts.withdraw_request,
+            &ctx.accounts.user,
+            &ctx.accounts.system_program,
+            ctx.bumps.withdraw_request,
+            WithdrawRequest {
+                user,
+                collateral_mint: ctx.accounts.collateral_mint.key(),
+                amount,
+                requested_at: now,
+                claimable_at,
             },
-            &[&[TRANSFER_AUTHORITY_SEED, &[bump]]],
-        ),
-        amount,
-        ctx.accounts.usdc_mint.decimals,
-    )?;
+        )?;
+        None
+    };
 
     token_2022::burn_checked(
         CpiContext::new_with_signer(
@@ -88,5 +162,139 @@ pub fn handler(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
         ctx.accounts.xusdc_mint.decimals,
     )?;
 
+    ctx.accounts.collateral_vault.reload()?;
+    ctx.accounts.xusdc_mint.reload()?;
+    let registry = &ctx.accounts.collateral_registry;
+    registry
+        .get(&ctx.accounts.collateral_mint.key())
+        .ok_or(ErrorCode::UnsupportedCollateral)?
+        .check_backing(ctx.accounts.collateral_vault.amount)?;
+    registry.check_supply(ctx.accounts.xusdc_mint.supply)?;
+
+    let collateral_mint = ctx.accounts.collateral_mint.key();
+    match fee {
+        Some(fee) => emit_cpi!(Withdrawn {
+            user,
+            collateral_mint,
+            amount,
+            fee,
+        }),
+        None => emit_cpi!(WithdrawRequested {
+            user,
+            collateral_mint,
+            amount,
+            claimable_at,
+        }),
+    }
+
+    Ok(())
+}
+
+/// Look up the registry entry for a collateral and check `amount` can be redeemed from it
+pub(crate) fn registered_collateral<'a>(
+    registry: &'a CollateralRegistry,
+    collateral_mint: &InterfaceAccount<Mint>,
+    collateral_vault: &InterfaceAccount<TokenAccount>,
+    collateral_token_program: &Interface<TokenInterface>,
+    amount: u64,
+) -> Result<&'a CollateralInfo> {
+    let collateral = registry
+        .get(&collateral_mint.key())
+        .ok_or(ErrorCode::UnsupportedCollateral)?;
+    require_keys_eq!(
+        collateral.vault,
+        collateral_vault.key(),
+        ErrorCode::InvalidCollateralVault
+    );
+    require_keys_eq!(
+        collateral.token_program,
+        collateral_token_program.key(),
+        ErrorCode::InvalidCollateralVault
+    );
+    require!(
+        amount <= collateral.outstanding,
+        ErrorCode::InsufficientCollateral
+    );
+    Ok(collateral)
+}
+
+/// Pay out redeemed xUSDC from the vault, returning the fee. The fee stays in the vault,
+/// so it remains backing for the rest of the supply.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn release_collateral<'info>(
+    registry: &mut CollateralRegistry,
+    collateral_mint: &InterfaceAccount<'info, Mint>,
+    collateral_vault: &InterfaceAccount<'info, TokenAccount>,
+    user_collateral_ata: &InterfaceAccount<'info, TokenAccount>,
+    collateral_token_program: &Interface<'info, TokenInterface>,
+    transfer_authority: &AccountInfo<'info>,
+    transfer_authority_bump: u8,
+    amount: u64,
+) -> Result<u64> {
+    registered_collateral(
+        registry,
+        collateral_mint,
+        collateral_vault,
+        collateral_token_program,
+        amount,
+    )?;
+    let collateral = registry
+        .get_mut(&collateral_mint.key())
+        .ok_or(ErrorCode::UnsupportedCollateral)?;
+
+    let fee = collateral.withdraw_fee(amount);
+    collateral.outstanding -= amount;
+    collateral.fees_accrued = collateral
+        .fees_accrued
+        .checked_add(fee)
+        .ok_or(ErrorCode::Overflow)?;
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            collateral_token_program.to_account_info(),
+            token_interface::TransferChecked {
+                from: collateral_vault.to_account_info(),
+                to: user_collateral_ata.to_account_info(),
+                mint: collateral_mint.to_account_info(),
+                authority: transfer_authority.to_account_info(),
+            },
+            &[&[TRANSFER_AUTHORITY_SEED, &[transfer_authority_bump]]],
+        ),
+        amount - fee,
+        collateral_mint.decimals,
+    )?;
+    Ok(fee)
+}
+
+/// Create the user's `WithdrawRequest` PDA; a user can only have one pending at a time
+pub(crate) fn open_withdraw_request<'info>(
+    withdraw_request: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    bump: u8,
+    request: WithdrawRequest,
+) -> Result<()> {
+    require!(
+        withdraw_request.data_is_empty(),
+        ErrorCode::WithdrawRequestPending
+    );
+
+    let space = 8 + std::mem::size_of::<WithdrawRequest>();
+    anchor_lang::system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::CreateAccount {
+                from: payer.to_account_info(),
+                to: withdraw_request.to_account_info(),
+            },
+            &[&[WITHDRAW_REQUEST_SEED, &request.user.to_bytes(), &[bump]]],
+        ),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        &crate::ID,
+    )?;
+
+    let mut data = withdraw_request.try_borrow_mut_data()?;
+    request.try_serialize(&mut &mut data[..])?;
     Ok(())
 }
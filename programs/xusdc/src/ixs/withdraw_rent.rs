@@ -1,10 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_lang::system_program::{Transfer, transfer};
+use anchor_lang::system_program::{transfer, Transfer};
 
 use crate::error::ErrorCode;
+use crate::events::RentWithdrawn;
 use crate::state::ContributorRentInfo;
 use crate::state::{RENT_CONTRIBUTOR_SEED, RENT_POOL_SEED};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct WithdrawRent<'info> {
     #[account(mut)]
@@ -54,5 +56,9 @@ pub fn handler(ctx: Context<WithdrawRent>, amt: u64) -> Result<()> {
         amt,
     )?;
 
+    emit_cpi!(RentWithdrawn {
+        user: ctx.accounts.user.key(),
+        amount: amt,
+    });
     Ok(())
 }
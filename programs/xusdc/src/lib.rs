@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 
 pub mod error;
+pub mod events;
 pub mod ixs;
 pub mod state;
 
@@ -8,6 +9,7 @@ pub mod state;
 mod tests;
 
 use ixs::*;
+use state::MetadataField;
 
 declare_id!("AZzGDkysPRAZ9cfyRo1w4rHMS51NDDNT9XqHsC1WziLM");
 
@@ -15,8 +17,13 @@ declare_id!("AZzGDkysPRAZ9cfyRo1w4rHMS51NDDNT9XqHsC1WziLM");
 pub mod xusdc {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
-        initialize::handler(ctx)
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        initialize::handler(ctx, name, symbol, uri)
     }
 
     pub fn contribute_rent(ctx: Context<ContributeRent>, amount: u64) -> Result<()> {
@@ -33,10 +40,36 @@ pub mod xusdc {
         garbage_collect::handler(ctx)
     }
 
-    pub fn settle_payment(ctx: Context<SettlePayment>, payload: SettlePayload) -> Result<()> {
+    pub fn migrate_nonce(ctx: Context<MigrateNonce>) -> Result<()> {
+        migrate_nonce::handler(ctx)
+    }
+
+    pub fn get_payment(ctx: Context<GetPayment>, nonce: [u8; 32]) -> Result<Option<state::Nonce>> {
+        get_payment::handler(ctx, nonce)
+    }
+
+    pub fn settle_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettlePayment<'info>>,
+        payload: SettlePayload,
+    ) -> Result<()> {
         settle_payment::settle_payment(ctx, payload)
     }
 
+    pub fn settle_split_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleSplitPayment<'info>>,
+        payload: SplitSettlePayload,
+    ) -> Result<()> {
+        settle_split_payment::handler(ctx, payload)
+    }
+
+    pub fn refund_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundPayment<'info>>,
+        nonce: [u8; 32],
+        amount: u64,
+    ) -> Result<()> {
+        refund_payment::handler(ctx, nonce, amount)
+    }
+
     pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
         deposit::handler(ctx, amount)
     }
@@ -44,4 +77,89 @@ pub mod xusdc {
     pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
         withdraw::handler(ctx, amount)
     }
+
+    pub fn add_collateral(
+        ctx: Context<AddCollateral>,
+        deposit_cap: u64,
+        withdraw_fee_bps: u16,
+    ) -> Result<()> {
+        add_collateral::handler(ctx, deposit_cap, withdraw_fee_bps)
+    }
+
+    pub fn init_collateral_registry(ctx: Context<InitCollateralRegistry>) -> Result<()> {
+        init_collateral_registry::handler(ctx)
+    }
+
+    pub fn update_collateral(
+        ctx: Context<UpdateCollateral>,
+        deposit_cap: u64,
+        withdraw_fee_bps: u16,
+    ) -> Result<()> {
+        update_collateral::handler(ctx, deposit_cap, withdraw_fee_bps)
+    }
+
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        field: MetadataField,
+        value: String,
+    ) -> Result<()> {
+        update_metadata::handler(ctx, field, value)
+    }
+
+    pub fn set_transfer_hook(
+        ctx: Context<SetTransferHook>,
+        program_id: Option<Pubkey>,
+    ) -> Result<()> {
+        set_transfer_hook::handler(ctx, program_id)
+    }
+
+    pub fn attest_reserves<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AttestReserves<'info>>,
+    ) -> Result<()> {
+        attest_reserves::handler(ctx)
+    }
+
+    pub fn set_withdraw_limits(
+        ctx: Context<SetWithdrawLimits>,
+        window_seconds: i64,
+        global_limit: u64,
+        wallet_limit: u64,
+        queue_delay_seconds: i64,
+    ) -> Result<()> {
+        set_withdraw_limits::handler(
+            ctx,
+            window_seconds,
+            global_limit,
+            wallet_limit,
+            queue_delay_seconds,
+        )
+    }
+
+    pub fn claim_withdraw(ctx: Context<ClaimWithdraw>) -> Result<()> {
+        claim_withdraw::handler(ctx)
+    }
+
+    pub fn request_withdraw(ctx: Context<RequestWithdraw>, amount: u64) -> Result<()> {
+        request_withdraw::handler(ctx, amount)
+    }
+
+    pub fn cancel_withdraw_request(ctx: Context<CancelWithdrawRequest>) -> Result<()> {
+        cancel_withdraw_request::handler(ctx)
+    }
+
+    pub fn block_account(ctx: Context<BlockAccount>, wallet: Pubkey) -> Result<()> {
+        block_account::handler(ctx, wallet)
+    }
+
+    pub fn unblock_account(ctx: Context<UnblockAccount>, wallet: Pubkey) -> Result<()> {
+        unblock_account::handler(ctx, wallet)
+    }
+
+    pub fn set_frozen(ctx: Context<SetFrozen>, wallet: Pubkey, frozen: bool) -> Result<()> {
+        set_frozen::handler(ctx, wallet, frozen)
+    }
+
+    pub fn seize(ctx: Context<Seize>, wallet: Pubkey, amount: u64) -> Result<()> {
+        seize::handler(ctx, wallet, amount)
+    }
 }
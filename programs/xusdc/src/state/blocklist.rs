@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+pub const BLOCKED_ACCOUNT_SEED: &[u8] = b"blocked-account";
+
+/// Exists while `wallet` is blocked. Blocked wallets can't settle payments either way,
+/// deposit or withdraw, and their xUSDC can be seized.
+#[account]
+#[derive(InitSpace)]
+pub struct BlockedAccount {
+    pub wallet: Pubkey,
+    pub blocked_at: i64,
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::state::USDC_MINT_KEY;
+
+pub const COLLATERAL_REGISTRY_SEED: &[u8] = b"collateral-registry";
+pub const MAX_COLLATERALS: usize = 4;
+pub const MAX_WITHDRAW_FEE_BPS: u16 = 1_000;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// The set of stablecoins accepted as backing for xUSDC
+#[account]
+#[derive(InitSpace)]
+pub struct CollateralRegistry {
+    #[max_len(MAX_COLLATERALS)]
+    pub collaterals: Vec<CollateralInfo>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct CollateralInfo {
+    pub mint: Pubkey,
+    /// ATA of the transfer authority holding this collateral
+    pub vault: Pubkey,
+    /// Either the legacy Token program or Token-2022
+    pub token_program: Pubkey,
+    /// Maximum vault balance accepted through `deposit`
+    pub deposit_cap: u64,
+    pub withdraw_fee_bps: u16,
+    /// xUSDC minted against this collateral and not yet redeemed from it
+    pub outstanding: u64,
+    /// Withdraw fees kept in the vault on top of `outstanding`
+    pub fees_accrued: u64,
+}
+
+impl CollateralRegistry {
+    pub fn total_outstanding(&self) -> Result<u64> {
+        self.collaterals.iter().try_fold(0u64, |total, c| {
+            total
+                .checked_add(c.outstanding)
+                .ok_or(error!(ErrorCode::Overflow))
+        })
+    }
+
+    /// Every xUSDC in circulation must be accounted to some collateral
+    pub fn check_supply(&self, supply: u64) -> Result<()> {
+        require!(
+            supply <= self.total_outstanding()?,
+            ErrorCode::InsufficientBacking
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<&CollateralInfo> {
+        self.collaterals.iter().find(|c| c.mint == *mint)
+    }
+
+    pub fn get_mut(&mut self, mint: &Pubkey) -> Option<&mut CollateralInfo> {
+        self.collaterals.iter_mut().find(|c| c.mint == *mint)
+    }
+}
+
+impl CollateralInfo {
+    /// USDC as `initialize` registers it: uncapped and without a withdraw fee
+    pub fn usdc(vault: Pubkey, outstanding: u64) -> Self {
+        Self {
+            mint: USDC_MINT_KEY,
+            vault,
+            token_program: anchor_spl::token::ID,
+            deposit_cap: u64::MAX,
+            withdraw_fee_bps: 0,
+            outstanding,
+            fees_accrued: 0,
+        }
+    }
+
+    /// The vault must hold at least what was minted against it
+    pub fn check_backing(&self, vault_amount: u64) -> Result<()> {
+        require!(
+            vault_amount >= self.outstanding,
+            ErrorCode::InsufficientBacking
+        );
+        Ok(())
+    }
+
+    pub fn withdraw_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.withdraw_fee_bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
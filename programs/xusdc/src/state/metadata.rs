@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022_extensions::spl_token_metadata_interface::state::Field;
+
+/// A field of the xUSDC mint's Token-2022 metadata
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Name,
+    Symbol,
+    Uri,
+}
+
+impl From<MetadataField> for Field {
+    fn from(field: MetadataField) -> Self {
+        match field {
+            MetadataField::Name => Field::Name,
+            MetadataField::Symbol => Field::Symbol,
+            MetadataField::Uri => Field::Uri,
+        }
+    }
+}
@@ -1,10 +1,18 @@
 use anchor_lang::prelude::*;
 
+mod blocklist;
+mod collateral;
 mod contributor;
+mod metadata;
 mod nonce;
+mod withdraw_limits;
 
+pub use blocklist::*;
+pub use collateral::*;
 pub use contributor::*;
+pub use metadata::*;
 pub use nonce::*;
+pub use withdraw_limits::*;
 
 // Alias for clarity
 pub type NonceAccount = Nonce;
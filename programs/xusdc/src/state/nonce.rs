@@ -1,6 +1,102 @@
+use std::io::Write;
+
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+/// The layout `settle_payment` writes. `migrate_nonce` brings older accounts up to it.
+pub const NONCE_VERSION: u8 = 1;
 
-#[account]
+/// A used nonce and the payment settled with it.
+///
+/// Accounts from before `version` existed hold only `expires_at`. They are version 0,
+/// told apart by their length.
+///
+/// The account traits `#[account]` would derive are written out below, so that an
+/// `Account<Nonce>` loads either version through [`Nonce::read`]. Only the current layout
+/// can be written back, so an instruction that changes a nonce requires `version` to be
+/// [`NONCE_VERSION`].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace, Debug, PartialEq, Eq)]
 pub struct Nonce {
+    /// Stays first in both versions, so `garbage_collect` can read it from either
     pub expires_at: i64,
+    pub version: u8,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    /// Total returned by `refund_payment`, at most `amount`
+    pub refunded: u64,
+    pub facilitator: Pubkey,
+    pub settled_at: i64,
+}
+
+impl Nonce {
+    const V0_LEN: usize = 8 + 8;
+
+    /// Read only the leading `expires_at`, which both versions have
+    pub fn expires_at(data: &[u8]) -> Result<i64> {
+        let mut fields = data
+            .strip_prefix(Self::DISCRIMINATOR)
+            .ok_or(ErrorCode::AccountDiscriminatorMismatch)?;
+        i64::deserialize(&mut fields).map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    }
+
+    /// Read a nonce of either version. A version 0 nonce reads with every field but
+    /// `expires_at` zeroed.
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let fields = data
+            .strip_prefix(Self::DISCRIMINATOR)
+            .ok_or(ErrorCode::AccountDiscriminatorMismatch)?;
+        Self::read_fields(data.len(), fields)
+    }
+
+    /// The fields after the discriminator of an account `len` bytes long
+    fn read_fields(len: usize, mut fields: &[u8]) -> Result<Self> {
+        let nonce = match len {
+            Self::V0_LEN => i64::deserialize(&mut fields).map(|expires_at| Self {
+                expires_at,
+                version: 0,
+                from: Pubkey::default(),
+                to: Pubkey::default(),
+                amount: 0,
+                refunded: 0,
+                facilitator: Pubkey::default(),
+                settled_at: 0,
+            }),
+            _ => Self::deserialize(&mut fields),
+        };
+        nonce.map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl Discriminator for Nonce {
+    /// `sha256("account:Nonce")[..8]`, as `#[account]` derives it
+    const DISCRIMINATOR: &'static [u8] = &[143, 197, 147, 95, 106, 165, 50, 43];
+}
+
+impl Owner for Nonce {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+impl AccountSerialize for Nonce {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer
+            .write_all(Self::DISCRIMINATOR)
+            .and_then(|()| AnchorSerialize::serialize(self, writer))
+            .map_err(|_| ErrorCode::AccountDidNotSerialize.into())
+    }
+}
+
+impl AccountDeserialize for Nonce {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        Self::read(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let fields = buf
+            .get(Self::DISCRIMINATOR.len()..)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        Self::read_fields(buf.len(), fields)
+    }
 }
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+pub const WITHDRAW_LIMITS_SEED: &[u8] = b"withdraw-limits";
+pub const WALLET_OUTFLOW_SEED: &[u8] = b"wallet-outflow";
+pub const WITHDRAW_REQUEST_SEED: &[u8] = b"withdraw-request";
+
+pub const DEFAULT_WITHDRAW_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+/// Set by `initialize`, so limits set later never take effect without a delay
+pub const DEFAULT_WITHDRAW_QUEUE_DELAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// Caps on how fast collateral can leave the vaults through `withdraw`
+#[account]
+pub struct WithdrawLimits {
+    pub window_seconds: i64,
+    /// Maximum outflow across all wallets per window
+    pub global_limit: u64,
+    /// Maximum outflow of a single wallet per window
+    pub wallet_limit: u64,
+    /// How long withdrawals over the limits wait before they can be claimed. Only zero
+    /// while both limits are unlimited.
+    pub queue_delay_seconds: i64,
+    pub window_start: i64,
+    pub window_outflow: u64,
+}
+
+#[account]
+pub struct WalletOutflow {
+    pub user: Pubkey,
+    pub window_start: i64,
+    pub outflow: u64,
+}
+
+/// A withdrawal whose xUSDC has been burned, paid out by `claim_withdraw`
+#[account]
+pub struct WithdrawRequest {
+    pub user: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub requested_at: i64,
+    pub claimable_at: i64,
+}
+
+impl WithdrawLimits {
+    /// Start a new window once the current one has elapsed
+    pub fn roll_window(&mut self, now: i64) {
+        if now >= self.window_start.saturating_add(self.window_seconds) {
+            self.window_start = now;
+            self.window_outflow = 0;
+        }
+    }
+
+    pub fn allows(&self, wallet: &WalletOutflow, amount: u64) -> bool {
+        self.window_outflow.saturating_add(amount) <= self.global_limit
+            && wallet.outflow.saturating_add(amount) <= self.wallet_limit
+    }
+}
+
+impl WalletOutflow {
+    pub fn roll_window(&mut self, now: i64, window_seconds: i64) {
+        if now >= self.window_start.saturating_add(window_seconds) {
+            self.window_start = now;
+            self.outflow = 0;
+        }
+    }
+}
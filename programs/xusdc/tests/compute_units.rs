@@ -0,0 +1,388 @@
+//! Compute units each instruction consumes, compared with the baseline in
+//! `compute_units.txt`. A run fails if any instruction uses more than
+//! [`THRESHOLD_PERCENT`] over its baseline. Run with `--nocapture` to see the table.
+//!
+//! Run with `XUSDC_CU_BLESS=1` to rewrite the baseline after a deliberate change.
+//! Until then, an instruction missing from the baseline, or a baseline entry no longer
+//! measured, fails the run too.
+//!
+//! Besides every instruction once, it measures the batches a facilitator sends:
+//! `xusdc gc` collecting ten nonces, and as many `settle_payment`s as fit in one
+//! transaction.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use xusdc::state::DEFAULT_WITHDRAW_QUEUE_DELAY_SECONDS;
+use xusdc_client::accounts::{Nonce, ProgramAccount};
+use xusdc_client::auth::DEFAULT_TTL;
+use xusdc_client::instruction::{self, Collateral};
+use xusdc_client::{
+    pda, MetadataField, MintMetadata, Split, ADMIN_KEY, TOKEN_PROGRAM_ID, USDC_MINT_KEY,
+};
+use xusdc_harness::{Env, METADATA_URI};
+
+/// Allowed growth over the baseline
+const THRESHOLD_PERCENT: u64 = 5;
+
+/// Nonces `xusdc gc` collects per transaction
+const GC_BATCH: usize = 10;
+
+/// `settle_payment`s that fit in one transaction
+const SETTLE_BATCH: usize = 2;
+
+const TEN_USDC: u64 = 10_000_000;
+
+fn baseline_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compute_units.txt")
+}
+
+/// `name units` per line, `#` starting a comment
+fn read_baseline() -> BTreeMap<String, u64> {
+    let path = baseline_path();
+    let baseline = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("reading {}: {e}", path.display()));
+    baseline
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, units) = line
+                .split_once(char::is_whitespace)
+                .unwrap_or_else(|| panic!("malformed baseline line {line:?}"));
+            (name.to_string(), units.trim().parse().unwrap())
+        })
+        .collect()
+}
+
+fn write_baseline(measured: &[(&str, u64)]) {
+    let mut baseline = String::from(
+        "# Compute units per instruction, checked by tests/compute_units.rs.\n\
+         # Regenerate with XUSDC_CU_BLESS=1 cargo test -p xusdc --test compute_units\n",
+    );
+    for (name, units) in measured {
+        baseline += &format!("{name} {units}\n");
+    }
+    std::fs::write(baseline_path(), baseline).unwrap();
+}
+
+struct Bench {
+    env: Env,
+    measured: Vec<(&'static str, u64)>,
+}
+
+impl Bench {
+    /// Send `instructions` in one transaction, returning the units it consumed
+    fn send(&mut self, payer: &Pubkey, instructions: &[Instruction]) -> u64 {
+        match self.env.send(payer, instructions) {
+            Ok(meta) => meta.compute_units_consumed,
+            Err(failed) => panic!("{:?}\n{}", failed.err, failed.meta.logs.join("\n")),
+        }
+    }
+
+    /// Send `instructions` in one transaction and record the units it consumed
+    fn run(&mut self, name: &'static str, payer: &Pubkey, instructions: &[Instruction]) {
+        let units = self.send(payer, instructions);
+        self.measured.push((name, units));
+    }
+}
+
+/// Every instruction once, in an order that lets each succeed
+fn measure() -> Vec<(&'static str, u64)> {
+    let mut bench = Bench {
+        env: Env::uninitialized(),
+        measured: Vec::new(),
+    };
+    let usdc = Collateral::USDC;
+
+    bench.run(
+        "initialize",
+        &ADMIN_KEY,
+        &[instruction::initialize(
+            &ADMIN_KEY,
+            &USDC_MINT_KEY,
+            &MintMetadata::xusdc(METADATA_URI),
+        )],
+    );
+    // As deployed before the registry existed
+    bench
+        .env
+        .svm
+        .set_account(pda::collateral_registry().0, Account::default())
+        .unwrap();
+    bench.run(
+        "init_collateral_registry",
+        &ADMIN_KEY,
+        &[instruction::init_collateral_registry(
+            &ADMIN_KEY,
+            &USDC_MINT_KEY,
+        )],
+    );
+    let facilitator = bench.env.facilitator.pubkey();
+    bench.env.svm.airdrop(&facilitator, 1_000_000_000).unwrap();
+    bench.run(
+        "contribute_rent",
+        &facilitator,
+        &[instruction::contribute_rent(&facilitator, 100_000_000)],
+    );
+
+    let alice = bench.env.user();
+    let bob = bench.env.user();
+    let (alice_key, bob_key) = (alice.pubkey(), bob.pubkey());
+    bench.env.mint_usdc(&alice_key, TEN_USDC);
+    bench.run(
+        "deposit",
+        &alice_key,
+        &[instruction::deposit(&alice_key, &usdc, TEN_USDC)],
+    );
+
+    let payloads: Vec<_> = (0..=GC_BATCH)
+        .map(|_| bench.env.sign(&alice, &bob_key, 100_000))
+        .collect();
+    bench.run(
+        "settle_payment",
+        &facilitator,
+        &[instruction::settle_payment(&facilitator, &payloads[0])],
+    );
+    let batch: Vec<_> = (0..SETTLE_BATCH)
+        .map(|_| {
+            let payload = bench.env.sign(&alice, &bob_key, 100_000);
+            instruction::settle_payment(&facilitator, &payload)
+        })
+        .collect();
+    let message = Message::new(&batch, Some(&facilitator));
+    assert!(
+        message.serialize().len() + 1 + 64 <= PACKET_DATA_SIZE,
+        "{SETTLE_BATCH} settlements don't fit in one transaction"
+    );
+    bench.run("settle_payment_batch", &facilitator, &batch);
+    let carol_key = bench.env.user().pubkey();
+    let splits = [(bob_key, 90_000), (carol_key, 10_000)]
+        .map(|(recipient, amount)| Split { recipient, amount });
+    let split_payload = bench.env.sign_split(&alice, &splits, true);
+    bench.run(
+        "settle_split_payment",
+        &facilitator,
+        &[instruction::settle_split_payment(
+            &facilitator,
+            &split_payload,
+        )],
+    );
+    bench.run(
+        "refund_payment",
+        &bob_key,
+        &[instruction::refund_payment(
+            &bob_key,
+            &alice_key,
+            &payloads[0].payment_auth.nonce,
+            50_000,
+        )],
+    );
+    bench.run(
+        "get_payment",
+        &facilitator,
+        &[instruction::get_payment(&payloads[0].payment_auth.nonce)],
+    );
+    // A nonce from before payments were recorded
+    let old_nonce = pda::nonce(&[0xff; 32]).0;
+    let data = [&Nonce::DISCRIMINATOR[..], &bench.env.now().to_le_bytes()].concat();
+    let old_account = Account {
+        lamports: bench.env.svm.minimum_balance_for_rent_exemption(data.len()),
+        data,
+        owner: xusdc_client::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    bench.env.svm.set_account(old_nonce, old_account).unwrap();
+    bench.run(
+        "migrate_nonce",
+        &facilitator,
+        &[instruction::migrate_nonce(&old_nonce)],
+    );
+    // More nonces for the batch to collect
+    for payload in &payloads[1..] {
+        bench.send(
+            &facilitator,
+            &[instruction::settle_payment(&facilitator, payload)],
+        );
+    }
+
+    bench.run(
+        "withdraw",
+        &bob_key,
+        &[instruction::withdraw(&bob_key, &usdc, 100_000)],
+    );
+    bench.run(
+        "request_withdraw",
+        &alice_key,
+        &[instruction::request_withdraw(&alice_key, &usdc, 1_000_000)],
+    );
+    bench.env.warp(DEFAULT_WITHDRAW_QUEUE_DELAY_SECONDS);
+    bench.run(
+        "claim_withdraw",
+        &alice_key,
+        &[instruction::claim_withdraw(&alice_key, &usdc)],
+    );
+    bench.send(
+        &alice_key,
+        &[instruction::request_withdraw(&alice_key, &usdc, 1_000_000)],
+    );
+    bench.run(
+        "cancel_withdraw_request",
+        &alice_key,
+        &[instruction::cancel_withdraw_request(&alice_key)],
+    );
+
+    bench.run(
+        "set_withdraw_limits",
+        &ADMIN_KEY,
+        &[instruction::set_withdraw_limits(
+            &ADMIN_KEY,
+            24 * 60 * 60,
+            u64::MAX,
+            u64::MAX,
+            0,
+        )],
+    );
+    bench.run(
+        "update_metadata",
+        &ADMIN_KEY,
+        &[instruction::update_metadata(
+            &ADMIN_KEY,
+            MetadataField::Uri,
+            "https://xusdc.example/metadata-v2.json",
+        )],
+    );
+    // Left off, since every later transfer would need the hook's accounts
+    bench.run(
+        "set_transfer_hook",
+        &ADMIN_KEY,
+        &[instruction::set_transfer_hook(&ADMIN_KEY, None)],
+    );
+    // A second Token mint, a copy of USDC
+    let second = Collateral {
+        mint: Pubkey::new_unique(),
+        token_program: TOKEN_PROGRAM_ID,
+    };
+    let mint = bench.env.svm.get_account(&USDC_MINT_KEY).unwrap();
+    bench.env.svm.set_account(second.mint, mint).unwrap();
+    bench.run(
+        "add_collateral",
+        &ADMIN_KEY,
+        &[instruction::add_collateral(
+            &ADMIN_KEY,
+            &second,
+            u64::MAX,
+            0,
+        )],
+    );
+    bench.run(
+        "update_collateral",
+        &ADMIN_KEY,
+        &[instruction::update_collateral(
+            &ADMIN_KEY,
+            &second.mint,
+            TEN_USDC,
+            10,
+        )],
+    );
+    bench.run(
+        "attest_reserves",
+        &facilitator,
+        &[instruction::attest_reserves(&[
+            usdc.vault(),
+            second.vault(),
+        ])],
+    );
+
+    // Bob is blocked, frozen and seized from, then unblocked and thawed
+    bench.run(
+        "block_account",
+        &ADMIN_KEY,
+        &[instruction::block_account(&ADMIN_KEY, &bob_key)],
+    );
+    bench.run(
+        "set_frozen",
+        &ADMIN_KEY,
+        &[instruction::set_frozen(&ADMIN_KEY, &bob_key, true)],
+    );
+    bench.run(
+        "seize",
+        &ADMIN_KEY,
+        &[instruction::seize(
+            &ADMIN_KEY, &bob_key, &alice_key, 100_000,
+        )],
+    );
+    bench.send(
+        &ADMIN_KEY,
+        &[instruction::set_frozen(&ADMIN_KEY, &bob_key, false)],
+    );
+    bench.run(
+        "unblock_account",
+        &ADMIN_KEY,
+        &[instruction::unblock_account(&ADMIN_KEY, &bob_key)],
+    );
+
+    bench.env.warp(DEFAULT_TTL.as_secs() as i64 + 1);
+    let gc: Vec<_> = payloads
+        .iter()
+        .map(|payload| instruction::garbage_collect(&pda::nonce(&payload.payment_auth.nonce).0))
+        .collect();
+    bench.run("garbage_collect", &facilitator, &gc[..1]);
+    bench.run("garbage_collect_batch", &facilitator, &gc[1..]);
+    bench.run(
+        "withdraw_rent",
+        &facilitator,
+        &[instruction::withdraw_rent(&facilitator, 10_000_000)],
+    );
+
+    bench.measured
+}
+
+#[test]
+fn test_compute_units_within_baseline() {
+    let measured = measure();
+    if std::env::var_os("XUSDC_CU_BLESS").is_some() {
+        write_baseline(&measured);
+        return;
+    }
+
+    let baseline = read_baseline();
+    let mut failures = Vec::new();
+    println!(
+        "{:<24} {:>10} {:>10} {:>8}",
+        "instruction", "baseline", "measured", "change"
+    );
+    for &(name, units) in &measured {
+        let Some(&expected) = baseline.get(name) else {
+            println!("{name:<24} {:>10} {units:>10} {:>8}", "-", "new");
+            failures.push(format!("{name}: missing from the baseline"));
+            continue;
+        };
+        let change = (units as f64 - expected as f64) / expected.max(1) as f64 * 100.0;
+        println!("{name:<24} {expected:>10} {units:>10} {change:>+7.1}%");
+        if units * 100 > expected * (100 + THRESHOLD_PERCENT) {
+            failures.push(format!(
+                "{name}: {expected} -> {units} units, more than {THRESHOLD_PERCENT}% over"
+            ));
+        }
+    }
+    for name in baseline.keys() {
+        if !measured.iter().any(|(measured, _)| measured == name) {
+            println!("{name:<24} is in the baseline but was not measured");
+            failures.push(format!("{name}: in the baseline but not measured"));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "compute units don't match the baseline, rerun with XUSDC_CU_BLESS=1 if \
+         deliberate:\n{}",
+        failures.join("\n")
+    );
+}
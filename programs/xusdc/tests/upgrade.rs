@@ -0,0 +1,277 @@
+//! Upgrading a deployment initialized before the accounts later instructions rely on,
+//! driven through the client builders
+
+// Results are litesvm's own `TransactionResult`
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::permanent_delegate::PermanentDelegate;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+};
+use anchor_spl::token_2022::spl_token_2022::state::Mint;
+use litesvm::types::TransactionResult;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::TransactionError;
+use xusdc::error::ErrorCode;
+use xusdc::state::{CollateralRegistry, WithdrawLimits};
+use xusdc_client::hook::HOOK_PROGRAM_ID;
+use xusdc_client::instruction::{self, Collateral};
+use xusdc_client::{pda, MetadataField, ADMIN_KEY, USDC_MINT_KEY, XUSDC_MINT_KEY};
+use xusdc_harness::Env;
+
+const TEN_USDC: u64 = 10_000_000;
+
+fn assert_custom_error(result: TransactionResult, code: u32) {
+    match result {
+        Ok(_) => panic!("Expected Custom({code}), but the transaction succeeded"),
+        Err(e) => match e.err {
+            TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+                assert_eq!(actual, code, "{}", e.meta.logs.join("\n"))
+            }
+            err => panic!("Expected Custom({code}), got {err:?}"),
+        },
+    }
+}
+
+fn must(result: TransactionResult) {
+    if let Err(failed) = result {
+        panic!("{:?}\n{}", failed.err, failed.meta.logs.join("\n"));
+    }
+}
+
+fn collateral_registry(env: &Env) -> CollateralRegistry {
+    let account = env.svm.get_account(&pda::collateral_registry().0).unwrap();
+    CollateralRegistry::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// The xUSDC mint as `initialize` created it before freezing, metadata and the transfer
+/// hook: a permanent delegate and no freeze authority
+fn legacy_mint(env: &Env) -> Account {
+    let authority = pda::transfer_authority().0;
+    let len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::PermanentDelegate])
+        .unwrap();
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+    state
+        .init_extension::<PermanentDelegate>(true)
+        .unwrap()
+        .delegate = Some(authority).try_into().unwrap();
+    state.base = Mint {
+        mint_authority: COption::Some(authority),
+        supply: env.xusdc_supply(),
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+    Account {
+        lamports: env.svm.minimum_balance_for_rent_exemption(len),
+        data,
+        owner: spl_token_2022::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+#[test]
+fn test_init_collateral_registry_seeds_outstanding_from_supply() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let alice_key = alice.pubkey();
+    let usdc = Collateral::USDC;
+
+    // As deployed before the registry existed
+    let registry = pda::collateral_registry().0;
+    env.svm.set_account(registry, Account::default()).unwrap();
+    let not_initialized = anchor_lang::error::ErrorCode::AccountNotInitialized.into();
+    assert_custom_error(
+        env.send(
+            &alice_key,
+            &[instruction::withdraw(&alice_key, &usdc, 1_000_000)],
+        ),
+        not_initialized,
+    );
+
+    assert_custom_error(
+        env.send(
+            &alice_key,
+            &[instruction::init_collateral_registry(
+                &alice_key,
+                &USDC_MINT_KEY,
+            )],
+        ),
+        anchor_lang::error::ErrorCode::ConstraintAddress.into(),
+    );
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::init_collateral_registry(
+            &ADMIN_KEY,
+            &USDC_MINT_KEY,
+        )],
+    ));
+    let collaterals = collateral_registry(&env).collaterals;
+    assert_eq!(collaterals.len(), 1);
+    assert_eq!(collaterals[0].mint, USDC_MINT_KEY);
+    assert_eq!(collaterals[0].vault, usdc.vault());
+    assert_eq!(collaterals[0].outstanding, TEN_USDC);
+    assert_eq!(collaterals[0].outstanding, env.xusdc_supply());
+
+    // Everything minted before can be redeemed, and deposits pick up from there
+    must(env.send(
+        &alice_key,
+        &[instruction::withdraw(&alice_key, &usdc, TEN_USDC)],
+    ));
+    env.deposit(&alice_key, 1_000_000);
+    assert_eq!(
+        collateral_registry(&env).collaterals[0].outstanding,
+        1_000_000
+    );
+    must(env.send(&alice_key, &[instruction::attest_reserves(&[usdc.vault()])]));
+
+    // Only once
+    env.warp(1);
+    assert!(env
+        .send(
+            &ADMIN_KEY,
+            &[instruction::init_collateral_registry(
+                &ADMIN_KEY,
+                &USDC_MINT_KEY
+            )],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_set_withdraw_limits_creates_missing_limits() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let alice_key = alice.pubkey();
+    let usdc = Collateral::USDC;
+
+    // As deployed before withdrawals were limited
+    let limits = pda::withdraw_limits().0;
+    env.svm.set_account(limits, Account::default()).unwrap();
+    assert_custom_error(
+        env.send(
+            &alice_key,
+            &[instruction::withdraw(&alice_key, &usdc, 1_000_000)],
+        ),
+        anchor_lang::error::ErrorCode::AccountNotInitialized.into(),
+    );
+
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::set_withdraw_limits(
+            &ADMIN_KEY,
+            60,
+            u64::MAX,
+            2_000_000,
+            60,
+        )],
+    ));
+    let account = env.svm.get_account(&limits).unwrap();
+    let created = WithdrawLimits::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(created.window_seconds, 60);
+    assert_eq!(created.wallet_limit, 2_000_000);
+    assert_eq!(created.window_start, env.now());
+    assert_eq!(created.window_outflow, 0);
+
+    must(env.send(
+        &alice_key,
+        &[instruction::withdraw(&alice_key, &usdc, 1_000_000)],
+    ));
+    assert_eq!(env.usdc_balance(&alice_key), 1_000_000);
+
+    // Later calls update the same account
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::set_withdraw_limits(
+            &ADMIN_KEY,
+            120,
+            u64::MAX,
+            u64::MAX,
+            0,
+        )],
+    ));
+    let account = env.svm.get_account(&limits).unwrap();
+    let updated = WithdrawLimits::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(updated.window_seconds, 120);
+    assert_eq!(updated.window_start, created.window_start);
+    assert_eq!(updated.window_outflow, 1_000_000);
+}
+
+#[test]
+fn test_update_metadata_needs_a_mint_with_metadata() {
+    let mut env = Env::new();
+    let legacy = legacy_mint(&env);
+    env.svm.set_account(XUSDC_MINT_KEY, legacy).unwrap();
+
+    // Token-2022 can't add the metadata pointer to an existing mint
+    assert_custom_error(
+        env.send(
+            &ADMIN_KEY,
+            &[instruction::update_metadata(
+                &ADMIN_KEY,
+                MetadataField::Name,
+                "xUSDC",
+            )],
+        ),
+        ErrorCode::MintMissingExtension.into(),
+    );
+}
+
+#[test]
+fn test_legacy_mint_refuses_what_needs_a_new_mint() {
+    let mut env = Env::new();
+    let alice = env.funded_user(TEN_USDC);
+    let alice_key = alice.pubkey();
+    let bob = env.user();
+    let bob_key = bob.pubkey();
+    let legacy = legacy_mint(&env);
+    env.svm.set_account(XUSDC_MINT_KEY, legacy).unwrap();
+
+    // Token-2022 can't add a freeze authority or the hook extension to an existing mint
+    assert_custom_error(
+        env.send(
+            &ADMIN_KEY,
+            &[instruction::set_frozen(&ADMIN_KEY, &alice_key, true)],
+        ),
+        ErrorCode::MintNotFreezable.into(),
+    );
+    assert_custom_error(
+        env.send(
+            &ADMIN_KEY,
+            &[instruction::set_transfer_hook(
+                &ADMIN_KEY,
+                Some(HOOK_PROGRAM_ID),
+            )],
+        ),
+        ErrorCode::MintMissingExtension.into(),
+    );
+
+    // Payments still work, and the program itself enforces the blocklist
+    env.sign_and_settle(&alice, &bob_key, 1_000_000);
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::block_account(&ADMIN_KEY, &alice_key)],
+    ));
+    let payload = env.sign(&alice, &bob_key, 1_000_000);
+    assert_custom_error(env.settle(&payload), ErrorCode::AccountBlocked.into());
+
+    // Seizing only needs the permanent delegate and the mint authority
+    must(env.send(
+        &ADMIN_KEY,
+        &[instruction::seize(
+            &ADMIN_KEY, &alice_key, &bob_key, 2_000_000,
+        )],
+    ));
+    assert_eq!(env.xusdc_balance(&alice_key), TEN_USDC - 3_000_000);
+    assert_eq!(env.xusdc_balance(&bob_key), 3_000_000);
+    assert_eq!(env.xusdc_supply(), TEN_USDC);
+}